@@ -0,0 +1,323 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use axum::extract::{Query, State};
+use axum::Json;
+use chrono::{DateTime, Utc};
+use indexmap::IndexSet;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::AppState;
+use crate::game::state::{PlayerMove, TurnRecord};
+use crate::game::team::PlayerTeam;
+use crate::persistence::AnalyticsUpdateSender;
+
+// Everything analytics need to know about a completed game
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GameRecord {
+    pub id: Uuid,
+    pub map_name: String,
+    pub finished_at: DateTime<Utc>,
+    pub score: HashMap<PlayerTeam, usize>,
+    pub decks: HashMap<PlayerTeam, IndexSet<String>>,
+    pub turns: Vec<HashMap<PlayerTeam, TurnRecord>>,
+}
+
+impl GameRecord {
+    pub fn winner(&self) -> Option<PlayerTeam> {
+        let score = |team| self.score.get(&team).copied().unwrap_or(0);
+        match score(PlayerTeam::Alpha).cmp(&score(PlayerTeam::Bravo)) {
+            Ordering::Greater => Some(PlayerTeam::Alpha),
+            Ordering::Less => Some(PlayerTeam::Bravo),
+            Ordering::Equal => None,
+        }
+    }
+}
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+struct CardCounters {
+    // A card in both decks of a game counts twice
+    games_in_deck: usize,
+    wins_in_deck: usize,
+    times_placed: usize,
+    times_passed: usize,
+    times_special: usize,
+    squares_claimed: usize,
+}
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+struct MapCounters {
+    games: usize,
+    wins: HashMap<PlayerTeam, usize>,
+    draws: usize,
+    total_score: HashMap<PlayerTeam, usize>,
+    cards: HashMap<String, CardCounters>,
+}
+
+// Only these aggregates are kept in the persistent store, so it doesn't grow with every game played
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct AnalyticsCounters {
+    cards: HashMap<String, CardCounters>,
+    maps: HashMap<String, MapCounters>,
+}
+
+impl AnalyticsCounters {
+    pub fn add(&mut self, record: &GameRecord) {
+        let winner = record.winner();
+        let map = self.maps.entry(record.map_name.clone()).or_default();
+        map.games += 1;
+        match &winner {
+            Some(team) => *map.wins.entry(team.clone()).or_insert(0) += 1,
+            None => map.draws += 1,
+        }
+        for (team, score) in &record.score {
+            *map.total_score.entry(team.clone()).or_insert(0) += score;
+        }
+
+        for card_counters in [&mut self.cards, &mut map.cards] {
+            for (team, deck) in &record.decks {
+                for card in deck {
+                    let counters = card_counters.entry(card.clone()).or_default();
+                    counters.games_in_deck += 1;
+                    if winner.as_ref() == Some(team) {
+                        counters.wins_in_deck += 1;
+                    }
+                }
+            }
+
+            for turn in record.turns.iter().flat_map(|turn| turn.values()) {
+                let counters = card_counters.entry(turn.player_move.card_name().to_string()).or_default();
+                match turn.player_move {
+                    PlayerMove::PlaceCard { special, .. } => {
+                        counters.times_placed += 1;
+                        counters.squares_claimed += turn.claimed_squares;
+                        if special {
+                            counters.times_special += 1;
+                        }
+                    }
+                    PlayerMove::Pass { .. } => counters.times_passed += 1,
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CardStatistics {
+    pub name: String,
+    pub games_in_deck: usize,
+    pub wins_in_deck: usize,
+    // Draws don't count as wins
+    pub win_rate: f64,
+    pub times_placed: usize,
+    pub times_passed: usize,
+    // The share of placements that used the card's special attack
+    pub special_usage_rate: f64,
+    pub average_squares_claimed: f64,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MapStatistics {
+    pub name: String,
+    pub games: usize,
+    pub wins: HashMap<PlayerTeam, usize>,
+    pub draws: usize,
+    pub average_score: HashMap<PlayerTeam, f64>,
+}
+
+fn ratio(count: usize, total: usize) -> f64 {
+    if total == 0 { 0.0 } else { count as f64 / total as f64 }
+}
+
+fn card_statistics(cards: &HashMap<String, CardCounters>) -> Vec<CardStatistics> {
+    let mut statistics: Vec<CardStatistics> = cards.iter()
+        .map(|(name, counters)| CardStatistics {
+            name: name.clone(),
+            games_in_deck: counters.games_in_deck,
+            wins_in_deck: counters.wins_in_deck,
+            win_rate: ratio(counters.wins_in_deck, counters.games_in_deck),
+            times_placed: counters.times_placed,
+            times_passed: counters.times_passed,
+            special_usage_rate: ratio(counters.times_special, counters.times_placed),
+            average_squares_claimed: ratio(counters.squares_claimed, counters.times_placed),
+        })
+        .collect();
+    statistics.sort_by(|a, b| a.name.cmp(&b.name));
+    statistics
+}
+
+// Aggregates the records of completed games into card and map statistics
+#[derive(Default)]
+pub struct Analytics {
+    counters: Mutex<AnalyticsCounters>,
+    update_sender: Mutex<Option<AnalyticsUpdateSender>>,
+}
+
+impl Analytics {
+    // Starts from counters that were saved before, the counters are sent to the given channel to be saved after
+    // every recorded game
+    pub fn new(counters: AnalyticsCounters, update_sender: Option<AnalyticsUpdateSender>) -> Self {
+        Self {
+            counters: Mutex::new(counters),
+            update_sender: Mutex::new(update_sender),
+        }
+    }
+
+    pub fn record(&self, record: GameRecord) {
+        let mut counters = self.counters.lock().unwrap();
+        counters.add(&record);
+        if let Some(update_sender) = self.update_sender.lock().unwrap().as_ref() {
+            update_sender.send(Box::new(counters.clone())).ok();
+        }
+    }
+
+    // Closes the channel to the persistent store, letting its writer finish once it has saved everything
+    pub fn shut_down(&self) {
+        self.update_sender.lock().unwrap().take();
+    }
+
+    pub fn cards(&self, map: Option<&str>) -> Vec<CardStatistics> {
+        let counters = self.counters.lock().unwrap();
+        match map {
+            Some(map) => counters.maps.get(map).map(|map| card_statistics(&map.cards)).unwrap_or_default(),
+            None => card_statistics(&counters.cards),
+        }
+    }
+
+    pub fn maps(&self) -> Vec<MapStatistics> {
+        let counters = self.counters.lock().unwrap();
+        let mut statistics: Vec<MapStatistics> = counters.maps.iter()
+            .map(|(name, counters)| MapStatistics {
+                name: name.clone(),
+                games: counters.games,
+                wins: counters.wins.clone(),
+                draws: counters.draws,
+                average_score: counters.total_score.iter()
+                    .map(|(team, score)| (team.clone(), ratio(*score, counters.games)))
+                    .collect(),
+            })
+            .collect();
+        statistics.sort_by(|a, b| a.name.cmp(&b.name));
+        statistics
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CardStatisticsParams {
+    map: Option<String>,
+}
+
+pub async fn get_card_statistics(State(state): State<Arc<AppState>>, Query(params): Query<CardStatisticsParams>) -> Json<Vec<CardStatistics>> {
+    Json(state.analytics.cards(params.map.as_deref()))
+}
+
+pub async fn get_map_statistics(State(state): State<Arc<AppState>>) -> Json<Vec<MapStatistics>> {
+    Json(state.analytics.maps())
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc;
+    use crate::game::state::CardRotation;
+    use super::*;
+
+    fn place(card_name: &str, special: bool, claimed_squares: usize) -> TurnRecord {
+        TurnRecord {
+            player_move: PlayerMove::PlaceCard {
+                card_name: card_name.to_string(),
+                position: (0, 0).into(),
+                rotation: CardRotation::Deg0,
+                special,
+            },
+            claimed_squares,
+        }
+    }
+
+    fn pass(card_name: &str) -> TurnRecord {
+        TurnRecord { player_move: PlayerMove::Pass { card_name: card_name.to_string() }, claimed_squares: 0 }
+    }
+
+    fn record(map_name: &str, alpha_score: usize, bravo_score: usize) -> GameRecord {
+        GameRecord {
+            id: Uuid::new_v4(),
+            map_name: map_name.to_string(),
+            finished_at: Utc::now(),
+            score: HashMap::from([(PlayerTeam::Alpha, alpha_score), (PlayerTeam::Bravo, bravo_score)]),
+            decks: HashMap::from([
+                (PlayerTeam::Alpha, IndexSet::from(["a".to_string(), "shared".to_string()])),
+                (PlayerTeam::Bravo, IndexSet::from(["b".to_string(), "shared".to_string()])),
+            ]),
+            turns: vec!(
+                HashMap::from([(PlayerTeam::Alpha, place("a", false, 4)), (PlayerTeam::Bravo, place("b", false, 3))]),
+                HashMap::from([(PlayerTeam::Alpha, place("shared", true, 6)), (PlayerTeam::Bravo, pass("shared"))]),
+            ),
+        }
+    }
+
+    fn find<'a>(statistics: &'a [CardStatistics], name: &str) -> &'a CardStatistics {
+        statistics.iter().find(|card| card.name == name).unwrap()
+    }
+
+    #[test]
+    fn winner() {
+        assert_eq!(Some(PlayerTeam::Alpha), record("Map", 10, 8).winner());
+        assert_eq!(Some(PlayerTeam::Bravo), record("Map", 8, 10).winner());
+        assert_eq!(None, record("Map", 9, 9).winner());
+    }
+
+    #[test]
+    fn card_statistics() {
+        let mut counters = AnalyticsCounters::default();
+        counters.add(&record("Main Street", 10, 8));
+        let analytics = Analytics::new(counters, None);
+        analytics.record(record("Thunder Point", 8, 10));
+
+        let cards = analytics.cards(None);
+
+        assert_eq!(vec!("a", "b", "shared"), cards.iter().map(|card| card.name.as_str()).collect::<Vec<_>>());
+        let a = find(&cards, "a");
+        assert_eq!((2, 1, 0.5), (a.games_in_deck, a.wins_in_deck, a.win_rate));
+        assert_eq!((2, 0, 0.0, 4.0), (a.times_placed, a.times_passed, a.special_usage_rate, a.average_squares_claimed));
+        let shared = find(&cards, "shared");
+        assert_eq!((4, 2), (shared.games_in_deck, shared.wins_in_deck));
+        assert_eq!((2, 2, 1.0, 6.0), (shared.times_placed, shared.times_passed, shared.special_usage_rate, shared.average_squares_claimed));
+
+        let main_street = analytics.cards(Some("Main Street"));
+        assert_eq!((1, 1), (find(&main_street, "a").games_in_deck, find(&main_street, "a").wins_in_deck));
+        assert!(analytics.cards(Some("Unknown")).is_empty());
+    }
+
+    #[test]
+    fn map_statistics() {
+        let analytics = Analytics::default();
+        analytics.record(record("Main Street", 10, 8));
+        analytics.record(record("Main Street", 9, 9));
+        analytics.record(record("Main Street", 5, 12));
+
+        let maps = analytics.maps();
+
+        assert_eq!(1, maps.len());
+        assert_eq!(3, maps[0].games);
+        assert_eq!(Some(&1), maps[0].wins.get(&PlayerTeam::Alpha));
+        assert_eq!(Some(&1), maps[0].wins.get(&PlayerTeam::Bravo));
+        assert_eq!(1, maps[0].draws);
+        assert_eq!(Some(&8.0), maps[0].average_score.get(&PlayerTeam::Alpha));
+        assert_eq!(Some(&(29.0 / 3.0)), maps[0].average_score.get(&PlayerTeam::Bravo));
+    }
+
+    #[test]
+    fn saves_counters() {
+        let (update_sender, mut update_receiver) = mpsc::unbounded_channel();
+        let analytics = Analytics::new(AnalyticsCounters::default(), Some(update_sender));
+
+        analytics.record(record("Main Street", 10, 8));
+        analytics.shut_down();
+
+        let saved = Analytics::new(*update_receiver.try_recv().unwrap(), None);
+        assert_eq!(1, saved.maps()[0].games);
+        assert!(update_receiver.try_recv().is_err());
+    }
+}
@@ -0,0 +1,123 @@
+pub mod admin;
+pub mod forward;
+pub mod openapi;
+
+use std::sync::Arc;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::Json;
+use axum::response::{IntoResponse, Response};
+use schemars::JsonSchema;
+use serde::Serialize;
+use crate::AppState;
+use crate::game::card::Card;
+use crate::game::endgame::EndgameError;
+use crate::game::puzzle::PuzzleError;
+use crate::game::map::TableturfMap;
+use crate::persistence::registry;
+use crate::socket::proxy;
+use crate::socket::room_store::RoomSummary;
+
+#[derive(Serialize, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(tag = "code", content = "detail")]
+pub enum ApiError {
+    RoomNotFound(String),
+    RoomNotStarted(String),
+    InvalidAnnouncement,
+    Unauthorized,
+    PuzzleNotFound(String),
+    InvalidSolution(PuzzleError),
+    EndgameNotSolvable(EndgameError),
+    RoomHostUnavailable(String),
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            ApiError::RoomNotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::RoomNotStarted(_) => StatusCode::CONFLICT,
+            ApiError::InvalidAnnouncement => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ApiError::PuzzleNotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::InvalidSolution(_) => StatusCode::BAD_REQUEST,
+            ApiError::EndgameNotSolvable(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::RoomHostUnavailable(_) => StatusCode::BAD_GATEWAY,
+        };
+
+        (status, Json(self)).into_response()
+    }
+}
+
+// With several instances, the rooms of the others are asked for as well, unless the request comes from one of them
+pub async fn list_rooms(State(state): State<Arc<AppState>>, headers: HeaderMap) -> Json<Vec<RoomSummary>> {
+    let (mut rooms, registry) = {
+        let room_store = state.read_room_store().await;
+        (room_store.public_rooms(), room_store.registry())
+    };
+    let (Some(registry), Some(relay_secret)) = (registry, state.relay_secret.as_deref()) else {
+        return Json(rooms);
+    };
+    if proxy::is_relayed(&headers, Some(relay_secret)) {
+        return Json(rooms);
+    }
+
+    let hosts = registry.hosts().await.unwrap_or_else(|err| {
+        log::error!("Failed to list the other instances: {err}");
+        Vec::new()
+    });
+    let requests = hosts.iter()
+        .filter(|host| host.as_str() != registry.address())
+        .map(|host| async move { (host, forward::fetch::<Vec<RoomSummary>>(host, "/rooms", relay_secret).await) });
+    for (host, result) in futures::future::join_all(requests).await {
+        match result {
+            Ok(remote_rooms) => rooms.extend(remote_rooms.unwrap_or_default()),
+            Err(err) => log::warn!("Failed to list the rooms of {host}: {err}"),
+        }
+    }
+
+    rooms.sort_by(|a, b| a.code.cmp(&b.code));
+    Json(rooms)
+}
+
+// Private rooms can be looked up as well, as their code has to be known to find them. Rooms hosted by another instance
+// are looked up there.
+pub async fn get_room(State(state): State<Arc<AppState>>, headers: HeaderMap, Path(code): Path<String>) -> Result<Json<RoomSummary>, ApiError> {
+    let code = code.to_uppercase();
+    let registry = {
+        let room_store = state.read_room_store().await;
+        if let Some(room) = room_store.get(&code) {
+            return Ok(Json(room.summary()));
+        }
+        room_store.registry_for(&code)
+    };
+    let (Some(registry), Some(relay_secret)) = (registry, state.relay_secret.as_deref()) else {
+        return Err(ApiError::RoomNotFound(code));
+    };
+    if proxy::is_relayed(&headers, Some(relay_secret)) {
+        return Err(ApiError::RoomNotFound(code));
+    }
+    let Some(host) = registry::remote_host(registry.as_ref(), &code).await else {
+        return Err(ApiError::RoomNotFound(code));
+    };
+
+    match forward::fetch(&host, &format!("/rooms/{code}"), relay_secret).await {
+        Ok(Some(room)) => Ok(Json(room)),
+        Ok(None) => Err(ApiError::RoomNotFound(code)),
+        Err(err) => {
+            log::warn!("Failed to look up room {code} on {host}: {err}");
+            Err(ApiError::RoomHostUnavailable(code))
+        }
+    }
+}
+
+pub async fn list_cards(State(state): State<Arc<AppState>>) -> Json<Vec<Card>> {
+    Json(state.card_provider.get_all())
+}
+
+pub async fn list_maps(State(state): State<Arc<AppState>>) -> Json<Vec<TableturfMap>> {
+    Json(state.map_provider.get_all())
+}
+
+pub async fn get_openapi() -> Json<serde_json::Value> {
+    Json(openapi::document())
+}
@@ -0,0 +1,119 @@
+use std::sync::Arc;
+use axum::async_trait;
+use axum::extract::{FromRequestParts, Path, State};
+use axum::http::header;
+use axum::http::request::Parts;
+use axum::Json;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use crate::AppState;
+use crate::api::ApiError;
+use crate::game::endgame::{self, DEFAULT_MAX_NODES, EndgameSolution};
+use crate::game::state::GameSnapshot;
+use crate::socket::chat::MAX_MESSAGE_LENGTH;
+use crate::socket::close_code::SocketCloseCode;
+use crate::socket::messages::RoomEvent;
+use crate::socket::room_store::AdminRoomSummary;
+
+// Extracting this rejects requests that don't carry the configured admin token. Without an `admin` section in the
+// configuration, every request is rejected.
+pub struct AdminAuth;
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for AdminAuth {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<AppState>) -> Result<Self, Self::Rejection> {
+        let token = parts.headers.get(header::AUTHORIZATION)
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| header.strip_prefix("Bearer "));
+
+        match (token, &state.admin_token) {
+            (Some(token), Some(admin_token)) if tokens_match(token, admin_token) => Ok(AdminAuth),
+            _ => Err(ApiError::Unauthorized),
+        }
+    }
+}
+
+// Compares every byte, so that the time taken does not reveal how much of the token was guessed correctly
+pub fn tokens_match(token: &str, admin_token: &str) -> bool {
+    token.len() == admin_token.len()
+        && token.bytes().zip(admin_token.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct Announcement {
+    pub message: String,
+}
+
+// Lists every room hosted by this instance, including private ones
+pub async fn list_rooms(_: AdminAuth, State(state): State<Arc<AppState>>) -> Json<Vec<AdminRoomSummary>> {
+    Json(state.read_room_store().await.admin_rooms())
+}
+
+pub async fn get_game(_: AdminAuth, State(state): State<Arc<AppState>>, Path(code): Path<String>) -> Result<Json<GameSnapshot>, ApiError> {
+    let code = code.to_uppercase();
+    let room_store = state.read_room_store().await;
+    let room = room_store.get(&code).ok_or_else(|| ApiError::RoomNotFound(code.clone()))?;
+    room.game_state.as_ref()
+        .map(|game| Json(game.snapshot()))
+        .ok_or(ApiError::RoomNotStarted(code))
+}
+
+// Solves the rest of a room's game once only a few turns are left, e.g. to analyse a reported game. The search runs
+// on a clone of the game, so the room isn't locked meanwhile.
+pub async fn get_endgame(_: AdminAuth, State(state): State<Arc<AppState>>, Path(code): Path<String>) -> Result<Json<EndgameSolution>, ApiError> {
+    let code = code.to_uppercase();
+    let game = {
+        let room_store = state.read_room_store().await;
+        let room = room_store.get(&code).ok_or_else(|| ApiError::RoomNotFound(code.clone()))?;
+        room.endgame_state().ok_or(ApiError::RoomNotStarted(code))?
+    };
+
+    tokio::task::spawn_blocking(move || endgame::solve(&game, DEFAULT_MAX_NODES)).await
+        .expect("The endgame solver panicked")
+        .map(Json)
+        .map_err(ApiError::EndgameNotSolvable)
+}
+
+pub async fn end_game(_: AdminAuth, State(state): State<Arc<AppState>>, Path(code): Path<String>) -> Result<(), ApiError> {
+    let code = code.to_uppercase();
+    let mut room_store = state.write_room_store().await;
+    let room = room_store.get_mut(&code).ok_or_else(|| ApiError::RoomNotFound(code.clone()))?;
+    room.end_game().map_err(|_| ApiError::RoomNotStarted(code.clone()))?;
+
+    log::info!("Game in room {code} was ended by an administrator");
+    room_store.persist(&code);
+    Ok(())
+}
+
+pub async fn close_room(_: AdminAuth, State(state): State<Arc<AppState>>, Path(code): Path<String>) -> Result<(), ApiError> {
+    let code = code.to_uppercase();
+    let room = state.write_room_store().await.close(&code, SocketCloseCode::RoomClosed)
+        .ok_or_else(|| ApiError::RoomNotFound(code.clone()))?;
+
+    log::info!("Room {code} was closed by an administrator, disconnecting {} user(s)", room.users.len());
+    Ok(())
+}
+
+pub async fn announce(_: AdminAuth, State(state): State<Arc<AppState>>, Json(announcement): Json<Announcement>) -> Result<(), ApiError> {
+    let message = announcement.message.trim();
+    if message.is_empty() || message.chars().count() > MAX_MESSAGE_LENGTH {
+        return Err(ApiError::InvalidAnnouncement);
+    }
+
+    log::info!("Broadcasting announcement: {message}");
+    state.read_room_store().await.notify_all(RoomEvent::Announcement(message.to_string()));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn tokens_match() {
+        assert!(super::tokens_match("secret", "secret"));
+        assert!(!super::tokens_match("secreT", "secret"));
+        assert!(!super::tokens_match("secret2", "secret"));
+        assert!(!super::tokens_match("", "secret"));
+    }
+}
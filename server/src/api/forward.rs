@@ -0,0 +1,64 @@
+use std::time::Duration;
+use hyper::{Body, Client, Request, StatusCode};
+use serde::de::DeserializeOwned;
+use url::Url;
+use crate::socket::proxy::RELAY_SECRET_HEADER;
+
+// Other instances that take longer than this are left out of the response
+const FORWARD_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Asks the instance at the given address for one of the public room endpoints. The request carries the cluster's
+// secret, so that the instance answers with its own rooms instead of asking around itself. Returns None if the
+// instance does not know the requested room.
+pub async fn fetch<T: DeserializeOwned>(host: &str, path: &str, relay_secret: &str) -> Result<Option<T>, String> {
+    // Instances are addressed by the URL their WebSocket is reachable at
+    let mut url = Url::parse(host).map_err(|err| format!("Invalid host address {host}: {err}"))?;
+    let scheme = if url.scheme() == "wss" { "https" } else { "http" };
+    url.set_scheme(scheme).map_err(|_| format!("Invalid host address {host}"))?;
+    let url = url.join(path).map_err(|err| format!("Invalid path {path}: {err}"))?;
+
+    let request = Request::get(url.as_str())
+        .header(RELAY_SECRET_HEADER, relay_secret)
+        .body(Body::empty())
+        .map_err(|err| err.to_string())?;
+    let response = tokio::time::timeout(FORWARD_TIMEOUT, Client::new().request(request)).await
+        .map_err(|_| format!("{host} did not respond in time"))?
+        .map_err(|err| err.to_string())?;
+
+    match response.status() {
+        StatusCode::NOT_FOUND => Ok(None),
+        status if status.is_success() => {
+            let body = hyper::body::to_bytes(response.into_body()).await.map_err(|err| err.to_string())?;
+            serde_json::from_slice(&body).map(Some).map_err(|err| err.to_string())
+        }
+        status => Err(format!("{host} responded with {status}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderMap;
+    use axum::Json;
+    use axum::Router;
+    use axum::routing::get;
+    use super::*;
+
+    async fn start_host() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let router = Router::new().route("/rooms", get(|headers: HeaderMap| async move {
+            Json(headers.get(RELAY_SECRET_HEADER).and_then(|header| header.to_str().ok()).map(str::to_string))
+        }));
+
+        tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(router.into_make_service()));
+        format!("ws://{address}")
+    }
+
+    #[tokio::test]
+    async fn fetches_from_host_with_secret() {
+        let host = start_host().await;
+
+        assert_eq!(Ok(Some(Some("secret".to_string()))), fetch::<Option<String>>(&host, "/rooms", "secret").await);
+        assert_eq!(Ok(None), fetch::<Option<String>>(&host, "/rooms/ABCD", "secret").await);
+    }
+}
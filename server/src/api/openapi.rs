@@ -0,0 +1,347 @@
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::JsonSchema;
+use serde_json::{json, Value};
+use crate::analytics::{CardStatistics, MapStatistics};
+use crate::api::ApiError;
+use crate::api::admin::Announcement;
+use crate::game::card::Card;
+use crate::game::endgame::EndgameSolution;
+use crate::game::map::TableturfMap;
+use crate::game::puzzle::PuzzleOutcome;
+use crate::game::state::PlayerMove;
+use crate::puzzles::{PuzzleEntry, PuzzleSummary};
+use crate::socket::protocol::PROTOCOL_VERSION;
+use crate::socket::room_store::{AdminRoomSummary, RoomSummary};
+use crate::tournament::{AdminTournamentSummary, TournamentError, TournamentSettings, TournamentSummary};
+
+fn json_content<T: JsonSchema>(generator: &mut SchemaGenerator, description: &str) -> Value {
+    json!({
+        "description": description,
+        "content": {
+            "application/json": {
+                "schema": generator.subschema_for::<T>(),
+            },
+        },
+    })
+}
+
+fn path_parameter(name: &str, description: &str) -> Value {
+    json!({ "name": name, "in": "path", "required": true, "description": description, "schema": { "type": "string" } })
+}
+
+fn query_parameter(name: &str, description: &str, required: bool) -> Value {
+    json!({ "name": name, "in": "query", "required": required, "description": description, "schema": { "type": "string" } })
+}
+
+// The OpenAPI description of the HTTP endpoints, served at /openapi.json
+pub fn document() -> Value {
+    let mut generator = SchemaSettings::openapi3().into_generator();
+
+    let paths = json!({
+        "/rooms": {
+            "get": {
+                "summary": "List the public rooms of all instances",
+                "responses": {
+                    "200": json_content::<Vec<RoomSummary>>(&mut generator, "The public rooms"),
+                },
+            },
+        },
+        "/rooms/{code}": {
+            "get": {
+                "summary": "Get a room by its code",
+                "parameters": [path_parameter("code", "The room code")],
+                "responses": {
+                    "200": json_content::<RoomSummary>(&mut generator, "The room"),
+                    "404": json_content::<ApiError>(&mut generator, "The room does not exist"),
+                    "502": json_content::<ApiError>(&mut generator, "The instance hosting the room could not be reached"),
+                },
+            },
+        },
+        "/cards": {
+            "get": {
+                "summary": "List all cards",
+                "responses": {
+                    "200": json_content::<Vec<Card>>(&mut generator, "All cards, sorted by number"),
+                },
+            },
+        },
+        "/maps": {
+            "get": {
+                "summary": "List all maps",
+                "responses": {
+                    "200": json_content::<Vec<TableturfMap>>(&mut generator, "All maps, sorted by name"),
+                },
+            },
+        },
+        "/analytics/cards": {
+            "get": {
+                "summary": "Statistics of every card played in completed games",
+                "parameters": [query_parameter("map", "Only include games played on this map", false)],
+                "responses": {
+                    "200": json_content::<Vec<CardStatistics>>(&mut generator, "The card statistics, sorted by name"),
+                },
+            },
+        },
+        "/analytics/maps": {
+            "get": {
+                "summary": "Statistics of every map completed games were played on",
+                "responses": {
+                    "200": json_content::<Vec<MapStatistics>>(&mut generator, "The map statistics, sorted by name"),
+                },
+            },
+        },
+        "/tournaments": {
+            "post": {
+                "summary": "Create a tournament",
+                "security": [{ "adminToken": [] }],
+                "requestBody": {
+                    "required": true,
+                    "content": {
+                        "application/json": {
+                            "schema": generator.subschema_for::<TournamentSettings>(),
+                        },
+                    },
+                },
+                "responses": {
+                    "200": json_content::<AdminTournamentSummary>(&mut generator, "The created tournament with the players' tokens"),
+                    "400": json_content::<TournamentError>(&mut generator, "The settings are invalid"),
+                    "401": json_content::<ApiError>(&mut generator, "The admin token is missing or incorrect"),
+                },
+            },
+        },
+        "/tournaments/{id}": {
+            "get": {
+                "summary": "Get a tournament, without the room codes of its matches",
+                "parameters": [path_parameter("id", "The tournament ID")],
+                "responses": {
+                    "200": json_content::<TournamentSummary>(&mut generator, "The tournament"),
+                    "404": json_content::<TournamentError>(&mut generator, "The tournament does not exist"),
+                },
+            },
+        },
+        "/tournaments/{id}/start": {
+            "post": {
+                "summary": "Start a tournament and create the rooms of its first round",
+                "security": [{ "adminToken": [] }],
+                "parameters": [path_parameter("id", "The tournament ID")],
+                "responses": {
+                    "200": json_content::<AdminTournamentSummary>(&mut generator, "The started tournament with its room codes and the players' tokens"),
+                    "401": json_content::<ApiError>(&mut generator, "The admin token is missing or incorrect"),
+                    "404": json_content::<TournamentError>(&mut generator, "The tournament does not exist"),
+                    "409": json_content::<TournamentError>(&mut generator, "The tournament has already started"),
+                },
+            },
+        },
+        "/puzzles": {
+            "get": {
+                "summary": "List the puzzles in the configured directory",
+                "responses": {
+                    "200": json_content::<Vec<PuzzleSummary>>(&mut generator, "The puzzles, sorted by ID"),
+                },
+            },
+        },
+        "/puzzles/{id}": {
+            "get": {
+                "summary": "Get a puzzle, `daily` returns the one named after the current UTC date",
+                "parameters": [path_parameter("id", "The puzzle ID")],
+                "responses": {
+                    "200": json_content::<PuzzleEntry>(&mut generator, "The puzzle"),
+                    "404": json_content::<ApiError>(&mut generator, "The puzzle does not exist"),
+                },
+            },
+        },
+        "/puzzles/{id}/solutions": {
+            "post": {
+                "summary": "Check whether the player's moves solve a puzzle",
+                "parameters": [path_parameter("id", "The puzzle ID")],
+                "requestBody": {
+                    "required": true,
+                    "content": {
+                        "application/json": {
+                            "schema": generator.subschema_for::<Vec<PlayerMove>>(),
+                        },
+                    },
+                },
+                "responses": {
+                    "200": json_content::<PuzzleOutcome>(&mut generator, "The final score and whether the objective was reached"),
+                    "400": json_content::<ApiError>(&mut generator, "A move is invalid or the number of moves is wrong"),
+                    "404": json_content::<ApiError>(&mut generator, "The puzzle does not exist"),
+                },
+            },
+        },
+        "/healthz": {
+            "get": {
+                "summary": "Check whether the server is able to handle requests",
+                "responses": {
+                    "200": { "description": "The server is healthy" },
+                    "503": { "description": "The room store could not be locked in time" },
+                },
+            },
+        },
+        "/metrics": {
+            "get": {
+                "summary": "Metrics in the Prometheus text format",
+                "responses": {
+                    "200": { "description": "The metrics", "content": { "text/plain": { "schema": { "type": "string" } } } },
+                },
+            },
+        },
+        "/admin/rooms": {
+            "get": {
+                "summary": "List all rooms hosted by this instance with their users and game progress",
+                "security": [{ "adminToken": [] }],
+                "responses": {
+                    "200": json_content::<Vec<AdminRoomSummary>>(&mut generator, "All rooms"),
+                    "401": json_content::<ApiError>(&mut generator, "The admin token is missing or incorrect"),
+                },
+            },
+        },
+        "/admin/rooms/{code}": {
+            "delete": {
+                "summary": "Close a room, disconnecting its users",
+                "security": [{ "adminToken": [] }],
+                "parameters": [path_parameter("code", "The room code")],
+                "responses": {
+                    "200": { "description": "The room was closed" },
+                    "401": json_content::<ApiError>(&mut generator, "The admin token is missing or incorrect"),
+                    "404": json_content::<ApiError>(&mut generator, "The room does not exist"),
+                },
+            },
+        },
+        "/admin/rooms/{code}/game": {
+            "get": {
+                "summary": "Dump the full state of a room's game, including both hands and decks",
+                "security": [{ "adminToken": [] }],
+                "parameters": [path_parameter("code", "The room code")],
+                "responses": {
+                    "200": { "description": "The game state", "content": { "application/json": { "schema": { "type": "object" } } } },
+                    "401": json_content::<ApiError>(&mut generator, "The admin token is missing or incorrect"),
+                    "404": json_content::<ApiError>(&mut generator, "The room does not exist"),
+                    "409": json_content::<ApiError>(&mut generator, "No game was started in the room"),
+                },
+            },
+        },
+        "/admin/rooms/{code}/endgame": {
+            "get": {
+                "summary": "Solve the rest of a room's game once only a few turns are left, or of its last game from that point",
+                "security": [{ "adminToken": [] }],
+                "parameters": [path_parameter("code", "The room code")],
+                "responses": {
+                    "200": json_content::<EndgameSolution>(&mut generator, "The optimal strategies of both teams for the current turn"),
+                    "401": json_content::<ApiError>(&mut generator, "The admin token is missing or incorrect"),
+                    "404": json_content::<ApiError>(&mut generator, "The room does not exist"),
+                    "409": json_content::<ApiError>(&mut generator, "No game was started in the room"),
+                    "422": json_content::<ApiError>(&mut generator, "Too many turns are left, or the search took too long"),
+                },
+            },
+        },
+        "/admin/rooms/{code}/end-game": {
+            "post": {
+                "summary": "End a room's game without recording a result and return its users to the room",
+                "security": [{ "adminToken": [] }],
+                "parameters": [path_parameter("code", "The room code")],
+                "responses": {
+                    "200": { "description": "The game was ended" },
+                    "401": json_content::<ApiError>(&mut generator, "The admin token is missing or incorrect"),
+                    "404": json_content::<ApiError>(&mut generator, "The room does not exist"),
+                    "409": json_content::<ApiError>(&mut generator, "No game was started in the room"),
+                },
+            },
+        },
+        "/admin/announcements": {
+            "post": {
+                "summary": "Send an announcement to every room hosted by this instance",
+                "security": [{ "adminToken": [] }],
+                "requestBody": {
+                    "required": true,
+                    "content": {
+                        "application/json": {
+                            "schema": generator.subschema_for::<Announcement>(),
+                        },
+                    },
+                },
+                "responses": {
+                    "200": { "description": "The announcement was sent" },
+                    "400": json_content::<ApiError>(&mut generator, "The message is empty or too long"),
+                    "401": json_content::<ApiError>(&mut generator, "The admin token is missing or incorrect"),
+                },
+            },
+        },
+        "/admin/tournaments/{id}": {
+            "get": {
+                "summary": "Get a tournament with the room codes of its matches and the players' tokens",
+                "security": [{ "adminToken": [] }],
+                "parameters": [path_parameter("id", "The tournament ID")],
+                "responses": {
+                    "200": json_content::<AdminTournamentSummary>(&mut generator, "The tournament"),
+                    "401": json_content::<ApiError>(&mut generator, "The admin token is missing or incorrect"),
+                    "404": json_content::<TournamentError>(&mut generator, "The tournament does not exist"),
+                },
+            },
+        },
+        "/ws": {
+            "get": {
+                "summary": "Join or create a room over a WebSocket connection",
+                "description": format!("The messages exchanged over the connection are described in \
+                    web/types/socket/generated. Clients must use protocol version {PROTOCOL_VERSION}."),
+                "parameters": [
+                    query_parameter("username", "The name to join with", true),
+                    query_parameter("version", "The protocol version the client was built against", true),
+                    query_parameter("room", "The code of the room to join, a new room is created without it", false),
+                    query_parameter("password", "The room's password, if it has one", false),
+                    query_parameter("token", "The reconnect token of an earlier connection, or a tournament player's token", false),
+                    query_parameter("format", "The format events are sent in, either json (default) or msgpack", false),
+                ],
+                "responses": {
+                    "101": { "description": "The connection was upgraded to a WebSocket" },
+                },
+            },
+        },
+    });
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Tableturfer",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": {
+            "schemas": generator.definitions(),
+            "securitySchemes": {
+                "adminToken": { "type": "http", "scheme": "bearer", "description": "The token configured in admin.token" },
+            },
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect_references(value: &Value, references: &mut Vec<String>) {
+        match value {
+            Value::Object(object) => {
+                if let Some(Value::String(reference)) = object.get("$ref") {
+                    references.push(reference.clone());
+                }
+                object.values().for_each(|value| collect_references(value, references));
+            }
+            Value::Array(array) => array.iter().for_each(|value| collect_references(value, references)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn references_resolve() {
+        let document = document();
+        let mut references = Vec::new();
+        collect_references(&document, &mut references);
+
+        assert!(!references.is_empty());
+        for reference in references {
+            let name = reference.strip_prefix("#/components/schemas/").unwrap();
+            assert!(document["components"]["schemas"].get(name).is_some(), "{reference} does not resolve");
+        }
+    }
+}
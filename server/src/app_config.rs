@@ -1,11 +1,28 @@
 use config::Config;
 
+// Persistence is opt-in, so that a `redis` section alone doesn't require a Redis server to start
 #[derive(serde::Deserialize)]
 pub struct RedisConfiguration {
+    #[serde(default)]
+    pub enabled: bool,
     pub host: String,
     pub port: u16
 }
 
+impl RedisConfiguration {
+    pub fn url(&self) -> String {
+        format!("redis://{}:{}", self.host, self.port)
+    }
+}
+
+// Set when running several instances behind a load balancer. The address must be reachable by the other instances.
+// Instances relaying connections to each other authenticate with the shared secret, which must be the same everywhere.
+#[derive(serde::Deserialize)]
+pub struct ClusterConfiguration {
+    pub address: String,
+    pub secret: String
+}
+
 #[derive(serde::Deserialize)]
 pub struct LoggerConfiguration {
     pub write_style: String,
@@ -18,13 +35,66 @@ pub struct ApplicationConfiguration {
     pub port: u16
 }
 
+// Both values are in seconds
+#[derive(Clone, serde::Deserialize)]
+pub struct RoomConfiguration {
+    pub idle_timeout: u64,
+    pub reaper_interval: u64
+}
+
+// Protects the WS endpoint from misbehaving clients
+#[derive(Clone, serde::Deserialize)]
+pub struct LimitsConfiguration {
+    // In bytes
+    pub max_message_size: usize,
+    pub max_connections_per_ip: usize,
+    pub action_burst: usize,
+    pub actions_per_second: f64,
+    // Consecutive actions over the rate limit before the connection is closed
+    pub max_rate_limit_violations: usize,
+    // Only enable this behind a proxy that sets the X-Forwarded-For header itself
+    pub trust_forwarded_for: bool
+}
+
+// Both values are in seconds. Without persistence, running games get up to drain_period to finish when the server
+// shuts down; with persistence, they are saved and users only get notice_period to see the notice.
+#[derive(Clone, serde::Deserialize)]
+pub struct ShutdownConfiguration {
+    pub drain_period: u64,
+    pub notice_period: u64
+}
+
+// Enables the admin API, see api/admin.rs. Requests must send the token as `Authorization: Bearer <token>`.
+#[derive(serde::Deserialize)]
+pub struct AdminConfiguration {
+    pub token: String
+}
+
+// Serves the puzzles in the directory, see puzzles.rs
+#[derive(serde::Deserialize)]
+pub struct PuzzleConfiguration {
+    pub directory: String
+}
+
 #[derive(serde::Deserialize)]
 pub struct Configuration {
     pub app: ApplicationConfiguration,
-    pub redis: RedisConfiguration,
+    pub rooms: RoomConfiguration,
+    pub limits: LimitsConfiguration,
+    pub shutdown: ShutdownConfiguration,
+    pub redis: Option<RedisConfiguration>,
+    pub cluster: Option<ClusterConfiguration>,
+    pub admin: Option<AdminConfiguration>,
+    pub puzzles: Option<PuzzleConfiguration>,
     pub logger: LoggerConfiguration
 }
 
+impl Configuration {
+    pub fn redis(&self) -> Option<&RedisConfiguration> {
+        self.redis.as_ref().filter(|redis| redis.enabled)
+    }
+}
+
 pub fn load_config() -> Configuration {
     Config::builder()
         .add_source(config::File::with_name("cfg/defaults.toml"))
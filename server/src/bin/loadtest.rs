@@ -0,0 +1,570 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use clap::Parser;
+use futures::{SinkExt, StreamExt};
+use indexmap::IndexSet;
+use itertools::Itertools;
+use rand::Rng;
+use rand::seq::{IteratorRandom, SliceRandom};
+use serde_json::{json, Value};
+use tokio::net::TcpStream;
+use tokio::time::Instant;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::tungstenite::Message;
+use url::Url;
+use tableturfer_server::PROTOCOL_VERSION;
+use tableturfer_server::game::card::{CardProvider, CardSquareProviderImpl};
+use tableturfer_server::game::map::{MapProvider, MapProviderImpl};
+use tableturfer_server::game::move_validator::{MoveValidator, MoveValidatorImpl};
+use tableturfer_server::game::state::{DECK_SIZE, GameState, PlayerDeck, PlayerMove, ROTATIONS};
+use tableturfer_server::game::team::PlayerTeam;
+use tableturfer_server::position::INamedPosition;
+
+const REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Plays games against a running server with pairs of simulated players, then reports latencies and errors.
+/// Every room takes two connections, so raise the server's limits.max_connections_per_ip before running many rooms.
+#[derive(Parser)]
+struct Options {
+    /// The server's WS endpoint
+    #[arg(long, default_value = "ws://localhost:8080/ws")]
+    url: String,
+    /// Rooms to create, each with two players
+    #[arg(long, default_value_t = 100)]
+    rooms: usize,
+    /// Games to play in every room
+    #[arg(long, default_value_t = 1)]
+    games: usize,
+    /// Average time players take to make a move, in milliseconds
+    #[arg(long, default_value_t = 1000)]
+    move_delay: u64,
+    /// Rooms are created evenly over this many seconds
+    #[arg(long, default_value_t = 10)]
+    ramp_up: u64,
+    /// How often players ping the server, in seconds
+    #[arg(long, default_value_t = 5)]
+    ping_interval: u64,
+    /// Stops after this many seconds, even if games are still running
+    #[arg(long, default_value_t = 600)]
+    timeout: u64,
+}
+
+// The same game data the server uses, to pick legal decks and moves
+struct Rules {
+    card_provider: Arc<dyn CardProvider + Send + Sync>,
+    map_provider: MapProviderImpl,
+    validator: MoveValidatorImpl,
+    card_names: Vec<String>,
+    map_names: Vec<String>,
+}
+
+impl Rules {
+    fn new() -> Self {
+        let card_provider: Arc<dyn CardProvider + Send + Sync> = Arc::new(CardSquareProviderImpl::new());
+        let map_provider = MapProviderImpl::new();
+
+        Self {
+            validator: MoveValidatorImpl::new(card_provider.clone()),
+            card_names: card_provider.get_all().into_iter().map(|card| card.name).collect(),
+            map_names: map_provider.get_names(),
+            card_provider,
+            map_provider,
+        }
+    }
+
+    fn random_deck(&self) -> IndexSet<String> {
+        self.card_names.choose_multiple(&mut rand::thread_rng(), DECK_SIZE).cloned().collect()
+    }
+
+    // Picks a random legal move, passing if none of the cards in the hand can be placed
+    fn choose_move(&self, game: &GameState, team: &PlayerTeam, hand: &IndexSet<String>, rng: &mut impl Rng) -> PlayerMove {
+        let mut deck = PlayerDeck::new(hand.clone());
+        deck.current_hand = hand.clone();
+        let board_size = game.board.size();
+        let special_points = game.available_special_points(team);
+
+        let mut candidates: Vec<PlayerMove> = hand.iter()
+            .cartesian_product(ROTATIONS)
+            .cartesian_product([false, true])
+            .cartesian_product((0..board_size.w).cartesian_product(0..board_size.h))
+            .map(|(((card_name, rotation), special), (x, y))| PlayerMove::PlaceCard {
+                card_name: card_name.clone(),
+                position: INamedPosition::new(x as isize, y as isize),
+                rotation,
+                special,
+            })
+            .collect();
+        candidates.shuffle(rng);
+
+        candidates.into_iter()
+            .find(|player_move| self.validator.validate(&game.board, special_points, team, player_move, &deck).is_ok())
+            .unwrap_or_else(|| PlayerMove::Pass { card_name: hand.iter().choose(rng).unwrap().clone() })
+    }
+}
+
+#[derive(Default)]
+struct Stats {
+    latencies: Mutex<BTreeMap<&'static str, Vec<Duration>>>,
+    errors: Mutex<BTreeMap<String, usize>>,
+    open_connections: AtomicUsize,
+    failed_connections: AtomicUsize,
+    games_completed: AtomicUsize,
+    moves: AtomicUsize,
+}
+
+impl Stats {
+    fn record_latency(&self, request: &'static str, latency: Duration) {
+        self.latencies.lock().unwrap().entry(request).or_default().push(latency);
+    }
+
+    fn record_error(&self, error: String) {
+        *self.errors.lock().unwrap().entry(error).or_insert(0) += 1;
+    }
+
+    fn progress(&self, elapsed: Duration) -> String {
+        format!("[{:>5.0}s] {} open connection(s), {} game(s) completed, {} move(s), {} error(s)",
+            elapsed.as_secs_f64(),
+            self.open_connections.load(Ordering::Relaxed),
+            self.games_completed.load(Ordering::Relaxed),
+            self.moves.load(Ordering::Relaxed),
+            self.errors.lock().unwrap().values().sum::<usize>())
+    }
+
+    fn summary(&self, elapsed: Duration) -> String {
+        let mut output = format!("Finished after {:.1}s: {} game(s) completed, {} move(s), {} failed connection(s)\n\n",
+            elapsed.as_secs_f64(),
+            self.games_completed.load(Ordering::Relaxed),
+            self.moves.load(Ordering::Relaxed),
+            self.failed_connections.load(Ordering::Relaxed));
+
+        output += &format!("{:<12} {:>8} {:>10} {:>10} {:>10} {:>10}\n", "Request", "Count", "p50", "p90", "p99", "max");
+        for (request, latencies) in self.latencies.lock().unwrap().iter_mut() {
+            latencies.sort();
+            let format = |latency: Duration| format!("{:.1}ms", latency.as_secs_f64() * 1000.0);
+            output += &format!("{:<12} {:>8} {:>10} {:>10} {:>10} {:>10}\n", request, latencies.len(),
+                format(percentile(latencies, 0.5)), format(percentile(latencies, 0.9)),
+                format(percentile(latencies, 0.99)), format(percentile(latencies, 1.0)));
+        }
+
+        let errors = self.errors.lock().unwrap();
+        if !errors.is_empty() {
+            output += "\nErrors:\n";
+            for (error, count) in errors.iter().sorted_by_key(|(_, count)| std::cmp::Reverse(**count)) {
+                output += &format!("{count:>8}  {error}\n");
+            }
+        }
+        output
+    }
+}
+
+// Expects the latencies to be sorted
+fn percentile(latencies: &[Duration], percentile: f64) -> Duration {
+    let index = ((latencies.len() as f64 * percentile).ceil() as usize).saturating_sub(1);
+    latencies[index.min(latencies.len() - 1)]
+}
+
+// Describes a socket error including its nested codes, e.g. "GameError/InvalidMove/CardNotInHand"
+fn error_name(error: &Value) -> String {
+    match (&error["code"], error) {
+        (Value::String(code), _) if error["detail"].is_null() => code.clone(),
+        (Value::String(code), _) => format!("{code}/{}", error_name(&error["detail"])),
+        (_, Value::String(detail)) => detail.clone(),
+        _ => error.to_string(),
+    }
+}
+
+// Connects and waits for the welcome event, recording why if that fails
+async fn connect(options: &Options, stats: &Stats, username: &str, room_code: Option<&str>) -> Option<(Socket, Value)> {
+    let mut url = Url::parse(&options.url).expect("Invalid server URL");
+    url.query_pairs_mut()
+        .append_pair("username", username)
+        .append_pair("version", &PROTOCOL_VERSION.to_string());
+    if let Some(room_code) = room_code {
+        url.query_pairs_mut().append_pair("room", room_code);
+    }
+
+    let start = Instant::now();
+    let mut socket = match tokio_tungstenite::connect_async(url).await {
+        Ok((socket, _response)) => socket,
+        Err(err) => {
+            stats.failed_connections.fetch_add(1, Ordering::Relaxed);
+            stats.record_error(format!("Failed to connect: {err}"));
+            return None;
+        }
+    };
+
+    while let Some(message) = socket.next().await {
+        match message {
+            Ok(Message::Text(text)) => {
+                let event: Value = serde_json::from_str(&text).unwrap_or_default();
+                if event["event"] == "Welcome" {
+                    stats.record_latency("Connect", start.elapsed());
+                    return Some((socket, event["detail"].clone()));
+                }
+            }
+            Ok(Message::Close(frame)) => {
+                stats.record_error(format!("Closed while connecting: {}", frame.map_or("no reason".to_string(), |frame| frame.to_string())));
+                break;
+            }
+            Ok(_) => {}
+            Err(err) => {
+                stats.record_error(format!("Failed to connect: {err}"));
+                break;
+            }
+        }
+    }
+
+    stats.failed_connections.fetch_add(1, Ordering::Relaxed);
+    None
+}
+
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+// A simulated player. The room owner starts games once both players have set their decks.
+struct Player {
+    options: Arc<Options>,
+    rules: Arc<Rules>,
+    stats: Arc<Stats>,
+    socket: Socket,
+    id: String,
+    is_owner: bool,
+    team: PlayerTeam,
+    opponent_id: Option<String>,
+    users_with_decks: HashSet<String>,
+    deck: IndexSet<String>,
+    hand: IndexSet<String>,
+    game: Option<GameState>,
+    starting_game: bool,
+    moved: bool,
+    // The card played this turn, until the server has replaced it in the hand
+    replaced_card: Option<String>,
+    pass_next_move: bool,
+    next_move_at: Option<Instant>,
+    sent_at: HashMap<&'static str, Instant>,
+    games_played: usize,
+    opponent_left: bool,
+}
+
+impl Player {
+    fn new(options: Arc<Options>, rules: Arc<Rules>, stats: Arc<Stats>, socket: Socket, welcome: &Value) -> Self {
+        let id = welcome["id"].as_str().unwrap_or_default().to_string();
+        let is_owner = welcome["owner"] == id.as_str();
+
+        Self {
+            deck: rules.random_deck(),
+            options,
+            rules,
+            stats,
+            socket,
+            team: if is_owner { PlayerTeam::Alpha } else { PlayerTeam::Bravo },
+            opponent_id: welcome["opponent"].as_str().map(str::to_string),
+            is_owner,
+            id,
+            users_with_decks: HashSet::new(),
+            hand: IndexSet::new(),
+            game: None,
+            starting_game: false,
+            moved: false,
+            replaced_card: None,
+            pass_next_move: false,
+            next_move_at: None,
+            sent_at: HashMap::new(),
+            games_played: 0,
+            opponent_left: false,
+        }
+    }
+
+    async fn run(mut self) {
+        self.stats.open_connections.fetch_add(1, Ordering::Relaxed);
+        self.send("SetDeck", json!({ "action": "SetDeck", "args": { "id": "loadtest", "cards": self.deck } })).await;
+
+        let ping_period = Duration::from_secs(self.options.ping_interval);
+        let mut ping_interval = tokio::time::interval_at(Instant::now() + ping_period, ping_period);
+
+        while self.games_played < self.options.games && !self.opponent_left {
+            tokio::select! {
+                message = self.socket.next() => match message {
+                    Some(Ok(Message::Text(text))) => match serde_json::from_str(&text) {
+                        Ok(event) => self.handle_event(event).await,
+                        Err(err) => self.stats.record_error(format!("Unexpected message: {err}")),
+                    },
+                    Some(Ok(Message::Close(frame))) => {
+                        self.stats.record_error(format!("Closed: {}", frame.map_or("no reason".to_string(), |frame| frame.to_string())));
+                        break;
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(err)) => {
+                        self.stats.record_error(format!("Connection failed: {err}"));
+                        break;
+                    }
+                    None => break,
+                },
+                _ = sleep_until(self.next_move_at) => {
+                    self.next_move_at = None;
+                    self.propose_move().await;
+                }
+                _ = ping_interval.tick() => self.send("Ping", json!({ "action": "Ping" })).await,
+            }
+        }
+
+        self.socket.close(None).await.ok();
+        self.stats.open_connections.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    async fn send(&mut self, request: &'static str, action: Value) {
+        self.sent_at.insert(request, Instant::now());
+        if let Err(err) = self.socket.send(Message::Text(action.to_string())).await {
+            self.stats.record_error(format!("Failed to send {request}: {err}"));
+        }
+    }
+
+    fn complete(&mut self, request: &'static str) {
+        if let Some(sent_at) = self.sent_at.remove(request) {
+            self.stats.record_latency(request, sent_at.elapsed());
+        }
+    }
+
+    async fn handle_event(&mut self, event: Value) {
+        match event["event"].as_str() {
+            Some("Pong") => self.complete("Ping"),
+            Some("Error") => {
+                self.stats.record_error(error_name(&event["detail"]));
+                // Fall back to passing if a move was rejected, e.g. because the local board is out of sync
+                if event["detail"]["code"] == "GameError" && self.sent_at.remove("ProposeMove").is_some() {
+                    self.moved = false;
+                    self.replaced_card = None;
+                    self.pass_next_move = true;
+                    self.schedule_move();
+                }
+            }
+            Some("RoomEvent") => self.handle_room_event(&event["detail"]).await,
+            _ => {}
+        }
+    }
+
+    async fn handle_room_event(&mut self, event: &Value) {
+        let detail = &event["detail"];
+
+        match event["event"].as_str() {
+            Some("UserUpdate") => {
+                let user_id = detail["id"].as_str().unwrap_or_default().to_string();
+                if !detail["user"]["deck"].is_null() {
+                    if user_id == self.id {
+                        self.complete("SetDeck");
+                    }
+                    self.users_with_decks.insert(user_id);
+                }
+                self.try_start_game().await;
+            }
+            Some("OpponentChange") => {
+                self.opponent_id = detail.as_str().map(str::to_string);
+                self.try_start_game().await;
+            }
+            Some("UserLeave") => self.opponent_left |= detail.as_str() == self.opponent_id.as_deref(),
+            Some("StartGame") => {
+                self.complete("StartGame");
+                self.starting_game = false;
+                let map = self.rules.map_provider.get(detail["mapName"].as_str().unwrap_or_default()).unwrap();
+                let card_provider = self.rules.card_provider.clone();
+                // The opponent's deck is unknown, but only our own moves are validated locally
+                let opponent_deck = self.rules.card_names.iter().cloned().collect();
+                let decks = match self.team {
+                    PlayerTeam::Alpha => HashMap::from([(PlayerTeam::Alpha, self.deck.clone()), (PlayerTeam::Bravo, opponent_deck)]),
+                    PlayerTeam::Bravo => HashMap::from([(PlayerTeam::Alpha, opponent_deck), (PlayerTeam::Bravo, self.deck.clone())]),
+                };
+                self.game = Some(GameState::new(map.squares, card_provider.clone(), Arc::new(MoveValidatorImpl::new(card_provider)), decks));
+                self.moved = false;
+                self.replaced_card = None;
+                self.schedule_move();
+            }
+            Some("HandAssigned") => {
+                self.hand = serde_json::from_value(detail.clone()).unwrap_or_default();
+                self.schedule_move();
+            }
+            Some("NextCardDrawn") => {
+                let replacing = detail["replacing"].as_str().unwrap_or_default();
+                self.hand.shift_remove(replacing);
+                self.hand.insert(detail["newCard"].as_str().unwrap_or_default().to_string());
+                if self.replaced_card.as_deref() == Some(replacing) {
+                    self.replaced_card = None;
+                }
+                self.schedule_move();
+            }
+            Some("MoveReceived") if detail["team"] == json!(self.team) => {
+                self.complete("ProposeMove");
+                self.stats.moves.fetch_add(1, Ordering::Relaxed);
+            }
+            Some("MovesApplied") => {
+                if let (Some(game), Ok(moves)) = (self.game.as_mut(), serde_json::from_value(detail["moves"].clone())) {
+                    game.replay_moves(moves);
+                }
+                self.moved = false;
+                self.schedule_move();
+            }
+            Some("EndGame") => {
+                self.game = None;
+                self.hand.clear();
+                self.next_move_at = None;
+                self.games_played += 1;
+
+                if self.is_owner {
+                    self.stats.games_completed.fetch_add(1, Ordering::Relaxed);
+                    if self.games_played < self.options.games {
+                        self.send("ReturnToRoom", json!({ "action": "ReturnToRoom" })).await;
+                        self.try_start_game().await;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    async fn try_start_game(&mut self) {
+        let decks_set = self.users_with_decks.contains(&self.id)
+            && self.opponent_id.as_ref().is_some_and(|opponent_id| self.users_with_decks.contains(opponent_id));
+        if !self.is_owner || self.game.is_some() || self.starting_game || !decks_set {
+            return;
+        }
+
+        self.starting_game = true;
+        let map = self.rules.map_names.choose(&mut rand::thread_rng()).unwrap().clone();
+        self.send("SetMap", json!({ "action": "SetMap", "args": map })).await;
+        self.send("StartGame", json!({ "action": "StartGame" })).await;
+    }
+
+    fn can_move(&self) -> bool {
+        self.game.as_ref().is_some_and(|game| !game.completed())
+            && !self.moved
+            && self.replaced_card.is_none()
+            && !self.hand.is_empty()
+    }
+
+    // Moves are made after a random delay around the configured one, like a person would
+    fn schedule_move(&mut self) {
+        if self.can_move() && self.next_move_at.is_none() {
+            let delay = self.options.move_delay as f64 * rand::thread_rng().gen_range(0.5..1.5);
+            self.next_move_at = Some(Instant::now() + Duration::from_millis(delay as u64));
+        }
+    }
+
+    async fn propose_move(&mut self) {
+        if !self.can_move() {
+            return;
+        }
+
+        let player_move = {
+            let mut rng = rand::thread_rng();
+            if self.pass_next_move {
+                PlayerMove::Pass { card_name: self.hand.iter().choose(&mut rng).unwrap().clone() }
+            } else {
+                self.rules.choose_move(self.game.as_ref().unwrap(), &self.team, &self.hand, &mut rng)
+            }
+        };
+
+        self.moved = true;
+        self.pass_next_move = false;
+        self.replaced_card = Some(player_move.card_name().to_string());
+        self.send("ProposeMove", json!({ "action": "ProposeMove", "args": player_move })).await;
+    }
+}
+
+async fn play_room(index: usize, options: Arc<Options>, rules: Arc<Rules>, stats: Arc<Stats>) {
+    let Some((owner_socket, welcome)) = connect(&options, &stats, &format!("load-{index}-a"), None).await else {
+        return;
+    };
+    let room_code = welcome["roomCode"].as_str().unwrap_or_default().to_string();
+    let owner = Player::new(options.clone(), rules.clone(), stats.clone(), owner_socket, &welcome);
+
+    let opponent = async {
+        if let Some((socket, welcome)) = connect(&options, &stats, &format!("load-{index}-b"), Some(&room_code)).await {
+            Player::new(options.clone(), rules.clone(), stats.clone(), socket, &welcome).run().await;
+        }
+    };
+    tokio::join!(owner.run(), opponent);
+}
+
+#[tokio::main]
+async fn main() {
+    let options = Arc::new(Options::parse());
+    let rules = Arc::new(Rules::new());
+    let stats = Arc::new(Stats::default());
+    let start = Instant::now();
+
+    println!("Playing {} game(s) in each of {} room(s) against {}", options.games, options.rooms, options.url);
+    let ramp_up_interval = Duration::from_secs(options.ramp_up) / options.rooms.max(1) as u32;
+    let rooms = async {
+        let mut rooms = Vec::with_capacity(options.rooms);
+        for index in 0..options.rooms {
+            rooms.push(tokio::spawn(play_room(index, options.clone(), rules.clone(), stats.clone())));
+            tokio::time::sleep(ramp_up_interval).await;
+        }
+        futures::future::join_all(rooms).await;
+    };
+    let report = async {
+        let mut interval = tokio::time::interval_at(Instant::now() + REPORT_INTERVAL, REPORT_INTERVAL);
+        loop {
+            interval.tick().await;
+            println!("{}", stats.progress(start.elapsed()));
+        }
+    };
+
+    tokio::select! {
+        _ = rooms => {}
+        _ = report => {}
+        _ = tokio::time::sleep(Duration::from_secs(options.timeout)) => println!("Timed out with games still running"),
+    }
+
+    println!("\n{}", stats.summary(start.elapsed()));
+}
+
+#[cfg(test)]
+mod tests {
+    use tableturfer_server::game::team::PlayerTeam;
+    use super::*;
+
+    #[test]
+    fn percentile() {
+        let latencies: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+
+        assert_eq!(Duration::from_millis(50), super::percentile(&latencies, 0.5));
+        assert_eq!(Duration::from_millis(99), super::percentile(&latencies, 0.99));
+        assert_eq!(Duration::from_millis(100), super::percentile(&latencies, 1.0));
+        assert_eq!(Duration::from_millis(7), super::percentile(&[Duration::from_millis(7)], 0.5));
+    }
+
+    #[test]
+    fn error_name() {
+        assert_eq!("RateLimited", super::error_name(&json!({ "code": "RateLimited" })));
+        assert_eq!("GameError/InvalidMove/CardNotInHand",
+            super::error_name(&json!({ "code": "GameError", "detail": { "code": "InvalidMove", "detail": "CardNotInHand" } })));
+    }
+
+    #[test]
+    fn chooses_legal_moves() {
+        let rules = Rules::new();
+        let deck = rules.random_deck();
+        let map = rules.map_provider.get(&rules.map_names[0]).unwrap();
+        let mut game = GameState::new(
+            map.squares,
+            rules.card_provider.clone(),
+            Arc::new(MoveValidatorImpl::new(rules.card_provider.clone())),
+            HashMap::from([(PlayerTeam::Alpha, deck.clone()), (PlayerTeam::Bravo, deck)]));
+        let hands = game.assign_initial_hands();
+
+        let mut rng = rand::thread_rng();
+        for team in [PlayerTeam::Alpha, PlayerTeam::Bravo] {
+            let player_move = rules.choose_move(&game, &team, &hands[&team], &mut rng);
+            assert!(matches!(player_move, PlayerMove::PlaceCard { .. }), "The first move should be able to place a card");
+            game.propose_move(team, player_move).unwrap();
+        }
+    }
+}
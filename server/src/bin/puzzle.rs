@@ -0,0 +1,74 @@
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use clap::{Parser, Subcommand};
+use serde::de::DeserializeOwned;
+use tableturfer_server::game::card::{CardProvider, CardSquareProviderImpl};
+use tableturfer_server::game::puzzle::{DEFAULT_MAX_NODES, Puzzle};
+use tableturfer_server::game::state::PlayerMove;
+
+/// Checks puzzle files before they are published, see the Puzzles section of the README
+#[derive(Parser)]
+struct Options {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Validates a puzzle and searches for a solution, which is printed if one is found
+    Check {
+        puzzle: PathBuf,
+        /// Gives up after trying this many moves
+        #[arg(long, default_value_t = DEFAULT_MAX_NODES)]
+        max_nodes: usize,
+    },
+    /// Plays the moves in a solution file and prints the outcome
+    Verify {
+        puzzle: PathBuf,
+        solution: PathBuf,
+    },
+}
+
+fn read<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let content = std::fs::read_to_string(path).map_err(|err| format!("Failed to read {}: {err}", path.display()))?;
+    serde_json::from_str(&content).map_err(|err| format!("Failed to parse {}: {err}", path.display()))
+}
+
+fn run(command: Command) -> Result<bool, String> {
+    let card_provider: Arc<dyn CardProvider + Send + Sync> = Arc::new(CardSquareProviderImpl::new());
+
+    match command {
+        Command::Check { puzzle, max_nodes } => {
+            let puzzle: Puzzle = read(&puzzle)?;
+            match puzzle.solve(card_provider, max_nodes).map_err(|err| format!("Invalid puzzle: {err:?}"))? {
+                Some(solution) => {
+                    println!("{}", serde_json::to_string_pretty(&solution).unwrap());
+                    Ok(true)
+                }
+                None => {
+                    eprintln!("No solution found within {max_nodes} moves");
+                    Ok(false)
+                }
+            }
+        }
+        Command::Verify { puzzle, solution } => {
+            let puzzle: Puzzle = read(&puzzle)?;
+            let solution: Vec<PlayerMove> = read(&solution)?;
+            let outcome = puzzle.check_solution(&solution, card_provider).map_err(|err| format!("Invalid solution: {err:?}"))?;
+            println!("{}", serde_json::to_string_pretty(&outcome).unwrap());
+            Ok(outcome.solved)
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Options::parse().command) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -4,3 +4,51 @@ pub mod squares;
 pub mod team;
 pub mod card;
 pub mod move_validator;
+pub mod series;
+pub mod rules;
+pub mod draft;
+pub mod map_veto;
+pub mod sandbox;
+pub mod puzzle;
+pub mod endgame;
+
+use std::path::Path;
+use ts_rs::TS;
+use crate::game::squares::MapSquareType;
+use crate::game::state::CardRotation;
+
+// ts-rs can't derive numeric enums, so these are declared as unions of their values
+macro_rules! impl_ts_repr_enum {
+    ($name:ident, $values:literal) => {
+        impl TS for $name {
+            type WithoutGenerics = Self;
+
+            fn name() -> String {
+                stringify!($name).to_owned()
+            }
+
+            fn inline() -> String {
+                $values.to_owned()
+            }
+
+            fn inline_flattened() -> String {
+                panic!("{} cannot be flattened", stringify!($name))
+            }
+
+            fn decl() -> String {
+                format!("type {} = {};", stringify!($name), $values)
+            }
+
+            fn decl_concrete() -> String {
+                Self::decl()
+            }
+
+            fn output_path() -> Option<&'static Path> {
+                Some(Path::new(concat!(stringify!($name), ".ts")))
+            }
+        }
+    };
+}
+
+impl_ts_repr_enum!(CardRotation, "0 | 90 | 180 | 270");
+impl_ts_repr_enum!(MapSquareType, "0 | 1 | 2 | 3 | 4 | 5 | 6");
@@ -1,11 +1,13 @@
 use std::collections::HashMap;
-use serde::Deserialize;
-use serde_repr::Deserialize_repr;
+use itertools::Itertools;
+use schemars::{JsonSchema, JsonSchema_repr};
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
 use crate::matrix::Matrix;
 
 static CARDS_JSON: &str = include_str!("cards.json");
 
-#[derive(Clone, Copy, Eq, PartialEq, Debug, Deserialize_repr)]
+#[derive(Clone, Copy, Eq, Hash, PartialEq, Debug, Deserialize_repr, Serialize_repr, JsonSchema_repr)]
 #[repr(u8)]
 pub enum CardSquareType {
     Empty = 0,
@@ -13,7 +15,7 @@ pub enum CardSquareType {
     Special = 2,
 }
 
-#[derive(Clone, Deserialize_repr)]
+#[derive(Clone, Deserialize_repr, Serialize_repr, JsonSchema_repr)]
 #[repr(u8)]
 pub enum CardRarity {
     Common = 0,
@@ -21,7 +23,7 @@ pub enum CardRarity {
     Fresh = 2,
 }
 
-#[derive(Deserialize, Clone)]
+#[derive(Deserialize, Serialize, Clone, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Card {
     pub category: String,
@@ -30,6 +32,7 @@ pub struct Card {
     pub rarity: CardRarity,
     pub season: usize,
     pub special_cost: usize,
+    #[schemars(with = "Vec<Vec<CardSquareType>>")]
     pub squares: Matrix<CardSquareType>,
 }
 
@@ -37,6 +40,9 @@ pub trait CardProvider {
     fn get(&self, card_name: &str) -> Option<Card>;
 
     fn exists(&self, card_name: &str) -> bool;
+
+    // Sorted by card number
+    fn get_all(&self) -> Vec<Card>;
 }
 
 pub struct CardSquareProviderImpl {
@@ -55,14 +61,24 @@ impl CardSquareProviderImpl {
     }
 }
 
+impl Default for CardSquareProviderImpl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl CardProvider for CardSquareProviderImpl {
     fn get(&self, card_name: &str) -> Option<Card> {
-        self.cards.get(card_name).map(|card| card.clone())
+        self.cards.get(card_name).cloned()
     }
 
     fn exists(&self, card_name: &str) -> bool {
         self.cards.contains_key(card_name)
     }
+
+    fn get_all(&self) -> Vec<Card> {
+        self.cards.values().cloned().sorted_by_key(|card| card.number).collect()
+    }
 }
 
 #[cfg(test)]
@@ -133,11 +149,23 @@ pub mod tests {
 
     impl CardProvider for TestCardSquareProvider {
         fn get(&self, card_name: &str) -> Option<Card> {
-            self.cards.get(card_name).map(|card| card.clone())
+            self.cards.get(card_name).cloned()
         }
 
         fn exists(&self, card_name: &str) -> bool {
             self.cards.contains_key(card_name)
         }
+
+        fn get_all(&self) -> Vec<Card> {
+            self.cards.values().cloned().sorted_by_key(|card| card.number).collect()
+        }
+    }
+
+    #[test]
+    fn get_all_sorts_cards_by_number() {
+        let cards = CardSquareProviderImpl::new().get_all();
+
+        assert!(!cards.is_empty());
+        assert!(cards.windows(2).all(|pair| pair[0].number < pair[1].number));
     }
 }
@@ -0,0 +1,151 @@
+use std::collections::{HashMap, HashSet};
+use indexmap::IndexSet;
+use rand::seq::IteratorRandom;
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use ts_rs::TS;
+use crate::game::state::DECK_SIZE;
+use crate::game::team::PlayerTeam;
+
+// Both players pick from the same pack in turns, so each of them gets half of every pack
+pub const PACK_SIZE: usize = 6;
+
+#[derive(Serialize, Debug, Eq, PartialEq, JsonSchema, TS)]
+pub enum DraftError {
+    DraftNotRunning,
+    NotEnoughCards,
+    NotDraftPicker,
+    CardNotInPack,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum DraftPickResult {
+    NextPick,
+    NextPack,
+    Completed,
+}
+
+// Players build their decks by taking turns picking cards from random packs. Cards picked by either player don't
+// show up in later packs.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct Draft {
+    pub round: usize,
+    #[ts(as = "Vec<String>")]
+    pub pack: IndexSet<String>,
+    pub picker: PlayerTeam,
+    #[ts(as = "HashMap<PlayerTeam, Vec<String>>")]
+    pub picks: HashMap<PlayerTeam, IndexSet<String>>,
+}
+
+impl Draft {
+    pub fn new(card_names: &[String]) -> Result<Self, DraftError> {
+        if card_names.len() < DECK_SIZE * 2 {
+            return Err(DraftError::NotEnoughCards);
+        }
+
+        let mut draft = Self {
+            round: 0,
+            pack: IndexSet::new(),
+            picker: PlayerTeam::Alpha,
+            picks: HashMap::from([(PlayerTeam::Alpha, IndexSet::new()), (PlayerTeam::Bravo, IndexSet::new())]),
+        };
+        draft.open_pack(card_names);
+        Ok(draft)
+    }
+
+    // The first pick of a pack alternates between the players, so neither of them always gets first choice
+    fn open_pack(&mut self, card_names: &[String]) {
+        self.round += 1;
+        self.picker = if self.round % 2 == 1 { PlayerTeam::Alpha } else { PlayerTeam::Bravo };
+
+        let picked: HashSet<&String> = self.picks.values().flatten().collect();
+        let remaining_picks = DECK_SIZE * 2 - picked.len();
+        self.pack = card_names.iter()
+            .filter(|card| !picked.contains(card))
+            .choose_multiple(&mut rand::thread_rng(), PACK_SIZE.min(remaining_picks))
+            .into_iter()
+            .cloned()
+            .collect();
+    }
+
+    pub fn pick(&mut self, team: &PlayerTeam, card: &str, card_names: &[String]) -> Result<DraftPickResult, DraftError> {
+        if team != &self.picker {
+            return Err(DraftError::NotDraftPicker);
+        } else if !self.pack.shift_remove(card) {
+            return Err(DraftError::CardNotInPack);
+        }
+
+        self.picks.get_mut(team).unwrap().insert(card.to_string());
+        if self.completed() {
+            return Ok(DraftPickResult::Completed);
+        } else if self.pack.is_empty() {
+            self.open_pack(card_names);
+            return Ok(DraftPickResult::NextPack);
+        }
+
+        // A player with a full deck doesn't pick anymore
+        let other_team = match team {
+            PlayerTeam::Alpha => PlayerTeam::Bravo,
+            PlayerTeam::Bravo => PlayerTeam::Alpha,
+        };
+        if self.picks[&other_team].len() < DECK_SIZE {
+            self.picker = other_team;
+        }
+        Ok(DraftPickResult::NextPick)
+    }
+
+    pub fn completed(&self) -> bool {
+        self.picks.values().all(|picks| picks.len() >= DECK_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card_names(count: usize) -> Vec<String> {
+        (0..count).map(|index| format!("card_{index}")).collect()
+    }
+
+    #[test]
+    fn requires_enough_cards() {
+        assert_eq!(Err(DraftError::NotEnoughCards), Draft::new(&card_names(DECK_SIZE * 2 - 1)));
+        assert!(Draft::new(&card_names(DECK_SIZE * 2)).is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_picks() {
+        let cards = card_names(40);
+        let mut draft = Draft::new(&cards).unwrap();
+        let card = draft.pack[0].clone();
+
+        assert_eq!(Err(DraftError::NotDraftPicker), draft.pick(&PlayerTeam::Bravo, &card, &cards));
+        assert_eq!(Err(DraftError::CardNotInPack), draft.pick(&PlayerTeam::Alpha, "unknown", &cards));
+        assert_eq!(Ok(DraftPickResult::NextPick), draft.pick(&PlayerTeam::Alpha, &card, &cards));
+        assert_eq!(Err(DraftError::CardNotInPack), draft.pick(&PlayerTeam::Bravo, &card, &cards));
+    }
+
+    #[test]
+    fn builds_two_decks() {
+        // With exactly enough cards, every card ends up in one of the decks
+        let cards = card_names(DECK_SIZE * 2);
+        let mut draft = Draft::new(&cards).unwrap();
+        let mut first_pickers = vec!(draft.picker.clone());
+        let mut result = DraftPickResult::NextPick;
+
+        while result != DraftPickResult::Completed {
+            assert_eq!(PACK_SIZE, draft.pack.len() + draft.picks.values().map(IndexSet::len).sum::<usize>() % PACK_SIZE);
+            let (team, card) = (draft.picker.clone(), draft.pack[0].clone());
+            result = draft.pick(&team, &card, &cards).unwrap();
+            if result == DraftPickResult::NextPack {
+                first_pickers.push(draft.picker.clone());
+            }
+        }
+
+        assert_eq!(DECK_SIZE, draft.picks[&PlayerTeam::Alpha].len());
+        assert_eq!(DECK_SIZE, draft.picks[&PlayerTeam::Bravo].len());
+        assert!(draft.picks[&PlayerTeam::Alpha].is_disjoint(&draft.picks[&PlayerTeam::Bravo]));
+        assert_eq!(vec!(PlayerTeam::Alpha, PlayerTeam::Bravo, PlayerTeam::Alpha, PlayerTeam::Bravo, PlayerTeam::Alpha), first_pickers);
+    }
+}
@@ -0,0 +1,408 @@
+use std::collections::HashMap;
+use indexmap::IndexSet;
+use itertools::Itertools;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use serde::Serialize;
+use schemars::JsonSchema;
+use crate::game::squares::MapSquareType;
+use crate::game::state::{GameState, PlayerMove};
+use crate::game::team::PlayerTeam;
+use crate::matrix::Matrix;
+
+// Searching more turns than this is hopeless, as every turn multiplies the number of positions by thousands
+pub const MAX_ENDGAME_TURNS: usize = 3;
+// The solver gives up after evaluating this many move combinations by default, which takes a few seconds
+pub const DEFAULT_MAX_NODES: usize = 250_000;
+
+const EPSILON: f64 = 1e-9;
+
+#[derive(Serialize, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(tag = "code", content = "detail")]
+pub enum EndgameError {
+    GameEnded,
+    // Hands are only assigned once the game has started
+    HandsUnknown,
+    TooManyTurns,
+    NodeLimitReached,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WeightedMove {
+    pub player_move: PlayerMove,
+    pub probability: f64,
+}
+
+// The optimal strategies for the current turn. The margin is Alpha's score minus Bravo's at the end of the game, which
+// Alpha can achieve on average whatever Bravo does and vice versa. Cards drawn later are assumed to be random.
+#[derive(Clone, Debug, Serialize, PartialEq, JsonSchema)]
+pub struct EndgameSolution {
+    pub margin: f64,
+    // The moves of each team that are played with a non-zero probability, most likely first
+    pub strategies: HashMap<PlayerTeam, Vec<WeightedMove>>,
+}
+
+impl EndgameSolution {
+    // Picks a move following the team's strategy, e.g. for a bot
+    pub fn choose_move<R: Rng>(&self, team: &PlayerTeam, rng: &mut R) -> Option<PlayerMove> {
+        self.strategies.get(team)?
+            .choose_weighted(rng, |weighted_move| weighted_move.probability)
+            .ok()
+            .map(|weighted_move| weighted_move.player_move.clone())
+    }
+}
+
+// Searches every combination of moves of both teams until the end of the game. Each turn is solved as a zero-sum
+// matrix game, as both teams move simultaneously, and card draws are averaged over every card that could be drawn.
+pub fn solve(game: &GameState, max_nodes: usize) -> Result<EndgameSolution, EndgameError> {
+    if game.completed() {
+        return Err(EndgameError::GameEnded);
+    } else if game.remaining_turns > MAX_ENDGAME_TURNS {
+        return Err(EndgameError::TooManyTurns);
+    } else if [PlayerTeam::Alpha, PlayerTeam::Bravo].iter().any(|team| game.hand(team).is_none_or(IndexSet::is_empty)) {
+        return Err(EndgameError::HandsUnknown);
+    }
+
+    let mut search = Search { remaining_nodes: max_nodes, cache: HashMap::new() };
+    let (alpha_moves, bravo_moves) = (game.valid_moves(&PlayerTeam::Alpha), game.valid_moves(&PlayerTeam::Bravo));
+    let matrix_game = search.solve_turn(game, &alpha_moves, &bravo_moves)?;
+
+    let strategy = |moves: Vec<PlayerMove>, probabilities: Vec<f64>| {
+        let mut weighted_moves: Vec<WeightedMove> = moves.into_iter().zip(probabilities)
+            .filter(|(_, probability)| *probability > EPSILON)
+            .map(|(player_move, probability)| WeightedMove { player_move, probability })
+            .collect();
+        weighted_moves.sort_by(|a, b| b.probability.total_cmp(&a.probability));
+        weighted_moves
+    };
+
+    Ok(EndgameSolution {
+        margin: matrix_game.value,
+        strategies: HashMap::from([
+            (PlayerTeam::Alpha, strategy(alpha_moves, matrix_game.row_strategy)),
+            (PlayerTeam::Bravo, strategy(bravo_moves, matrix_game.column_strategy)),
+        ]),
+    })
+}
+
+// Everything the rest of a game depends on. Passes and special attacks so far only matter through the special points
+// they leave each team with.
+#[derive(Eq, Hash, PartialEq)]
+struct PositionKey {
+    board: Matrix<MapSquareType>,
+    remaining_turns: usize,
+    hands: [Vec<String>; 2],
+    upcoming_cards: [Vec<String>; 2],
+    special_points: [usize; 2],
+}
+
+impl PositionKey {
+    fn new(game: &GameState) -> Self {
+        let sorted = |cards: IndexSet<String>| cards.into_iter().sorted().collect();
+        let teams = [PlayerTeam::Alpha, PlayerTeam::Bravo];
+
+        Self {
+            board: game.board.clone(),
+            remaining_turns: game.remaining_turns,
+            hands: teams.clone().map(|team| sorted(game.hand(&team).cloned().unwrap_or_default())),
+            upcoming_cards: teams.clone().map(|team| sorted(game.upcoming_cards(&team))),
+            special_points: teams.map(|team| game.available_special_points(&team)),
+        }
+    }
+}
+
+struct Search {
+    remaining_nodes: usize,
+    // The margin of every position solved so far
+    cache: HashMap<PositionKey, f64>,
+}
+
+impl Search {
+    fn value(&mut self, game: &GameState) -> Result<f64, EndgameError> {
+        if game.completed() {
+            let score = game.score();
+            return Ok(score[&PlayerTeam::Alpha] as f64 - score[&PlayerTeam::Bravo] as f64);
+        }
+
+        let key = PositionKey::new(game);
+        if let Some(value) = self.cache.get(&key) {
+            return Ok(*value);
+        }
+
+        let (alpha_moves, bravo_moves) = (game.valid_moves(&PlayerTeam::Alpha), game.valid_moves(&PlayerTeam::Bravo));
+        let value = self.solve_turn(game, &alpha_moves, &bravo_moves)?.value;
+        self.cache.insert(key, value);
+        Ok(value)
+    }
+
+    fn solve_turn(&mut self, game: &GameState, alpha_moves: &[PlayerMove], bravo_moves: &[PlayerMove]) -> Result<MatrixGame, EndgameError> {
+        let mut payoffs = Vec::with_capacity(alpha_moves.len());
+        for alpha_move in alpha_moves {
+            let row = bravo_moves.iter()
+                .map(|bravo_move| self.payoff(game, alpha_move, bravo_move))
+                .collect::<Result<Vec<f64>, EndgameError>>()?;
+            payoffs.push(row);
+        }
+
+        Ok(MatrixGame::solve(&payoffs))
+    }
+
+    // The average margin after both moves, over every pair of cards the teams could draw afterwards
+    fn payoff(&mut self, game: &GameState, alpha_move: &PlayerMove, bravo_move: &PlayerMove) -> Result<f64, EndgameError> {
+        self.remaining_nodes = self.remaining_nodes.checked_sub(1).ok_or(EndgameError::NodeLimitReached)?;
+
+        let moves = HashMap::from([(PlayerTeam::Alpha, alpha_move.clone()), (PlayerTeam::Bravo, bravo_move.clone())]);
+        let mut next_game = game.clone();
+        next_game.replay_moves(moves.clone());
+        if next_game.completed() {
+            return self.value(&next_game);
+        }
+
+        // The hands after playing, with every card that could be drawn into them
+        let draws: Vec<Vec<IndexSet<String>>> = [PlayerTeam::Alpha, PlayerTeam::Bravo].iter()
+            .map(|team| {
+                let mut hand = game.hand(team).cloned().unwrap_or_default();
+                hand.shift_remove(moves[team].card_name());
+                let upcoming_cards = game.upcoming_cards(team);
+                if upcoming_cards.is_empty() {
+                    return vec!(hand);
+                }
+
+                upcoming_cards.into_iter()
+                    .map(|card| {
+                        let mut hand = hand.clone();
+                        hand.insert(card);
+                        hand
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut total = 0.0;
+        for (alpha_hand, bravo_hand) in draws[0].iter().cartesian_product(&draws[1]) {
+            next_game.set_hand(&PlayerTeam::Alpha, alpha_hand.clone());
+            next_game.set_hand(&PlayerTeam::Bravo, bravo_hand.clone());
+            total += self.value(&next_game)?;
+        }
+        Ok(total / (draws[0].len() * draws[1].len()) as f64)
+    }
+}
+
+// A two-player zero-sum game given by the payoffs of the row player, who maximizes them
+#[derive(Debug, PartialEq)]
+struct MatrixGame {
+    value: f64,
+    row_strategy: Vec<f64>,
+    column_strategy: Vec<f64>,
+}
+
+impl MatrixGame {
+    fn solve(payoffs: &[Vec<f64>]) -> Self {
+        let (rows, columns) = Self::undominated(payoffs);
+        let reduced: Vec<Vec<f64>> = rows.iter()
+            .map(|row| columns.iter().map(|column| payoffs[*row][*column]).collect())
+            .collect();
+
+        let (value, row_probabilities, column_probabilities) = Self::saddle_point(&reduced)
+            .unwrap_or_else(|| Self::simplex(&reduced));
+
+        let mut row_strategy = vec!(0.0; payoffs.len());
+        rows.iter().zip(row_probabilities).for_each(|(row, probability)| row_strategy[*row] = probability);
+        let mut column_strategy = vec!(0.0; payoffs[0].len());
+        columns.iter().zip(column_probabilities).for_each(|(column, probability)| column_strategy[*column] = probability);
+
+        Self { value, row_strategy, column_strategy }
+    }
+
+    // Removes weakly dominated rows and columns one at a time. This keeps the value, and optimal strategies of the
+    // reduced game are optimal in the full game.
+    fn undominated(payoffs: &[Vec<f64>]) -> (Vec<usize>, Vec<usize>) {
+        let mut rows: Vec<usize> = (0..payoffs.len()).collect();
+        let mut columns: Vec<usize> = (0..payoffs[0].len()).collect();
+
+        loop {
+            let dominated_row = rows.iter().position(|row| rows.iter()
+                .any(|other| other != row && columns.iter().all(|column| payoffs[*other][*column] >= payoffs[*row][*column] - EPSILON)));
+            if let Some(index) = dominated_row {
+                rows.remove(index);
+                continue;
+            }
+
+            let dominated_column = columns.iter().position(|column| columns.iter()
+                .any(|other| other != column && rows.iter().all(|row| payoffs[*row][*other] <= payoffs[*row][*column] + EPSILON)));
+            match dominated_column {
+                Some(index) => { columns.remove(index); }
+                None => return (rows, columns),
+            }
+        }
+    }
+
+    // Games where the best guaranteed payoffs of both players meet are solved by pure strategies
+    fn saddle_point(payoffs: &[Vec<f64>]) -> Option<(f64, Vec<f64>, Vec<f64>)> {
+        let (row, lower_value) = payoffs.iter()
+            .map(|row| row.iter().copied().fold(f64::INFINITY, f64::min))
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))?;
+        let (column, upper_value) = (0..payoffs[0].len())
+            .map(|column| payoffs.iter().map(|row| row[column]).fold(f64::NEG_INFINITY, f64::max))
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))?;
+
+        if upper_value - lower_value > EPSILON {
+            return None;
+        }
+
+        let pure = |index: usize, length: usize| (0..length).map(|other| if other == index { 1.0 } else { 0.0 }).collect();
+        Some((lower_value, pure(row, payoffs.len()), pure(column, payoffs[0].len())))
+    }
+
+    // After shifting all payoffs to be positive, the column player's optimal strategy q and the value v follow from
+    // maximizing the sum of y = q / v subject to A y <= 1. The row player's strategy is the dual solution.
+    fn simplex(payoffs: &[Vec<f64>]) -> (f64, Vec<f64>, Vec<f64>) {
+        let (rows, columns) = (payoffs.len(), payoffs[0].len());
+        let minimum = payoffs.iter().flatten().copied().fold(f64::INFINITY, f64::min);
+        let shift = 1.0 - minimum;
+
+        // Each row holds the coefficients of y, then of the slack variables and finally the right hand side
+        let width = columns + rows + 1;
+        let mut tableau: Vec<Vec<f64>> = payoffs.iter().enumerate()
+            .map(|(row, payoffs)| {
+                let mut tableau_row: Vec<f64> = payoffs.iter().map(|payoff| payoff + shift).collect();
+                tableau_row.extend((0..rows).map(|slack| if slack == row { 1.0 } else { 0.0 }));
+                tableau_row.push(1.0);
+                tableau_row
+            })
+            .collect();
+        let mut objective: Vec<f64> = (0..width).map(|index| if index < columns { -1.0 } else { 0.0 }).collect();
+        let mut basis: Vec<usize> = (columns..columns + rows).collect();
+
+        // Bland's rule, which can't cycle
+        while let Some(entering) = (0..width - 1).find(|index| objective[*index] < -EPSILON) {
+            let leaving = (0..rows)
+                .filter(|row| tableau[*row][entering] > EPSILON)
+                .min_by(|a, b| {
+                    let ratio = |row: usize| tableau[row][width - 1] / tableau[row][entering];
+                    ratio(*a).total_cmp(&ratio(*b)).then(basis[*a].cmp(&basis[*b]))
+                })
+                .expect("Matrix games are bounded");
+
+            let pivot = tableau[leaving][entering];
+            tableau[leaving].iter_mut().for_each(|value| *value /= pivot);
+            let pivot_row = tableau[leaving].clone();
+            for (row, tableau_row) in tableau.iter_mut().enumerate() {
+                let factor = tableau_row[entering];
+                if row != leaving && factor != 0.0 {
+                    tableau_row.iter_mut().zip(&pivot_row).for_each(|(value, pivot_value)| *value -= factor * pivot_value);
+                }
+            }
+            let factor = objective[entering];
+            objective.iter_mut().zip(&pivot_row).for_each(|(value, pivot_value)| *value -= factor * pivot_value);
+            basis[leaving] = entering;
+        }
+
+        let shifted_value = 1.0 / objective[width - 1];
+        let mut column_strategy = vec!(0.0; columns);
+        basis.iter().enumerate()
+            .filter(|(_, variable)| **variable < columns)
+            .for_each(|(row, variable)| column_strategy[*variable] = tableau[row][width - 1] * shifted_value);
+        let row_strategy = (0..rows).map(|row| objective[columns + row] * shifted_value).collect();
+
+        (shifted_value - shift, row_strategy, column_strategy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use rand::thread_rng;
+    use crate::game::card::{CardProvider, CardSquareProviderImpl};
+    use crate::game::rules::RuleVariant;
+    use crate::game::squares::MST;
+    use crate::matrix::MatrixSize;
+    use super::*;
+
+    fn assert_close(expected: f64, actual: f64) {
+        assert!((expected - actual).abs() < 1e-6, "expected {expected}, got {actual}");
+    }
+
+    #[test]
+    fn solves_matrix_games() {
+        // Matching pennies has no saddle point, so both players mix evenly
+        let pennies = MatrixGame::solve(&[vec!(1.0, -1.0), vec!(-1.0, 1.0)]);
+        assert_close(0.0, pennies.value);
+        pennies.row_strategy.iter().chain(&pennies.column_strategy).for_each(|probability| assert_close(0.5, *probability));
+
+        // The second row is dominated by the first
+        let saddle = MatrixGame::solve(&[vec!(3.0, 2.0), vec!(1.0, 0.0)]);
+        assert_eq!(MatrixGame { value: 2.0, row_strategy: vec!(1.0, 0.0), column_strategy: vec!(0.0, 1.0) }, saddle);
+
+        let mixed = MatrixGame::solve(&[vec!(4.0, 0.0), vec!(1.0, 3.0), vec!(0.0, 1.0)]);
+        assert_close(2.0, mixed.value);
+        assert_close(1.0 / 3.0, mixed.row_strategy[0]);
+        assert_close(2.0 / 3.0, mixed.row_strategy[1]);
+        assert_close(0.0, mixed.row_strategy[2]);
+        assert_close(0.5, mixed.column_strategy[0]);
+    }
+
+    fn create(remaining_turns: usize) -> GameState {
+        let card_provider: Arc<dyn CardProvider + Send + Sync> = Arc::new(CardSquareProviderImpl::new());
+        let cards: Vec<String> = card_provider.get_all().into_iter().map(|card| card.name).take(6).collect();
+        let mut board = Matrix::filled_with(MatrixSize::new(7, 7), MST::Empty);
+        board[(1, 1)] = MST::SpecialAlpha;
+        board[(5, 5)] = MST::SpecialBravo;
+
+        let decks = HashMap::from([
+            (PlayerTeam::Alpha, cards[..3].iter().cloned().collect()),
+            (PlayerTeam::Bravo, cards[3..].iter().cloned().collect()),
+        ]);
+        let mut game = GameState::new(board, card_provider.clone(), RuleVariant::Standard.validator(card_provider), decks);
+        game.set_hand(&PlayerTeam::Alpha, cards[..2].iter().cloned().collect());
+        game.set_hand(&PlayerTeam::Bravo, cards[3..5].iter().cloned().collect());
+        game.remaining_turns = remaining_turns;
+        game
+    }
+
+    fn margin_after(game: &GameState, alpha_move: &PlayerMove, bravo_move: &PlayerMove) -> f64 {
+        let mut game = game.clone();
+        game.replay_moves(HashMap::from([(PlayerTeam::Alpha, alpha_move.clone()), (PlayerTeam::Bravo, bravo_move.clone())]));
+        let score = game.score();
+        score[&PlayerTeam::Alpha] as f64 - score[&PlayerTeam::Bravo] as f64
+    }
+
+    #[test]
+    fn finds_optimal_strategies_for_last_turn() {
+        let game = create(1);
+        let solution = solve(&game, DEFAULT_MAX_NODES).unwrap();
+
+        // Neither team can do better against the other's strategy by switching to any of their moves
+        let expected = |team: &PlayerTeam, player_move: &PlayerMove| solution.strategies[team].iter()
+            .map(|weighted_move| weighted_move.probability * match team {
+                PlayerTeam::Alpha => margin_after(&game, &weighted_move.player_move, player_move),
+                PlayerTeam::Bravo => margin_after(&game, player_move, &weighted_move.player_move),
+            })
+            .sum::<f64>();
+        for alpha_move in game.valid_moves(&PlayerTeam::Alpha) {
+            assert!(expected(&PlayerTeam::Bravo, &alpha_move) <= solution.margin + 1e-6);
+        }
+        for bravo_move in game.valid_moves(&PlayerTeam::Bravo) {
+            assert!(expected(&PlayerTeam::Alpha, &bravo_move) >= solution.margin - 1e-6);
+        }
+
+        let total: f64 = solution.strategies[&PlayerTeam::Alpha].iter().map(|weighted_move| weighted_move.probability).sum();
+        assert_close(1.0, total);
+        let alpha_move = solution.choose_move(&PlayerTeam::Alpha, &mut thread_rng()).unwrap();
+        assert!(solution.strategies[&PlayerTeam::Alpha].iter().any(|weighted_move| weighted_move.player_move == alpha_move));
+    }
+
+    #[test]
+    fn rejects_unsolvable_games() {
+        assert_eq!(Err(EndgameError::TooManyTurns), solve(&create(MAX_ENDGAME_TURNS + 1), DEFAULT_MAX_NODES));
+        assert_eq!(Err(EndgameError::GameEnded), solve(&create(0), DEFAULT_MAX_NODES));
+        assert_eq!(Err(EndgameError::NodeLimitReached), solve(&create(2), 100));
+
+        let mut game = create(1);
+        game.set_hand(&PlayerTeam::Bravo, IndexSet::new());
+        assert_eq!(Err(EndgameError::HandsUnknown), solve(&game, DEFAULT_MAX_NODES));
+    }
+}
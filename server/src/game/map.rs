@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use itertools::Itertools;
-use serde::Deserialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use crate::game::squares::MapSquareType;
 use crate::matrix::Matrix;
 
@@ -8,9 +9,10 @@ static MAPS_JSON: &str = include_str!("maps.json");
 
 pub const DEFAULT_GAME_MAP: &str = "Rectangle";
 
-#[derive(Deserialize, Clone)]
+#[derive(Deserialize, Serialize, Clone, JsonSchema)]
 pub struct TableturfMap {
     pub name: String,
+    #[schemars(with = "Vec<Vec<MapSquareType>>")]
     pub squares: Matrix<MapSquareType>,
 }
 
@@ -20,6 +22,9 @@ pub trait MapProvider {
     fn exists(&self, map_name: &str) -> bool;
 
     fn get_names(&self) -> Vec<String>;
+
+    // Sorted by name
+    fn get_all(&self) -> Vec<TableturfMap>;
 }
 
 pub struct MapProviderImpl {
@@ -38,6 +43,12 @@ impl MapProviderImpl {
     }
 }
 
+impl Default for MapProviderImpl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl MapProvider for MapProviderImpl {
     fn get(&self, map_name: &str) -> Option<TableturfMap> {
         self.maps.get(map_name).cloned()
@@ -48,6 +59,10 @@ impl MapProvider for MapProviderImpl {
     }
 
     fn get_names(&self) -> Vec<String> {
-        self.maps.keys().into_iter().cloned().collect_vec()
+        self.maps.keys().cloned().collect_vec()
+    }
+
+    fn get_all(&self) -> Vec<TableturfMap> {
+        self.maps.values().cloned().sorted_by(|a, b| a.name.cmp(&b.name)).collect()
     }
 }
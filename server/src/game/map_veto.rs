@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use ts_rs::TS;
+use crate::game::team::PlayerTeam;
+
+// When the loser of the previous game picks, bans stop once this many maps remain
+pub const LOSER_PICK_MAPS: usize = 3;
+
+#[derive(Serialize, Debug, Eq, PartialEq, JsonSchema, TS)]
+pub enum MapVetoError {
+    VetoNotRunning,
+    VetoInProgress,
+    NotEnoughMaps,
+    NotMapBanner,
+    MapNotInVeto,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, JsonSchema, TS)]
+pub struct MapBan {
+    pub team: PlayerTeam,
+    pub map: String,
+}
+
+// The players alternately ban maps from the room's map pool, starting with Alpha. The last remaining map is played,
+// unless a previous game had a loser: they pick the map once only a few are left.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct MapVeto {
+    pub maps: Vec<String>,
+    pub bans: Vec<MapBan>,
+    pub banner: PlayerTeam,
+    pub loser: Option<PlayerTeam>,
+}
+
+impl MapVeto {
+    pub fn new(map_pool: &[String], loser: Option<PlayerTeam>) -> Result<Self, MapVetoError> {
+        if map_pool.len() < 2 {
+            return Err(MapVetoError::NotEnoughMaps);
+        }
+
+        Ok(Self {
+            maps: map_pool.to_vec(),
+            bans: Vec::new(),
+            banner: PlayerTeam::Alpha,
+            loser,
+        })
+    }
+
+    // Whether the bans are over and the loser has to pick one of the remaining maps
+    pub fn picking(&self) -> bool {
+        self.loser.is_some() && self.maps.len() <= LOSER_PICK_MAPS
+    }
+
+    // Returns the map to play once it has been decided
+    pub fn ban(&mut self, team: PlayerTeam, map: &str) -> Result<Option<String>, MapVetoError> {
+        if team != self.banner || self.picking() {
+            return Err(MapVetoError::NotMapBanner);
+        }
+
+        let index = self.maps.iter().position(|candidate| candidate == map).ok_or(MapVetoError::MapNotInVeto)?;
+        self.maps.remove(index);
+        self.bans.push(MapBan { team: team.clone(), map: map.to_string() });
+        self.banner = match team {
+            PlayerTeam::Alpha => PlayerTeam::Bravo,
+            PlayerTeam::Bravo => PlayerTeam::Alpha,
+        };
+
+        Ok(match self.maps.as_slice() {
+            [map] => Some(map.clone()),
+            _ => None,
+        })
+    }
+
+    pub fn pick(&self, team: PlayerTeam, map: &str) -> Result<String, MapVetoError> {
+        if !self.picking() || self.loser.as_ref() != Some(&team) {
+            Err(MapVetoError::NotMapBanner)
+        } else if !self.maps.iter().any(|candidate| candidate == map) {
+            Err(MapVetoError::MapNotInVeto)
+        } else {
+            Ok(map.to_string())
+        }
+    }
+
+    // The team that acts next, either by banning or by picking
+    pub fn next_team(&self) -> PlayerTeam {
+        match &self.loser {
+            Some(loser) if self.picking() => loser.clone(),
+            _ => self.banner.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map_pool(count: usize) -> Vec<String> {
+        (0..count).map(|index| format!("Map_{index}")).collect()
+    }
+
+    #[test]
+    fn requires_two_maps() {
+        assert_eq!(Err(MapVetoError::NotEnoughMaps), MapVeto::new(&map_pool(1), None));
+        assert!(MapVeto::new(&map_pool(2), None).is_ok());
+    }
+
+    #[test]
+    fn bans_until_one_map_remains() {
+        let mut veto = MapVeto::new(&map_pool(4), None).unwrap();
+
+        assert_eq!(Err(MapVetoError::NotMapBanner), veto.ban(PlayerTeam::Bravo, "Map_0"));
+        assert_eq!(Ok(None), veto.ban(PlayerTeam::Alpha, "Map_0"));
+        assert_eq!(Err(MapVetoError::MapNotInVeto), veto.ban(PlayerTeam::Bravo, "Map_0"));
+        assert_eq!(Ok(None), veto.ban(PlayerTeam::Bravo, "Map_3"));
+        assert_eq!(Ok(Some("Map_2".to_string())), veto.ban(PlayerTeam::Alpha, "Map_1"));
+        assert_eq!(
+            vec!(PlayerTeam::Alpha, PlayerTeam::Bravo, PlayerTeam::Alpha),
+            veto.bans.into_iter().map(|ban| ban.team).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn loser_picks_from_last_maps() {
+        let mut veto = MapVeto::new(&map_pool(5), Some(PlayerTeam::Bravo)).unwrap();
+        assert_eq!(Err(MapVetoError::NotMapBanner), veto.pick(PlayerTeam::Bravo, "Map_0"));
+
+        assert_eq!(Ok(None), veto.ban(PlayerTeam::Alpha, "Map_0"));
+        assert_eq!(Ok(None), veto.ban(PlayerTeam::Bravo, "Map_1"));
+        assert!(veto.picking());
+        assert_eq!(PlayerTeam::Bravo, veto.next_team());
+
+        assert_eq!(Err(MapVetoError::NotMapBanner), veto.ban(PlayerTeam::Alpha, "Map_2"));
+        assert_eq!(Err(MapVetoError::NotMapBanner), veto.pick(PlayerTeam::Alpha, "Map_2"));
+        assert_eq!(Err(MapVetoError::MapNotInVeto), veto.pick(PlayerTeam::Bravo, "Map_1"));
+        assert_eq!(Ok("Map_3".to_string()), veto.pick(PlayerTeam::Bravo, "Map_3"));
+    }
+}
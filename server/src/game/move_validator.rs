@@ -1,14 +1,16 @@
-use std::cmp;
 use std::sync::Arc;
 use serde::Serialize;
+use schemars::JsonSchema;
+use ts_rs::TS;
 use crate::game::card::{CardProvider, CardSquareType};
-use crate::game::squares::{MapSquareType, MST};
+use crate::game::rules::Rules;
+use crate::game::squares::MapSquareType;
 use crate::game::state::{PlayerDeck, PlayerMove};
 use crate::game::team::PlayerTeam;
 use crate::matrix::{Matrix, Slice};
 use crate::position::INamedPosition;
 
-#[derive(Serialize, Debug, Eq, PartialEq)]
+#[derive(Serialize, Debug, Eq, PartialEq, JsonSchema, TS)]
 pub enum InvalidMoveError {
     CardNotFound,
     CardNotInHand,
@@ -27,10 +29,16 @@ pub trait MoveValidator {
         player_move: &PlayerMove,
         deck: &PlayerDeck,
     ) -> Result<(), InvalidMoveError>;
+
+    // The rules moves are validated by, which the game state applies them with
+    fn rules(&self) -> Rules {
+        Rules::default()
+    }
 }
 
 pub struct MoveValidatorImpl {
     card_provider: Arc<dyn CardProvider + Send + Sync>,
+    rules: Rules,
 }
 
 impl MoveValidator for MoveValidatorImpl {
@@ -48,19 +56,19 @@ impl MoveValidator for MoveValidatorImpl {
 
         match player_move {
             PlayerMove::PlaceCard { position, rotation, card_name, special } => {
-                match self.card_provider.get(&card_name) {
+                match self.card_provider.get(card_name) {
                     Some(card) => {
-                        if *special && card.special_cost > available_special_points {
+                        if *special && (!self.rules.special_attacks || card.special_cost > available_special_points) {
                             return Err(InvalidMoveError::CannotAffordSpecial);
                         }
 
-                        let squares = card.squares.rotate_clockwise(rotation.clone().into());
+                        let squares = card.squares.rotate_clockwise((*rotation).into());
 
                         if !Self::card_within_bounds(position, board, &squares) {
                             Err(InvalidMoveError::CardOutOfBounds)
-                        } else if !Self::card_on_correct_squares(position, *special, board, &squares) {
+                        } else if !self.card_on_correct_squares(position, *special, board, &squares) {
                             Err(InvalidMoveError::CardOnDisallowedSquares)
-                        } else if !Self::correct_squares_near_card(position, *special, board, &squares, team) {
+                        } else if !self.correct_squares_near_card(position, *special, board, &squares, team) {
                             Err(InvalidMoveError::NoExpectedSquaresNearCard)
                         } else {
                             Ok(())
@@ -80,12 +88,21 @@ impl MoveValidator for MoveValidatorImpl {
             }
         }
     }
+
+    fn rules(&self) -> Rules {
+        self.rules
+    }
 }
 
 impl MoveValidatorImpl {
     pub fn new(card_square_provider: Arc<dyn CardProvider + Send + Sync>) -> Self {
+        Self::with_rules(card_square_provider, Rules::default())
+    }
+
+    pub fn with_rules(card_square_provider: Arc<dyn CardProvider + Send + Sync>, rules: Rules) -> Self {
         Self {
             card_provider: card_square_provider,
+            rules,
         }
     }
 
@@ -101,18 +118,14 @@ impl MoveValidatorImpl {
             && (position.y + card_size.h as isize) <= board_size.h as isize
     }
 
-    fn card_on_correct_squares(position: &INamedPosition, special: bool, board: &Matrix<MapSquareType>, card_squares: &Matrix<CardSquareType>) -> bool {
+    fn card_on_correct_squares(&self, position: &INamedPosition, special: bool, board: &Matrix<MapSquareType>, card_squares: &Matrix<CardSquareType>) -> bool {
         let card_size = card_squares.size();
         let pos_from = (position.x as usize, position.y as usize);
         let squares_under_card = board.slice(pos_from..(pos_from.0 + card_size.w, pos_from.1 + card_size.h));
-        let accepted_covering_map_squares = if special {
-            vec!(MST::Empty, MST::FillAlpha, MST::FillBravo)
-        } else {
-            vec!(MST::Empty)
-        };
+        let accepted_covering_map_squares = self.rules.coverable_squares(special);
 
         squares_under_card.into_iter()
-            .zip(card_squares.clone().into_iter())
+            .zip(card_squares.clone())
             .all(|((map_square, _), (card_square, _))| {
                 if card_square == CardSquareType::Empty {
                     true
@@ -122,23 +135,22 @@ impl MoveValidatorImpl {
             })
     }
 
-    fn correct_squares_near_card(position: &INamedPosition, special: bool, board: &Matrix<MapSquareType>, card_squares: &Matrix<CardSquareType>, team: &PlayerTeam) -> bool {
+    fn correct_squares_near_card(&self, position: &INamedPosition, special: bool, board: &Matrix<MapSquareType>, card_squares: &Matrix<CardSquareType>, team: &PlayerTeam) -> bool {
         let pos_from = (position.x as usize, position.y as usize);
 
         let accepted_nearby_squares = match team {
-            PlayerTeam::Alpha => if special {
+            PlayerTeam::Alpha => if special && self.rules.specials_need_special_squares {
                 vec!(MapSquareType::SpecialAlpha)
             } else {
                 vec!(MapSquareType::FillAlpha, MapSquareType::SpecialAlpha)
             },
-            PlayerTeam::Bravo => if special {
+            PlayerTeam::Bravo => if special && self.rules.specials_need_special_squares {
                 vec!(MapSquareType::SpecialBravo)
             } else {
                 vec!(MapSquareType::FillBravo, MapSquareType::SpecialBravo)
             },
         };
 
-        let board_size = board.size();
         card_squares.clone().into_iter()
             .any(|(square, position)| {
                 if square == CardSquareType::Empty {
@@ -146,16 +158,14 @@ impl MoveValidatorImpl {
                 }
 
                 let square_pos = (pos_from.0 + position.0, pos_from.1 + position.1);
-                board.slice((square_pos.0.checked_sub(1).unwrap_or(0), square_pos.1.checked_sub(1).unwrap_or(0))..=(cmp::min(square_pos.0 + 1, board_size.w - 1), cmp::min(square_pos.1 + 1, board_size.h - 1)))
-                    .into_iter()
-                    .any(|(map_square, _)| accepted_nearby_squares.contains(&map_square))
+                self.rules.adjacent_squares(board, square_pos).iter()
+                    .any(|map_square| accepted_nearby_squares.contains(map_square))
             })
     }
 }
 
 #[cfg(test)]
 pub mod tests {
-    use std::borrow::Borrow;
     use indexmap::IndexSet;
     use parameterized::parameterized as pm;
     use crate::game::squares::MST;
@@ -175,7 +185,7 @@ pub mod tests {
             player_move: &PlayerMove,
             _deck: &PlayerDeck,
         ) -> Result<(), InvalidMoveError> {
-            match player_move.card_name().borrow() {
+            match player_move.card_name() {
                 "invalid_pos_card" => Err(InvalidMoveError::CardOutOfBounds),
                 "not_found_card" => Err(InvalidMoveError::CardNotFound),
                 _ => Ok(()),
@@ -319,6 +329,57 @@ pub mod tests {
         }
     }
 
+    mod rule_variants {
+        use crate::game::rules::RuleVariant;
+        use super::*;
+
+        fn validate(variant: RuleVariant, board: &Matrix<MapSquareType>, x: isize, y: isize, special: bool) -> Result<(), InvalidMoveError> {
+            variant.validator(TestCardSquareProvider::new()).validate(
+                board,
+                5,
+                &PlayerTeam::Alpha,
+                &player_move("card_3", INamedPosition::new(x, y), CardRotation::Deg0, special),
+                &player_deck("card_3"),
+            )
+        }
+
+        #[test]
+        fn no_special_attacks() {
+            assert_eq!(Ok(()), validate(RuleVariant::Standard, &board(), 2, 2, true));
+            assert_eq!(Err(InvalidMoveError::CannotAffordSpecial), validate(RuleVariant::NoSpecialAttacks, &board(), 2, 2, true));
+            assert_eq!(Ok(()), validate(RuleVariant::NoSpecialAttacks, &board(), 2, 2, false));
+        }
+
+        #[test]
+        fn specials_cover_anything() {
+            let mut board = board();
+            board[(2, 2)] = MST::Neutral;
+            board[(2, 1)] = MST::SpecialBravo;
+
+            for (x, y) in [(2, 2), (2, 1)] {
+                assert_eq!(Err(InvalidMoveError::CardOnDisallowedSquares), validate(RuleVariant::Standard, &board, x, y, true));
+                assert_eq!(Ok(()), validate(RuleVariant::SpecialsCoverAnything, &board, x, y, true));
+                assert_eq!(Err(InvalidMoveError::CardOnDisallowedSquares), validate(RuleVariant::SpecialsCoverAnything, &board, x, y, false));
+            }
+            assert_eq!(Err(InvalidMoveError::CardOnDisallowedSquares), validate(RuleVariant::SpecialsCoverAnything, &board, 0, 1, true));
+        }
+
+        #[test]
+        fn specials_next_to_any_square() {
+            assert_eq!(Err(InvalidMoveError::NoExpectedSquaresNearCard), validate(RuleVariant::Standard, &board(), 4, 2, true));
+            assert_eq!(Ok(()), validate(RuleVariant::SpecialsNextToAnySquare, &board(), 4, 2, true));
+            assert_eq!(Err(InvalidMoveError::NoExpectedSquaresNearCard), validate(RuleVariant::SpecialsNextToAnySquare, &board(), 3, 3, true));
+        }
+
+        #[test]
+        fn no_diagonal_adjacency() {
+            assert_eq!(Ok(()), validate(RuleVariant::Standard, &board(), 2, 2, false));
+            assert_eq!(Err(InvalidMoveError::NoExpectedSquaresNearCard), validate(RuleVariant::NoDiagonalAdjacency, &board(), 2, 2, false));
+            assert_eq!(Ok(()), validate(RuleVariant::NoDiagonalAdjacency, &board(), 2, 1, false));
+            assert_eq!(Ok(()), validate(RuleVariant::NoDiagonalAdjacency, &board(), 1, 2, true));
+        }
+    }
+
     mod team_alpha {
         use super::*;
 
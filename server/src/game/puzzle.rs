@@ -0,0 +1,391 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use indexmap::IndexSet;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use ts_rs::TS;
+use crate::game::card::CardProvider;
+use crate::game::rules::RuleVariant;
+use crate::game::squares::MapSquareType;
+use crate::game::state::{GameError, GameState, PlayerMove, TURN_COUNT};
+use crate::game::team::PlayerTeam;
+use crate::matrix::Matrix;
+
+// The solver gives up after trying this many moves by default
+pub const DEFAULT_MAX_NODES: usize = 1_000_000;
+
+#[derive(Serialize, Debug, Eq, PartialEq, JsonSchema, TS)]
+#[serde(tag = "code", content = "detail")]
+pub enum PuzzleError {
+    InvalidBoard,
+    InvalidTurnCount,
+    CardNotFound(String),
+    // The player's cards don't cover every turn
+    NotEnoughCards,
+    WrongMoveCount,
+    CardNotPlayable { turn: usize, card_name: String },
+    InvalidMove { turn: usize, error: GameError },
+}
+
+// The cards the player may use: any card from the hand once each, or exactly the given card on every turn
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, JsonSchema)]
+#[serde(tag = "type", content = "cards")]
+pub enum PuzzleCards {
+    Hand(Vec<String>),
+    Sequence(Vec<String>),
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, Eq, PartialEq, JsonSchema, TS)]
+#[serde(tag = "type", content = "squares")]
+pub enum PuzzleObjective {
+    // The player must finish with at least this many squares more than the opponent
+    FinishAhead(usize),
+    // The player must finish with at least this many squares
+    InkAtLeast(usize),
+}
+
+// A puzzle is played as Alpha against an opponent whose moves are scripted. Each scripted move is validated when its
+// turn comes; if the player's earlier moves made it impossible, the opponent passes with that card instead.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Puzzle {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub board: Vec<Vec<MapSquareType>>,
+    #[serde(default)]
+    pub rules: RuleVariant,
+    pub cards: PuzzleCards,
+    pub opponent_moves: Vec<PlayerMove>,
+    pub objective: PuzzleObjective,
+}
+
+#[derive(Clone, Debug, Serialize, Eq, PartialEq, JsonSchema, TS)]
+pub struct PuzzleOutcome {
+    pub solved: bool,
+    pub score: HashMap<PlayerTeam, usize>,
+}
+
+// What clients are shown of a puzzle being played in a room
+#[derive(Clone, Debug, Serialize, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct PuzzleState {
+    pub name: String,
+    pub description: String,
+    pub objective: PuzzleObjective,
+    #[schemars(with = "Vec<Vec<MapSquareType>>")]
+    #[ts(as = "Vec<Vec<MapSquareType>>")]
+    pub board: Matrix<MapSquareType>,
+    pub score: HashMap<PlayerTeam, usize>,
+    pub turn: usize,
+    pub turns: usize,
+    #[ts(as = "Vec<String>")]
+    pub playable_cards: IndexSet<String>,
+    pub special_points: usize,
+    // Both teams' moves of the previous turn, including the opponent's scripted one
+    pub last_moves: Option<HashMap<PlayerTeam, PlayerMove>>,
+    // Set once every turn has been played
+    pub outcome: Option<PuzzleOutcome>,
+}
+
+// Runs are restored by replaying the player's moves
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PuzzleSnapshot {
+    puzzle: Puzzle,
+    moves: Vec<PlayerMove>,
+}
+
+impl PuzzleSnapshot {
+    pub fn restore(self, card_provider: Arc<dyn CardProvider + Send + Sync>) -> Result<PuzzleRun, PuzzleError> {
+        let mut run = self.puzzle.start(card_provider)?;
+        for player_move in self.moves {
+            run.play(player_move)?;
+        }
+
+        Ok(run)
+    }
+}
+
+impl PuzzleObjective {
+    pub fn reached(&self, score: &HashMap<PlayerTeam, usize>) -> bool {
+        let (player, opponent) = (score[&PlayerTeam::Alpha], score[&PlayerTeam::Bravo]);
+
+        match self {
+            PuzzleObjective::FinishAhead(squares) => player >= opponent + squares,
+            PuzzleObjective::InkAtLeast(squares) => player >= *squares,
+        }
+    }
+}
+
+impl Puzzle {
+    pub fn turns(&self) -> usize {
+        self.opponent_moves.len()
+    }
+
+    pub fn validate(&self, card_provider: &(dyn CardProvider + Send + Sync)) -> Result<(), PuzzleError> {
+        let row_length = self.board.first().map_or(0, Vec::len);
+        if row_length == 0 || self.board.iter().any(|row| row.len() != row_length) {
+            return Err(PuzzleError::InvalidBoard);
+        } else if self.turns() == 0 || self.turns() > TURN_COUNT {
+            return Err(PuzzleError::InvalidTurnCount);
+        }
+
+        let player_cards = match &self.cards {
+            PuzzleCards::Hand(cards) if cards.iter().unique().count() < self.turns() => Err(PuzzleError::NotEnoughCards),
+            PuzzleCards::Sequence(cards) if cards.len() != self.turns() => Err(PuzzleError::NotEnoughCards),
+            PuzzleCards::Hand(cards) | PuzzleCards::Sequence(cards) => Ok(cards),
+        }?;
+
+        let opponent_cards = self.opponent_moves.iter().map(|player_move| player_move.card_name());
+        player_cards.iter().map(String::as_str)
+            .chain(opponent_cards)
+            .find(|card| !card_provider.exists(card))
+            .map_or(Ok(()), |card| Err(PuzzleError::CardNotFound(card.to_owned())))
+    }
+
+    pub fn start(&self, card_provider: Arc<dyn CardProvider + Send + Sync>) -> Result<PuzzleRun, PuzzleError> {
+        self.validate(card_provider.as_ref())?;
+
+        let decks = HashMap::from([(PlayerTeam::Alpha, IndexSet::new()), (PlayerTeam::Bravo, IndexSet::new())]);
+        let validator = self.rules.validator(card_provider.clone());
+        Ok(PuzzleRun {
+            puzzle: self.clone(),
+            game: GameState::new(Matrix::new(self.board.clone()), card_provider, validator, decks),
+            turn: 0,
+            used_cards: IndexSet::new(),
+            moves: Vec::new(),
+            last_moves: None,
+        })
+    }
+
+    // Plays the given moves of the player, one for each turn
+    pub fn check_solution(&self, solution: &[PlayerMove], card_provider: Arc<dyn CardProvider + Send + Sync>) -> Result<PuzzleOutcome, PuzzleError> {
+        if solution.len() != self.turns() {
+            return Err(PuzzleError::WrongMoveCount);
+        }
+
+        let mut run = self.start(card_provider)?;
+        for player_move in solution {
+            run.play(player_move.clone())?;
+        }
+
+        Ok(run.outcome())
+    }
+
+    // Searches for moves reaching the objective, trying at most max_nodes moves. Returns None when the puzzle can't be
+    // solved, or when the search gave up.
+    pub fn solve(&self, card_provider: Arc<dyn CardProvider + Send + Sync>, max_nodes: usize) -> Result<Option<Vec<PlayerMove>>, PuzzleError> {
+        let run = self.start(card_provider)?;
+        let mut remaining_nodes = max_nodes;
+        Ok(Self::search(&run, &mut Vec::new(), &mut remaining_nodes))
+    }
+
+    fn search(run: &PuzzleRun, moves: &mut Vec<PlayerMove>, remaining_nodes: &mut usize) -> Option<Vec<PlayerMove>> {
+        if run.finished() {
+            return run.outcome().solved.then(|| moves.clone());
+        }
+
+        for player_move in run.valid_moves() {
+            if *remaining_nodes == 0 {
+                return None;
+            }
+            *remaining_nodes -= 1;
+
+            let mut next_run = run.clone();
+            next_run.play(player_move.clone()).unwrap();
+            moves.push(player_move);
+            if let Some(solution) = Self::search(&next_run, moves, remaining_nodes) {
+                return Some(solution);
+            }
+            moves.pop();
+        }
+
+        None
+    }
+}
+
+// A puzzle being played, turn by turn
+#[derive(Clone)]
+pub struct PuzzleRun {
+    puzzle: Puzzle,
+    pub game: GameState,
+    pub turn: usize,
+    used_cards: IndexSet<String>,
+    // The player's moves so far
+    moves: Vec<PlayerMove>,
+    last_moves: Option<HashMap<PlayerTeam, PlayerMove>>,
+}
+
+impl PuzzleRun {
+    pub fn finished(&self) -> bool {
+        self.turn >= self.puzzle.turns()
+    }
+
+    pub fn outcome(&self) -> PuzzleOutcome {
+        let score = self.game.score();
+        PuzzleOutcome { solved: self.finished() && self.puzzle.objective.reached(&score), score }
+    }
+
+    // The cards the player may use this turn
+    pub fn playable_cards(&self) -> IndexSet<String> {
+        match &self.puzzle.cards {
+            PuzzleCards::Hand(cards) => cards.iter().filter(|card| !self.used_cards.contains(*card)).cloned().collect(),
+            PuzzleCards::Sequence(cards) => cards.get(self.turn).into_iter().cloned().collect(),
+        }
+    }
+
+    pub fn play(&mut self, player_move: PlayerMove) -> Result<(), PuzzleError> {
+        let turn = self.turn;
+        let card_name = player_move.card_name().to_owned();
+        if self.finished() || !self.playable_cards().contains(&card_name) {
+            return Err(PuzzleError::CardNotPlayable { turn, card_name });
+        }
+
+        self.game.set_hand(&PlayerTeam::Alpha, IndexSet::from([card_name.clone()]));
+        self.game.propose_move(PlayerTeam::Alpha, player_move.clone())
+            .map_err(|error| PuzzleError::InvalidMove { turn, error })?;
+
+        let mut opponent_move = self.puzzle.opponent_moves[turn].clone();
+        self.game.set_hand(&PlayerTeam::Bravo, IndexSet::from([opponent_move.card_name().to_owned()]));
+        if self.game.validate_move(&PlayerTeam::Bravo, &opponent_move).is_err() {
+            opponent_move = PlayerMove::Pass { card_name: opponent_move.card_name().to_owned() };
+        }
+        self.game.propose_move(PlayerTeam::Bravo, opponent_move.clone()).unwrap();
+
+        self.game.apply_moves();
+        self.used_cards.insert(card_name);
+        self.turn += 1;
+        self.last_moves = Some(HashMap::from([(PlayerTeam::Alpha, player_move.clone()), (PlayerTeam::Bravo, opponent_move)]));
+        self.moves.push(player_move);
+        Ok(())
+    }
+
+    pub fn state(&self) -> PuzzleState {
+        PuzzleState {
+            name: self.puzzle.name.clone(),
+            description: self.puzzle.description.clone(),
+            objective: self.puzzle.objective,
+            board: self.game.board.clone(),
+            score: self.game.score(),
+            turn: self.turn,
+            turns: self.puzzle.turns(),
+            playable_cards: self.playable_cards(),
+            special_points: self.game.available_special_points(&PlayerTeam::Alpha),
+            last_moves: self.last_moves.clone(),
+            outcome: self.finished().then(|| self.outcome()),
+        }
+    }
+
+    pub fn snapshot(&self) -> PuzzleSnapshot {
+        PuzzleSnapshot { puzzle: self.puzzle.clone(), moves: self.moves.clone() }
+    }
+
+    // Every legal move of the player this turn, placements first and passes last
+    pub fn valid_moves(&self) -> Vec<PlayerMove> {
+        let mut game = self.game.clone();
+        game.set_hand(&PlayerTeam::Alpha, self.playable_cards());
+        game.valid_moves(&PlayerTeam::Alpha)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::card::CardSquareProviderImpl;
+    use crate::game::move_validator::InvalidMoveError;
+    use crate::game::state::CardRotation;
+    use crate::game::squares::MST;
+    use super::*;
+
+    fn card_provider() -> Arc<dyn CardProvider + Send + Sync> {
+        Arc::new(CardSquareProviderImpl::new())
+    }
+
+    fn card_name(number: usize) -> String {
+        CardSquareProviderImpl::new().get_all()[number].name.clone()
+    }
+
+    // A 7x7 board with the player in the top left and the opponent in the bottom right corner
+    fn puzzle(cards: PuzzleCards, objective: PuzzleObjective) -> Puzzle {
+        let mut board = vec!(vec!(MST::Empty; 7); 7);
+        board[0][0] = MST::SpecialAlpha;
+        board[6][6] = MST::SpecialBravo;
+
+        Puzzle {
+            name: "Test".to_string(),
+            description: String::new(),
+            board,
+            rules: RuleVariant::Standard,
+            cards,
+            opponent_moves: vec!(PlayerMove::Pass { card_name: card_name(1) }, PlayerMove::Pass { card_name: card_name(1) }),
+            objective,
+        }
+    }
+
+    #[test]
+    fn validates_puzzles() {
+        let valid = puzzle(PuzzleCards::Hand(vec!(card_name(0), card_name(1))), PuzzleObjective::InkAtLeast(1));
+        assert_eq!(Ok(()), valid.validate(card_provider().as_ref()));
+
+        let ragged_board = Puzzle { board: vec!(vec!(MST::Empty; 2), vec!(MST::Empty)), ..valid.clone() };
+        assert_eq!(Err(PuzzleError::InvalidBoard), ragged_board.validate(card_provider().as_ref()));
+        let no_turns = Puzzle { opponent_moves: Vec::new(), ..valid.clone() };
+        assert_eq!(Err(PuzzleError::InvalidTurnCount), no_turns.validate(card_provider().as_ref()));
+        let small_hand = Puzzle { cards: PuzzleCards::Hand(vec!(card_name(0), card_name(0))), ..valid.clone() };
+        assert_eq!(Err(PuzzleError::NotEnoughCards), small_hand.validate(card_provider().as_ref()));
+        let unknown_card = Puzzle { cards: PuzzleCards::Sequence(vec!(card_name(0), "Unknown".to_string())), ..valid };
+        assert_eq!(Err(PuzzleError::CardNotFound("Unknown".to_string())), unknown_card.validate(card_provider().as_ref()));
+    }
+
+    #[test]
+    fn checks_solutions() {
+        let puzzle = puzzle(PuzzleCards::Sequence(vec!(card_name(0), card_name(0))), PuzzleObjective::InkAtLeast(2));
+        let pass = PlayerMove::Pass { card_name: card_name(0) };
+        let misplaced = PlayerMove::PlaceCard { card_name: card_name(0), position: (2, 3).into(), rotation: CardRotation::Deg0, special: false };
+
+        assert_eq!(Err(PuzzleError::WrongMoveCount), puzzle.check_solution(std::slice::from_ref(&pass), card_provider()));
+        assert_eq!(
+            Err(PuzzleError::CardNotPlayable { turn: 0, card_name: card_name(1) }),
+            puzzle.check_solution(&[PlayerMove::Pass { card_name: card_name(1) }, pass.clone()], card_provider()));
+        assert_eq!(
+            Err(PuzzleError::InvalidMove { turn: 1, error: GameError::InvalidMove(InvalidMoveError::NoExpectedSquaresNearCard) }),
+            puzzle.check_solution(&[pass.clone(), misplaced], card_provider()));
+
+        let outcome = puzzle.check_solution(&[pass.clone(), pass], card_provider()).unwrap();
+        assert!(!outcome.solved);
+        assert_eq!(HashMap::from([(PlayerTeam::Alpha, 1), (PlayerTeam::Bravo, 1)]), outcome.score);
+    }
+
+    #[test]
+    fn plays_turn_by_turn() {
+        let solvable = puzzle(PuzzleCards::Hand(vec!(card_name(0), card_name(1), card_name(2))), PuzzleObjective::FinishAhead(8));
+        let solution = solvable.solve(card_provider(), DEFAULT_MAX_NODES).unwrap().unwrap();
+        let mut run = solvable.start(card_provider()).unwrap();
+        assert_eq!(None, run.state().last_moves);
+
+        run.play(solution[0].clone()).unwrap();
+        let state = run.state();
+        assert_eq!(1, state.turn);
+        assert_eq!(Some(&solvable.opponent_moves[0]), state.last_moves.as_ref().map(|moves| &moves[&PlayerTeam::Bravo]));
+        assert!(!state.playable_cards.contains(solution[0].card_name()));
+        assert_eq!(None, state.outcome);
+
+        let mut restored = run.snapshot().restore(card_provider()).unwrap();
+        assert_eq!(run.game.board, restored.game.board);
+        restored.play(solution[1].clone()).unwrap();
+        assert!(restored.state().outcome.is_some_and(|outcome| outcome.solved));
+    }
+
+    #[test]
+    fn solves_puzzles() {
+        let solvable = puzzle(PuzzleCards::Hand(vec!(card_name(0), card_name(1), card_name(2))), PuzzleObjective::FinishAhead(8));
+        let solution = solvable.solve(card_provider(), DEFAULT_MAX_NODES).unwrap().unwrap();
+
+        assert_eq!(2, solution.len());
+        assert!(solvable.check_solution(&solution, card_provider()).unwrap().solved);
+
+        // Two cards can't cover the whole board
+        let unsolvable = Puzzle { objective: PuzzleObjective::InkAtLeast(49), ..solvable };
+        assert_eq!(Ok(None), unsolvable.solve(card_provider(), DEFAULT_MAX_NODES));
+    }
+}
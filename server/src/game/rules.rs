@@ -0,0 +1,133 @@
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use ts_rs::TS;
+use crate::game::card::CardProvider;
+use crate::game::move_validator::{MoveValidator, MoveValidatorImpl};
+use crate::game::squares::MapSquareType;
+use crate::matrix::Matrix;
+
+// The rule variants a room can be played with. Each one is a set of Rules, which both the move validator and the
+// game state follow.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, Eq, PartialEq, Hash, JsonSchema, TS)]
+pub enum RuleVariant {
+    #[default]
+    Standard,
+    // Special attacks can't be played
+    NoSpecialAttacks,
+    // Special attacks may cover any square of the board, including special and neutral ones
+    SpecialsCoverAnything,
+    // Special attacks may be placed next to any of the player's squares, not only their special ones
+    SpecialsNextToAnySquare,
+    // Only squares sharing a side are adjacent, both when placing cards and when activating special squares
+    NoDiagonalAdjacency,
+}
+
+impl RuleVariant {
+    pub fn rules(&self) -> Rules {
+        let standard = Rules::default();
+
+        match self {
+            RuleVariant::Standard => standard,
+            RuleVariant::NoSpecialAttacks => Rules { special_attacks: false, ..standard },
+            RuleVariant::SpecialsCoverAnything => Rules { specials_cover_anything: true, ..standard },
+            RuleVariant::SpecialsNextToAnySquare => Rules { specials_need_special_squares: false, ..standard },
+            RuleVariant::NoDiagonalAdjacency => Rules { diagonal_adjacency: false, ..standard },
+        }
+    }
+
+    pub fn validator(&self, card_provider: Arc<dyn CardProvider + Send + Sync>) -> Arc<dyn MoveValidator + Send + Sync> {
+        Arc::new(MoveValidatorImpl::with_rules(card_provider, self.rules()))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Rules {
+    // Without special attacks, special points are never available
+    pub special_attacks: bool,
+    pub specials_cover_anything: bool,
+    // Whether special attacks need to be next to one of the player's special squares, or any of their squares
+    pub specials_need_special_squares: bool,
+    pub diagonal_adjacency: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            special_attacks: true,
+            specials_cover_anything: false,
+            specials_need_special_squares: true,
+            diagonal_adjacency: true,
+        }
+    }
+}
+
+impl Rules {
+    // The squares a card may cover, see MoveValidatorImpl
+    pub fn coverable_squares(&self, special: bool) -> Vec<MapSquareType> {
+        match (special, self.specials_cover_anything) {
+            (false, _) => vec!(MapSquareType::Empty),
+            (true, false) => vec!(MapSquareType::Empty, MapSquareType::FillAlpha, MapSquareType::FillBravo),
+            (true, true) => vec!(
+                MapSquareType::Empty, MapSquareType::FillAlpha, MapSquareType::FillBravo,
+                MapSquareType::SpecialAlpha, MapSquareType::SpecialBravo, MapSquareType::Neutral,
+            ),
+        }
+    }
+
+    // The squares adjacent to the given position, leaving out those outside the board
+    pub fn adjacent_squares(&self, board: &Matrix<MapSquareType>, (x, y): (usize, usize)) -> Vec<MapSquareType> {
+        let board_size = board.size();
+        let offsets: &[(isize, isize)] = if self.diagonal_adjacency {
+            &[(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)]
+        } else {
+            &[(0, -1), (-1, 0), (1, 0), (0, 1)]
+        };
+
+        offsets.iter()
+            .map(|(offset_x, offset_y)| (x as isize + offset_x, y as isize + offset_y))
+            .filter(|(x, y)| *x >= 0 && *y >= 0 && (*x as usize) < board_size.w && (*y as usize) < board_size.h)
+            .map(|(x, y)| board[(x as usize, y as usize)])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::squares::MST;
+    use super::*;
+
+    fn board() -> Matrix<MapSquareType> {
+        Matrix::new(vec!(
+            vec!(MST::SpecialAlpha, MST::FillAlpha, MST::Empty),
+            vec!(MST::FillBravo, MST::Empty, MST::Neutral),
+        ))
+    }
+
+    #[test]
+    fn adjacent_squares() {
+        let rules = Rules::default();
+
+        assert_eq!(vec!(MST::FillAlpha, MST::FillBravo, MST::Empty), rules.adjacent_squares(&board(), (0, 0)));
+        assert_eq!(
+            vec!(MST::SpecialAlpha, MST::Empty, MST::FillBravo, MST::Empty, MST::Neutral),
+            rules.adjacent_squares(&board(), (1, 0)));
+    }
+
+    #[test]
+    fn adjacent_squares_without_diagonals() {
+        let rules = RuleVariant::NoDiagonalAdjacency.rules();
+
+        assert_eq!(vec!(MST::FillAlpha, MST::FillBravo), rules.adjacent_squares(&board(), (0, 0)));
+        assert_eq!(vec!(MST::FillAlpha, MST::Neutral), rules.adjacent_squares(&board(), (2, 0)));
+    }
+
+    #[test]
+    fn variants_change_one_rule_each() {
+        assert_eq!(Rules::default(), RuleVariant::Standard.rules());
+        assert!(!RuleVariant::NoSpecialAttacks.rules().special_attacks);
+        assert!(RuleVariant::SpecialsCoverAnything.rules().coverable_squares(true).contains(&MST::SpecialBravo));
+        assert!(!RuleVariant::Standard.rules().coverable_squares(true).contains(&MST::SpecialBravo));
+        assert!(!RuleVariant::SpecialsNextToAnySquare.rules().specials_need_special_squares);
+    }
+}
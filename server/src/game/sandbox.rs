@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use indexmap::IndexSet;
+use serde::Serialize;
+use schemars::JsonSchema;
+use ts_rs::TS;
+use crate::game::card::CardProvider;
+use crate::game::move_validator::MoveValidator;
+use crate::game::squares::MapSquareType;
+use crate::game::state::{GameError, GameSnapshot, GameState, PlayerMove};
+use crate::game::team::PlayerTeam;
+use crate::matrix::Matrix;
+use crate::position::{INamedPosition, UNamedPosition};
+
+// How many steps can be undone
+pub const MAX_HISTORY: usize = 100;
+
+#[derive(Serialize, Debug, Eq, PartialEq, JsonSchema, TS)]
+#[serde(tag = "code", content = "detail")]
+pub enum SandboxError {
+    SandboxNotRunning,
+    MissingMove,
+    SquareOutOfBounds,
+    NothingToUndo,
+    NothingToRedo,
+    GameError(GameError),
+}
+
+// What clients are shown of a sandbox after every change
+#[derive(Clone, Debug, Serialize, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct SandboxState {
+    #[schemars(with = "Vec<Vec<MapSquareType>>")]
+    #[ts(as = "Vec<Vec<MapSquareType>>")]
+    pub board: Matrix<MapSquareType>,
+    pub score: HashMap<PlayerTeam, usize>,
+    pub remaining_turns: usize,
+    pub special_points: HashMap<PlayerTeam, usize>,
+    pub can_undo: bool,
+    pub can_redo: bool,
+}
+
+// A game where both teams are controlled by the same user, who may play any card and edit the board. Turns are
+// validated and applied by a regular GameState, so they play out exactly like they would in a real game.
+#[derive(Clone)]
+pub struct Sandbox {
+    game: GameState,
+    undo_stack: Vec<GameState>,
+    redo_stack: Vec<GameState>,
+}
+
+impl Sandbox {
+    pub fn new(
+        board: Matrix<MapSquareType>,
+        card_provider: Arc<dyn CardProvider + Send + Sync>,
+        move_validator: Arc<dyn MoveValidator + Send + Sync>,
+    ) -> Self {
+        let decks = HashMap::from([(PlayerTeam::Alpha, IndexSet::new()), (PlayerTeam::Bravo, IndexSet::new())]);
+        Self::with_game(GameState::new(board, card_provider, move_validator, decks))
+    }
+
+    // The undo history isn't part of snapshots
+    pub fn from_snapshot(
+        snapshot: GameSnapshot,
+        card_provider: Arc<dyn CardProvider + Send + Sync>,
+        move_validator: Arc<dyn MoveValidator + Send + Sync>,
+    ) -> Self {
+        Self::with_game(GameState::from_snapshot(snapshot, card_provider, move_validator))
+    }
+
+    fn with_game(game: GameState) -> Self {
+        Self { game, undo_stack: Vec::new(), redo_stack: Vec::new() }
+    }
+
+    pub fn snapshot(&self) -> GameSnapshot {
+        self.game.snapshot()
+    }
+
+    pub fn state(&self) -> SandboxState {
+        SandboxState {
+            board: self.game.board.clone(),
+            score: self.game.score(),
+            remaining_turns: self.game.remaining_turns,
+            special_points: [PlayerTeam::Alpha, PlayerTeam::Bravo].into_iter()
+                .map(|team| (team.clone(), self.game.available_special_points(&team)))
+                .collect(),
+            can_undo: !self.undo_stack.is_empty(),
+            can_redo: !self.redo_stack.is_empty(),
+        }
+    }
+
+    // Plays a turn with a move for each team. The played cards are put into the hands first, so any card can be played.
+    pub fn play_turn(&mut self, moves: HashMap<PlayerTeam, PlayerMove>) -> Result<(), SandboxError> {
+        let mut game = self.game.clone();
+
+        for team in [PlayerTeam::Alpha, PlayerTeam::Bravo] {
+            let player_move = moves.get(&team).ok_or(SandboxError::MissingMove)?;
+            game.set_hand(&team, IndexSet::from([player_move.card_name().to_string()]));
+            game.propose_move(team, player_move.clone()).map_err(SandboxError::GameError)?;
+        }
+        game.apply_moves();
+
+        self.push(game);
+        Ok(())
+    }
+
+    pub fn set_square(&mut self, position: INamedPosition, square: MapSquareType) -> Result<(), SandboxError> {
+        let board_size = self.game.board.size();
+        let position: UNamedPosition = position.try_into()
+            .ok()
+            .filter(|position: &UNamedPosition| position.x < board_size.w && position.y < board_size.h)
+            .ok_or(SandboxError::SquareOutOfBounds)?;
+
+        let mut game = self.game.clone();
+        game.board[position.into()] = square;
+        self.push(game);
+        Ok(())
+    }
+
+    pub fn undo(&mut self) -> Result<(), SandboxError> {
+        let previous = self.undo_stack.pop().ok_or(SandboxError::NothingToUndo)?;
+        self.redo_stack.push(std::mem::replace(&mut self.game, previous));
+        Ok(())
+    }
+
+    pub fn redo(&mut self) -> Result<(), SandboxError> {
+        let next = self.redo_stack.pop().ok_or(SandboxError::NothingToRedo)?;
+        self.undo_stack.push(std::mem::replace(&mut self.game, next));
+        Ok(())
+    }
+
+    fn push(&mut self, game: GameState) {
+        self.undo_stack.push(std::mem::replace(&mut self.game, game));
+        if self.undo_stack.len() > MAX_HISTORY {
+            self.undo_stack.remove(0);
+        }
+        self.redo_stack.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use crate::game::card::CardSquareProviderImpl;
+    use crate::game::move_validator::{InvalidMoveError, MoveValidatorImpl};
+    use crate::game::squares::MST;
+    use crate::game::state::CardRotation;
+    use crate::matrix::MatrixSize;
+    use super::*;
+
+    fn create() -> Sandbox {
+        let card_provider = Arc::new(CardSquareProviderImpl::new());
+        let mut board = Matrix::filled_with(MatrixSize::new(9, 9), MST::Empty);
+        board[(2, 2)] = MST::SpecialAlpha;
+        board[(6, 6)] = MST::SpecialBravo;
+
+        Sandbox::new(board, card_provider.clone(), Arc::new(MoveValidatorImpl::new(card_provider)))
+    }
+
+    fn card_name(number: usize) -> String {
+        CardSquareProviderImpl::new().get_all()[number].name.clone()
+    }
+
+    fn pass(team: PlayerTeam, card_name: String) -> (PlayerTeam, PlayerMove) {
+        (team, PlayerMove::Pass { card_name })
+    }
+
+    fn place(team: PlayerTeam, card_name: String, x: isize, y: isize) -> (PlayerTeam, PlayerMove) {
+        (team, PlayerMove::PlaceCard { card_name, position: (x, y).into(), rotation: CardRotation::Deg0, special: false })
+    }
+
+    #[test]
+    fn plays_any_card_for_both_teams() {
+        let mut sandbox = create();
+        let card = card_name(0);
+
+        // Both teams may play the same card, which is validated like in a real game
+        assert_eq!(
+            Err(SandboxError::GameError(GameError::InvalidMove(InvalidMoveError::NoExpectedSquaresNearCard))),
+            sandbox.play_turn(HashMap::from([place(PlayerTeam::Alpha, card.clone(), 4, 0), pass(PlayerTeam::Bravo, card.clone())])));
+        assert_eq!(Err(SandboxError::MissingMove), sandbox.play_turn(HashMap::from([pass(PlayerTeam::Alpha, card.clone())])));
+
+        let placement = (0..9).cartesian_product(0..9)
+            .find(|(x, y)| sandbox.play_turn(HashMap::from([
+                place(PlayerTeam::Alpha, card.clone(), *x, *y),
+                pass(PlayerTeam::Bravo, card.clone()),
+            ])).is_ok());
+
+        assert!(placement.is_some());
+        let state = sandbox.state();
+        assert!(state.score[&PlayerTeam::Alpha] > 1);
+        assert_eq!(1, state.score[&PlayerTeam::Bravo]);
+        assert!(state.can_undo);
+
+        sandbox.play_turn(HashMap::from([pass(PlayerTeam::Alpha, card.clone()), pass(PlayerTeam::Bravo, card)])).unwrap();
+        assert_eq!(state.remaining_turns - 1, sandbox.state().remaining_turns);
+    }
+
+    #[test]
+    fn undoes_and_redoes_changes() {
+        let mut sandbox = create();
+        assert_eq!(Err(SandboxError::NothingToUndo), sandbox.undo());
+        assert_eq!(Err(SandboxError::SquareOutOfBounds), sandbox.set_square((9, 0).into(), MST::FillAlpha));
+
+        sandbox.set_square((0, 0).into(), MST::FillAlpha).unwrap();
+        sandbox.play_turn(HashMap::from([pass(PlayerTeam::Alpha, card_name(0)), pass(PlayerTeam::Bravo, card_name(1))])).unwrap();
+        let state = sandbox.state();
+
+        sandbox.undo().unwrap();
+        sandbox.undo().unwrap();
+        assert_eq!(MST::Empty, sandbox.state().board[(0, 0)]);
+        assert!(!sandbox.state().can_undo);
+
+        sandbox.redo().unwrap();
+        sandbox.redo().unwrap();
+        assert_eq!(Err(SandboxError::NothingToRedo), sandbox.redo());
+        assert_eq!(state.board, sandbox.state().board);
+        assert_eq!(state.remaining_turns, sandbox.state().remaining_turns);
+
+        // New changes discard undone ones
+        sandbox.undo().unwrap();
+        sandbox.set_square((1, 0).into(), MST::Neutral).unwrap();
+        assert!(!sandbox.state().can_redo);
+    }
+}
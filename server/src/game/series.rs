@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use ts_rs::TS;
+use uuid::Uuid;
+
+pub const ALLOWED_SERIES_LENGTHS: [usize; 3] = [3, 5, 7];
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, Eq, PartialEq, JsonSchema, TS)]
+pub enum SeriesMapSelection {
+    Rotation,
+    LoserPick,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct SeriesSettings {
+    pub best_of: usize,
+    pub map_selection: SeriesMapSelection,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, JsonSchema, TS)]
+pub struct SeriesGame {
+    pub map: String,
+    pub winner: Option<Uuid>,
+    pub loser: Option<Uuid>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct Series {
+    pub best_of: usize,
+    pub map_selection: SeriesMapSelection,
+    pub wins: HashMap<Uuid, usize>,
+    pub games: Vec<SeriesGame>,
+}
+
+impl Series {
+    pub fn new(settings: SeriesSettings) -> Option<Self> {
+        if !ALLOWED_SERIES_LENGTHS.contains(&settings.best_of) {
+            return None;
+        }
+
+        Some(Self {
+            best_of: settings.best_of,
+            map_selection: settings.map_selection,
+            wins: HashMap::new(),
+            games: Vec::new(),
+        })
+    }
+
+    pub fn wins_required(&self) -> usize {
+        self.best_of / 2 + 1
+    }
+
+    // Drawn games are recorded, but do not count towards either player's wins.
+    pub fn record_game(&mut self, map: String, winner: Option<Uuid>, loser: Option<Uuid>) {
+        if let Some(winner) = winner {
+            *self.wins.entry(winner).or_insert(0) += 1;
+        }
+
+        self.games.push(SeriesGame { map, winner, loser });
+    }
+
+    pub fn winner(&self) -> Option<Uuid> {
+        let wins_required = self.wins_required();
+        self.wins.iter()
+            .find(|(_id, wins)| **wins >= wins_required)
+            .map(|(id, _wins)| *id)
+    }
+
+    pub fn completed(&self) -> bool {
+        self.winner().is_some()
+    }
+
+    pub fn in_progress(&self) -> bool {
+        !self.games.is_empty() && !self.completed()
+    }
+
+    pub fn replace_player(&mut self, old_id: Uuid, new_id: Uuid) {
+        if let Some(wins) = self.wins.remove(&old_id) {
+            self.wins.insert(new_id, wins);
+        }
+
+        for game in self.games.iter_mut() {
+            for player in [&mut game.winner, &mut game.loser].into_iter().flatten() {
+                if *player == old_id {
+                    *player = new_id;
+                }
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.wins.clear();
+        self.games.clear();
+    }
+
+    // The user that may choose the next map when playing with loser's pick. After a drawn game, the loser of the
+    // most recent decided game keeps the pick.
+    pub fn map_picker(&self) -> Option<Uuid> {
+        if self.map_selection != SeriesMapSelection::LoserPick {
+            return None;
+        }
+
+        self.games.iter().rev().find_map(|game| game.loser)
+    }
+
+    // Picks the map following the previously played one from the given map pool.
+    pub fn next_rotation_map(&self, map_pool: &[String]) -> Option<String> {
+        if self.map_selection != SeriesMapSelection::Rotation || map_pool.is_empty() {
+            return None;
+        }
+
+        let next_index = self.games.last()
+            .and_then(|game| map_pool.iter().position(|map| map == &game.map))
+            .map_or(0, |index| (index + 1) % map_pool.len());
+
+        map_pool.get(next_index).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(best_of: usize, map_selection: SeriesMapSelection) -> Series {
+        Series::new(SeriesSettings { best_of, map_selection }).unwrap()
+    }
+
+    fn map_pool() -> Vec<String> {
+        vec!("Map_A".to_string(), "Map_B".to_string(), "Map_C".to_string())
+    }
+
+    #[test]
+    fn new_rejects_unsupported_lengths() {
+        assert_eq!(None, Series::new(SeriesSettings { best_of: 1, map_selection: SeriesMapSelection::Rotation }));
+        assert_eq!(None, Series::new(SeriesSettings { best_of: 4, map_selection: SeriesMapSelection::Rotation }));
+        assert!(Series::new(SeriesSettings { best_of: 5, map_selection: SeriesMapSelection::LoserPick }).is_some());
+    }
+
+    #[test]
+    fn wins_required() {
+        assert_eq!(2, series(3, SeriesMapSelection::Rotation).wins_required());
+        assert_eq!(3, series(5, SeriesMapSelection::Rotation).wins_required());
+        assert_eq!(4, series(7, SeriesMapSelection::Rotation).wins_required());
+    }
+
+    #[test]
+    fn completes_when_a_player_clinches() {
+        let player_a = Uuid::new_v4();
+        let player_b = Uuid::new_v4();
+        let mut series = series(3, SeriesMapSelection::Rotation);
+
+        series.record_game("Map_A".to_string(), Some(player_a), Some(player_b));
+        assert!(series.in_progress());
+        assert_eq!(None, series.winner());
+
+        series.record_game("Map_B".to_string(), Some(player_b), Some(player_a));
+        series.record_game("Map_C".to_string(), None, None);
+        assert!(series.in_progress());
+
+        series.record_game("Map_A".to_string(), Some(player_b), Some(player_a));
+        assert!(series.completed());
+        assert!(!series.in_progress());
+        assert_eq!(Some(player_b), series.winner());
+        assert_eq!(HashMap::from([(player_a, 1), (player_b, 2)]), series.wins);
+        assert_eq!(4, series.games.len());
+    }
+
+    #[test]
+    fn reset() {
+        let mut series = series(3, SeriesMapSelection::Rotation);
+        series.record_game("Map_A".to_string(), Some(Uuid::new_v4()), Some(Uuid::new_v4()));
+
+        series.reset();
+
+        assert!(series.wins.is_empty());
+        assert!(series.games.is_empty());
+        assert!(!series.in_progress());
+    }
+
+    #[test]
+    fn replace_player() {
+        let player_a = Uuid::new_v4();
+        let player_b = Uuid::new_v4();
+        let player_c = Uuid::new_v4();
+        let mut series = series(5, SeriesMapSelection::LoserPick);
+        series.record_game("Map_A".to_string(), Some(player_a), Some(player_b));
+        series.record_game("Map_B".to_string(), Some(player_b), Some(player_a));
+
+        series.replace_player(player_a, player_c);
+
+        assert_eq!(HashMap::from([(player_c, 1), (player_b, 1)]), series.wins);
+        assert_eq!(series.games[0], SeriesGame { map: "Map_A".to_string(), winner: Some(player_c), loser: Some(player_b) });
+        assert_eq!(series.games[1], SeriesGame { map: "Map_B".to_string(), winner: Some(player_b), loser: Some(player_c) });
+    }
+
+    #[test]
+    fn map_picker_is_last_loser() {
+        let player_a = Uuid::new_v4();
+        let player_b = Uuid::new_v4();
+        let mut series = series(5, SeriesMapSelection::LoserPick);
+        assert_eq!(None, series.map_picker());
+
+        series.record_game("Map_A".to_string(), Some(player_a), Some(player_b));
+        assert_eq!(Some(player_b), series.map_picker());
+
+        series.record_game("Map_B".to_string(), None, None);
+        assert_eq!(Some(player_b), series.map_picker());
+
+        series.record_game("Map_C".to_string(), Some(player_b), Some(player_a));
+        assert_eq!(Some(player_a), series.map_picker());
+    }
+
+    #[test]
+    fn map_picker_with_rotation() {
+        let mut series = series(3, SeriesMapSelection::Rotation);
+        series.record_game("Map_A".to_string(), Some(Uuid::new_v4()), Some(Uuid::new_v4()));
+
+        assert_eq!(None, series.map_picker());
+    }
+
+    #[test]
+    fn next_rotation_map() {
+        let mut series = series(3, SeriesMapSelection::Rotation);
+        assert_eq!(Some("Map_A".to_string()), series.next_rotation_map(&map_pool()));
+
+        series.record_game("Map_B".to_string(), None, None);
+        assert_eq!(Some("Map_C".to_string()), series.next_rotation_map(&map_pool()));
+
+        series.record_game("Map_C".to_string(), None, None);
+        assert_eq!(Some("Map_A".to_string()), series.next_rotation_map(&map_pool()));
+    }
+
+    #[test]
+    fn next_rotation_map_with_map_outside_pool() {
+        let mut series = series(3, SeriesMapSelection::Rotation);
+        series.record_game("Map_Z".to_string(), None, None);
+
+        assert_eq!(Some("Map_A".to_string()), series.next_rotation_map(&map_pool()));
+        assert_eq!(None, series.next_rotation_map(&[]));
+    }
+
+    #[test]
+    fn next_rotation_map_with_loser_pick() {
+        let series = series(3, SeriesMapSelection::LoserPick);
+
+        assert_eq!(None, series.next_rotation_map(&map_pool()));
+    }
+}
@@ -1,8 +1,9 @@
-use serde_repr::Deserialize_repr;
+use serde_repr::{Deserialize_repr, Serialize_repr};
+use schemars::JsonSchema_repr;
 use crate::game::card::CardSquareType;
 use crate::game::team::PlayerTeam;
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize_repr)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Deserialize_repr, Serialize_repr, JsonSchema_repr)]
 #[repr(u8)]
 pub enum MapSquareType {
     Disabled = 0,
@@ -14,6 +15,7 @@ pub enum MapSquareType {
     Neutral,
 }
 
+#[allow(clippy::upper_case_acronyms)]
 pub type MST = MapSquareType;
 
 impl MapSquareType {
@@ -38,4 +40,12 @@ impl MapSquareType {
     pub fn is_special(&self) -> bool {
         self == &Self::SpecialAlpha || self == &Self::SpecialBravo
     }
+
+    pub fn team(&self) -> Option<PlayerTeam> {
+        match self {
+            Self::FillAlpha | Self::SpecialAlpha => Some(PlayerTeam::Alpha),
+            Self::FillBravo | Self::SpecialBravo => Some(PlayerTeam::Bravo),
+            _ => None,
+        }
+    }
 }
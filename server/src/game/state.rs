@@ -1,25 +1,29 @@
 use std::borrow::Borrow;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ops::{Add, AddAssign, SubAssign};
 use std::sync::Arc;
 use indexmap::IndexSet;
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema_repr;
+use schemars::JsonSchema;
+use ts_rs::TS;
 use serde_repr::{Deserialize_repr, Serialize_repr};
 use strum::EnumCount;
 use itertools::Itertools;
 use rand::prelude::IteratorRandom;
 use crate::game::card::{Card, CardProvider, CardSquareType};
 use crate::game::move_validator::{InvalidMoveError, MoveValidator};
+use crate::game::rules::Rules;
 use crate::game::squares::MapSquareType;
 use crate::game::team::PlayerTeam;
-use crate::matrix::{Matrix, MatrixRotation, Slice};
-use crate::position::{INamedPosition, UNamedPosition};
+use crate::matrix::{Matrix, MatrixRotation};
+use crate::position::{INamedPosition, UNamedPosition, UPosition};
 
 pub const HAND_SIZE: usize = 4;
 pub const DECK_SIZE: usize = 15;
 pub const TURN_COUNT: usize = DECK_SIZE - (HAND_SIZE - 1);
 
-#[derive(Serialize, Debug, Eq, PartialEq)]
+#[derive(Serialize, Debug, Eq, PartialEq, JsonSchema, TS)]
 #[serde(tag = "code", content = "detail")]
 pub enum GameError {
     InvalidMove(InvalidMoveError),
@@ -30,7 +34,7 @@ pub enum GameError {
     RedrawNotPermitted,
 }
 
-#[derive(Clone, Copy, Debug, Serialize_repr, Deserialize_repr, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Serialize_repr, Deserialize_repr, Eq, PartialEq, JsonSchema_repr)]
 #[repr(u16)]
 pub enum CardRotation {
     Deg0 = 0,
@@ -39,6 +43,8 @@ pub enum CardRotation {
     Deg270 = 270,
 }
 
+pub const ROTATIONS: [CardRotation; 4] = [CardRotation::Deg0, CardRotation::Deg90, CardRotation::Deg180, CardRotation::Deg270];
+
 impl From<CardRotation> for MatrixRotation {
     fn from(r: CardRotation) -> Self {
         match r {
@@ -50,7 +56,7 @@ impl From<CardRotation> for MatrixRotation {
     }
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, JsonSchema, TS)]
 #[serde(tag = "type")]
 pub enum PlayerMove {
     #[serde(rename_all = "camelCase")]
@@ -73,7 +79,7 @@ impl PlayerMove {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
 pub struct PlayerDeck {
     pub cards: IndexSet<String>,
     pub used_cards: IndexSet<String>,
@@ -148,11 +154,33 @@ pub struct ApplyMovesResult {
     pub next_cards: HashMap<PlayerTeam, String>,
 }
 
+// A move played during a completed turn, along with the number of squares it claimed
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct TurnRecord {
+    pub player_move: PlayerMove,
+    pub claimed_squares: usize,
+}
+
+// Everything needed to restore a game, e.g. after a server restart.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub struct GameSnapshot {
+    board: Matrix<MapSquareType>,
+    next_moves: HashMap<PlayerTeam, PlayerMove>,
+    completed_moves: Vec<HashMap<PlayerTeam, PlayerMove>>,
+    #[serde(default)]
+    claimed_squares: Vec<HashMap<PlayerTeam, usize>>,
+    used_special_points: HashMap<PlayerTeam, usize>,
+    decks: HashMap<PlayerTeam, PlayerDeck>,
+    remaining_turns: usize,
+}
+
 #[derive(Clone)]
 pub struct GameState {
     pub board: Matrix<MapSquareType>,
     next_moves: HashMap<PlayerTeam, PlayerMove>,
     completed_moves: Vec<HashMap<PlayerTeam, PlayerMove>>,
+    // The squares each team claimed during every completed turn, kept for analytics
+    claimed_squares: Vec<HashMap<PlayerTeam, usize>>,
     used_special_points: HashMap<PlayerTeam, usize>,
     decks: HashMap<PlayerTeam, PlayerDeck>,
     pub remaining_turns: usize,
@@ -172,6 +200,7 @@ impl GameState {
             board,
             next_moves: HashMap::new(),
             completed_moves: Vec::new(),
+            claimed_squares: Vec::new(),
             used_special_points: Self::score_counter(),
             decks: decks.into_iter().map(|(team, cards)| (team, PlayerDeck::new(cards))).collect(),
             remaining_turns: TURN_COUNT.to_owned(),
@@ -180,12 +209,78 @@ impl GameState {
         }
     }
 
+    pub fn from_snapshot(
+        snapshot: GameSnapshot,
+        square_provider: Arc<dyn CardProvider + Send + Sync>,
+        move_validator: Arc<dyn MoveValidator + Send + Sync>,
+    ) -> Self {
+        Self {
+            board: snapshot.board,
+            next_moves: snapshot.next_moves,
+            completed_moves: snapshot.completed_moves,
+            claimed_squares: snapshot.claimed_squares,
+            used_special_points: snapshot.used_special_points,
+            decks: snapshot.decks,
+            remaining_turns: snapshot.remaining_turns,
+            square_provider,
+            move_validator,
+        }
+    }
+
+    pub fn snapshot(&self) -> GameSnapshot {
+        GameSnapshot {
+            board: self.board.clone(),
+            next_moves: self.next_moves.clone(),
+            completed_moves: self.completed_moves.clone(),
+            claimed_squares: self.claimed_squares.clone(),
+            used_special_points: self.used_special_points.clone(),
+            decks: self.decks.clone(),
+            remaining_turns: self.remaining_turns,
+        }
+    }
+
+    pub fn rules(&self) -> Rules {
+        self.move_validator.rules()
+    }
+
+    pub fn hand(&self, team: &PlayerTeam) -> Option<&IndexSet<String>> {
+        self.decks.get(team).map(|deck| &deck.current_hand)
+    }
+
+    // Replaces a team's hand regardless of their deck, e.g. so sandboxes can play any card
+    pub fn set_hand(&mut self, team: &PlayerTeam, hand: IndexSet<String>) {
+        if let Some(deck) = self.decks.get_mut(team) {
+            deck.current_hand = hand;
+        }
+    }
+
+    pub fn deck(&self, team: &PlayerTeam) -> Option<&IndexSet<String>> {
+        self.decks.get(team).map(|deck| &deck.cards)
+    }
+
+    // The cards a team may still draw, i.e. neither used nor in their hand
+    pub fn upcoming_cards(&self, team: &PlayerTeam) -> IndexSet<String> {
+        self.decks.get(team).map(PlayerDeck::upcoming_cards).unwrap_or_default()
+    }
+
+    // Games restored from snapshots taken before claimed squares were tracked count them as 0
+    pub fn turns(&self) -> Vec<HashMap<PlayerTeam, TurnRecord>> {
+        self.completed_moves.iter().enumerate()
+            .map(|(turn, moves)| moves.iter()
+                .map(|(team, player_move)| (team.clone(), TurnRecord {
+                    player_move: player_move.clone(),
+                    claimed_squares: self.claimed_squares.get(turn).and_then(|claimed| claimed.get(team)).copied().unwrap_or(0),
+                }))
+                .collect())
+            .collect()
+    }
+
     fn score_counter() -> HashMap<PlayerTeam, usize> {
         HashMap::from([(PlayerTeam::Alpha, 0), (PlayerTeam::Bravo, 0)])
     }
 
     pub fn completed(&self) -> bool {
-        self.remaining_turns <= 0
+        self.remaining_turns == 0
     }
 
     pub fn score(&self) -> HashMap<PlayerTeam, usize> {
@@ -210,30 +305,79 @@ impl GameState {
             return Err(GameError::RedrawNotPermitted);
         }
 
-        self.decks.get_mut(&team).unwrap().redraw().map_or(Err(GameError::RedrawNotPermitted), |result| Ok(result))
+        self.decks.get_mut(&team).unwrap().redraw().ok_or(GameError::RedrawNotPermitted)
     }
 
     pub fn propose_move(&mut self, team: PlayerTeam, player_move: PlayerMove) -> Result<(), GameError> {
-        if self.remaining_turns <= 0 {
+        if self.remaining_turns == 0 {
             return Err(GameError::GameEnded);
         }
 
-        match self.move_validator.validate(
+        self.validate_move(&team, &player_move)?;
+        self.next_moves.insert(team, player_move);
+        Ok(())
+    }
+
+    pub fn validate_move(&self, team: &PlayerTeam, player_move: &PlayerMove) -> Result<(), GameError> {
+        self.move_validator.validate(
             &self.board,
-            self.available_special_points(&team),
-            &team,
-            &player_move,
-            &self.decks[&team]
-        ) {
-            Ok(()) => {
-                self.next_moves.insert(team, player_move);
-                Ok(())
+            self.available_special_points(team),
+            team,
+            player_move,
+            &self.decks[team]
+        ).map_err(GameError::InvalidMove)
+    }
+
+    // Every legal move of a team with their current hand, placements first and passes last. Placements of a card
+    // covering the same squares, e.g. a symmetric card rotated by 180°, are only listed once.
+    pub fn valid_moves(&self, team: &PlayerTeam) -> Vec<PlayerMove> {
+        let hand = self.hand(team).cloned().unwrap_or_default();
+        let board_size = self.board.size();
+        let mut moves = Vec::new();
+
+        for card_name in &hand {
+            let Some(card) = self.square_provider.get(card_name) else {
+                continue;
+            };
+            let mut footprints = HashSet::new();
+
+            for rotation in ROTATIONS {
+                let squares: Vec<(CardSquareType, UPosition)> = card.squares.clone()
+                    .rotate_clockwise(rotation.into())
+                    .into_iter()
+                    .filter(|(square, _)| square != &CardSquareType::Empty)
+                    .collect();
+
+                for (special, (x, y)) in [false, true].into_iter().cartesian_product((0..board_size.w).cartesian_product(0..board_size.h)) {
+                    let player_move = PlayerMove::PlaceCard {
+                        card_name: card_name.clone(),
+                        position: INamedPosition::new(x as isize, y as isize),
+                        rotation,
+                        special,
+                    };
+                    if self.validate_move(team, &player_move).is_err() {
+                        continue;
+                    }
+
+                    let footprint: Vec<(CardSquareType, UPosition)> = squares.iter()
+                        .map(|(square, position)| (*square, (x + position.0, y + position.1)))
+                        .collect();
+                    if footprints.insert((special, footprint)) {
+                        moves.push(player_move);
+                    }
+                }
             }
-            Err(err) => Err(GameError::InvalidMove(err)),
         }
+
+        moves.extend(hand.into_iter().map(|card_name| PlayerMove::Pass { card_name }));
+        moves
     }
 
-    fn available_special_points(&self, team: &PlayerTeam) -> usize {
+    pub fn available_special_points(&self, team: &PlayerTeam) -> usize {
+        if !self.move_validator.rules().special_attacks {
+            return 0;
+        }
+
         self.count_active_special_points(team)
             .add(self.count_passes(team))
             .saturating_sub(self.used_special_points[team])
@@ -243,9 +387,10 @@ impl GameState {
         self.next_moves.len() == PlayerTeam::COUNT
     }
 
+    // Special squares are active once all squares adjacent to them are filled
     fn count_active_special_points(&self, team: &PlayerTeam) -> usize {
         let mut result: usize = 0;
-        let board_size = self.board.size();
+        let rules = self.move_validator.rules();
 
         self.board.clone().into_iter()
             .filter(|(square, _)| match team {
@@ -253,13 +398,7 @@ impl GameState {
                 PlayerTeam::Bravo => square == &MapSquareType::SpecialBravo,
             })
             .for_each(|(_square, position)| {
-                let x_from = if position.0 == 0 { 0 } else { position.0 - 1 };
-                let y_from = if position.1 == 0 { 0 } else { position.1 - 1 };
-                let x_to = if position.0 >= board_size.w - 1 { position.0 } else { position.0 + 1 };
-                let y_to = if position.1 >= board_size.h - 1 { position.1 } else { position.1 + 1 };
-
-                let squares_around = self.board.slice((x_from, y_from)..=(x_to, y_to));
-                if squares_around.into_iter().all(|(square, _)| square != MapSquareType::Empty) {
+                if rules.adjacent_squares(&self.board, position).iter().all(|square| square != &MapSquareType::Empty) {
                     result.add_assign(1);
                 }
             });
@@ -276,13 +415,19 @@ impl GameState {
             .count()
     }
 
+    // Applies moves that were validated elsewhere, e.g. by a client following a game played on the server
+    pub fn replay_moves(&mut self, moves: HashMap<PlayerTeam, PlayerMove>) -> ApplyMovesResult {
+        self.next_moves = moves;
+        self.apply_moves()
+    }
+
     pub fn apply_moves(&mut self) -> ApplyMovesResult {
         let moves = std::mem::take(&mut self.next_moves);
         let mut board_updates = Matrix::filled_with(self.board.size(), MapSquareType::Empty);
 
         let augmented_moves: HashMap<PlayerTeam, AugmentedPlayerMove> = moves.into_iter()
             .map(|(team, player_move)| {
-                let card = self.square_provider.get(&player_move.card_name()).unwrap();
+                let card = self.square_provider.get(player_move.card_name()).unwrap();
 
                 (team,
                  AugmentedPlayerMove {
@@ -295,7 +440,7 @@ impl GameState {
             })
             .collect();
 
-        let square_counts_match = augmented_moves.values().into_iter()
+        let square_counts_match = augmented_moves.values()
             .map(|player_move| player_move.card_square_count)
             .all_equal();
         let mut next_cards: HashMap<PlayerTeam, String> = HashMap::new();
@@ -303,18 +448,18 @@ impl GameState {
         for (team, aug_move) in augmented_moves.iter()
             .sorted_by(|(_, a), (_, b)| Ord::cmp(&b.card_square_count, &a.card_square_count))
         {
-            if let Some(next_card) = self.decks.get_mut(&team).unwrap().draw_new_card(&aug_move.card.name) {
+            if let Some(next_card) = self.decks.get_mut(team).unwrap().draw_new_card(&aug_move.card.name) {
                 next_cards.insert(team.clone(), next_card);
             }
 
             if let PlayerMove::PlaceCard { position, special, rotation, .. } = aug_move.player_move.borrow() {
                 if *special {
-                    self.used_special_points.get_mut(&team).unwrap().add_assign(aug_move.card.special_cost);
+                    self.used_special_points.get_mut(team).unwrap().add_assign(aug_move.card.special_cost);
                 }
 
                 let move_pos: UNamedPosition = position.clone().try_into().unwrap();
                 aug_move.card.squares.clone()
-                    .rotate_clockwise(rotation.clone().into())
+                    .rotate_clockwise((*rotation).into())
                     .into_iter()
                     .filter(|(item, _)| item != &CardSquareType::Empty)
                     .for_each(|(item, position)| {
@@ -338,12 +483,22 @@ impl GameState {
             }
         }
 
+        let mut claimed_squares = Self::score_counter();
         let mut new_board = self.board.clone();
         for (square, position) in board_updates.into_iter()
             .filter(|(square, _)| square != &MapSquareType::Empty)
         {
+            // Special squares on the board are kept when covered by fill squares, like they are when cards overlap
+            if new_board[position].is_special() && square.is_fill() {
+                continue;
+            }
+
+            if let Some(team) = square.team() {
+                claimed_squares.get_mut(&team).unwrap().add_assign(1);
+            }
             new_board[position] = square;
         }
+        self.claimed_squares.push(claimed_squares);
         self.board = new_board;
 
         self.remaining_turns.sub_assign(1);
@@ -424,6 +579,11 @@ mod tests {
                 vec!(MST::Empty, MST::Empty, MST::Empty, MST::Empty, MST::Empty, MST::Empty),
                 vec!(MST::Empty, MST::Empty, MST::Empty, MST::Empty, MST::Empty, MST::Empty),
             )));
+
+            let turns = state.turns();
+            assert_eq!(1, turns.len());
+            assert_eq!(4, turns[0][&PlayerTeam::Alpha].claimed_squares);
+            assert_eq!(4, turns[0][&PlayerTeam::Bravo].claimed_squares);
         }
 
         #[test]
@@ -647,6 +807,30 @@ mod tests {
         }
     }
 
+    mod snapshot {
+        use super::*;
+
+        #[test]
+        fn restores_snapshot() {
+            let mut state = create();
+            state.assign_initial_hands();
+            state.next_moves.insert(PlayerTeam::Alpha, place_card_move());
+            state.completed_moves = vec!(HashMap::from([(PlayerTeam::Alpha, pass_move()), (PlayerTeam::Bravo, place_card_move())]));
+            state.used_special_points = HashMap::from([(PlayerTeam::Alpha, 1), (PlayerTeam::Bravo, 2)]);
+            state.board[(2, 3)] = MST::SpecialBravo;
+            state.remaining_turns = 5;
+
+            let json = serde_json::to_string(&state.snapshot()).unwrap();
+            let restored = GameState::from_snapshot(
+                serde_json::from_str(&json).unwrap(),
+                TestCardSquareProvider::new(),
+                Arc::new(TestMoveValidator {}));
+
+            assert_eq!(state.snapshot(), restored.snapshot());
+            assert_eq!(state.hand(&PlayerTeam::Bravo), restored.hand(&PlayerTeam::Bravo));
+        }
+    }
+
     mod count_passes {
         use super::*;
 
@@ -721,6 +905,62 @@ mod tests {
         }
     }
 
+    mod rule_variants {
+        use crate::game::rules::RuleVariant;
+        use super::*;
+
+        fn create_with(variant: RuleVariant) -> GameState {
+            let mut state = create();
+            state.move_validator = variant.validator(TestCardSquareProvider::new());
+            state
+        }
+
+        #[test]
+        fn no_special_points_without_special_attacks() {
+            for (variant, expected_points) in [(RuleVariant::Standard, 1), (RuleVariant::NoSpecialAttacks, 0)] {
+                let mut state = create_with(variant);
+                state.completed_moves.push(HashMap::from([(PlayerTeam::Alpha, pass_move()), (PlayerTeam::Bravo, pass_move())]));
+
+                assert_eq!(expected_points, state.available_special_points(&PlayerTeam::Alpha));
+            }
+        }
+
+        #[test]
+        fn special_squares_activate_without_diagonals() {
+            for (variant, expected_points) in [(RuleVariant::Standard, 0), (RuleVariant::NoDiagonalAdjacency, 1)] {
+                let mut state = create_with(variant);
+                state.board = Matrix::new(vec!(
+                    vec!(MST::Empty, MST::FillBravo, MST::Empty),
+                    vec!(MST::FillAlpha, MST::SpecialAlpha, MST::FillAlpha),
+                    vec!(MST::Empty, MST::Neutral, MST::Empty),
+                ));
+
+                assert_eq!(expected_points, state.count_active_special_points(&PlayerTeam::Alpha));
+            }
+        }
+
+        #[test]
+        fn fill_squares_keep_covered_special_squares() {
+            let mut state = create_with(RuleVariant::SpecialsCoverAnything);
+            state.board[(2, 1)] = MST::SpecialBravo;
+            state.board[(2, 3)] = MST::Neutral;
+            state.next_moves.insert(PlayerTeam::Alpha, PlayerMove::PlaceCard {
+                card_name: "card_1".to_string(),
+                position: INamedPosition::new(1, 1),
+                rotation: CardRotation::Deg0,
+                special: true,
+            });
+            state.next_moves.insert(PlayerTeam::Bravo, PlayerMove::Pass { card_name: "card_3".to_string() });
+
+            state.apply_moves();
+
+            assert_eq!(MST::SpecialBravo, state.board[(2, 1)]);
+            assert_eq!(MST::SpecialAlpha, state.board[(2, 2)]);
+            assert_eq!(MST::FillAlpha, state.board[(2, 3)]);
+            assert_eq!(3, state.turns()[0][&PlayerTeam::Alpha].claimed_squares);
+        }
+    }
+
     mod player_deck {
         use super::*;
 
@@ -1,7 +1,9 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use ts_rs::TS;
 use strum::EnumCount;
 
-#[derive(Debug, Clone, Eq, Hash, PartialEq, Serialize, EnumCount)]
+#[derive(Debug, Clone, Eq, Hash, PartialEq, Deserialize, Serialize, EnumCount, JsonSchema, TS)]
 pub enum PlayerTeam {
     Alpha,
     Bravo,
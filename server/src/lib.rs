@@ -0,0 +1,8 @@
+// The game rules, shared by the server and the tools in src/bin
+pub mod game;
+pub mod matrix;
+pub mod position;
+
+// Clients send the version they were built against when connecting, see socket/protocol.rs. Bump this whenever a change
+// to the socket messages breaks existing clients.
+pub const PROTOCOL_VERSION: u32 = 1;
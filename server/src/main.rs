@@ -1,26 +1,176 @@
 use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::{Arc};
+use std::time::{Duration, Instant};
 use axum::Router;
-use axum::routing::get;
-use tokio::sync::RwLock;
+use axum::routing::{delete, get, post};
+use tokio::sync::{mpsc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use tokio::task::JoinHandle;
+use tableturfer_server::{game, matrix, position};
+use crate::analytics::{Analytics, get_card_statistics, get_map_statistics};
+use crate::api::{admin, get_openapi, get_room, list_cards, list_maps, list_rooms};
+use crate::app_config::{Configuration, LimitsConfiguration};
+use crate::game::card::{CardProvider, CardSquareProviderImpl};
+use crate::game::map::{MapProvider, MapProviderImpl};
+use crate::metrics::{get_health, get_metrics, Metrics};
+use crate::persistence::{AnalyticsPersistence, RoomPersistence, TournamentPersistence};
+use crate::persistence::redis_store::RedisRoomPersistence;
+use crate::persistence::registry::{RedisRoomRegistry, RoomRegistry};
+use crate::puzzles::{check_solution, get_puzzle, list_puzzles, PuzzleStore};
 use crate::socket::SocketHandler;
-use crate::socket::room_store::SocketRoomStore;
+use crate::socket::limits::ConnectionLimiter;
+use crate::socket::room_store::{RoomResultSender, SocketRoomStore};
+use crate::tournament::TournamentStore;
+use crate::tournament::routes::{create_tournament, get_admin_tournament, get_tournament, start_tournament};
 
+mod analytics;
+mod api;
 mod app_config;
 mod socket;
-mod game;
-mod matrix;
-mod position;
+mod metrics;
+mod persistence;
+mod puzzles;
+mod shutdown;
+mod tournament;
+#[cfg(test)]
+mod test_harness;
+
+const PERSISTENCE_FLUSH_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub struct AppState {
     room_store: RwLock<SocketRoomStore>,
+    tournament_store: RwLock<TournamentStore>,
+    limits: LimitsConfiguration,
+    connection_limiter: Arc<ConnectionLimiter>,
+    card_provider: Arc<dyn CardProvider + Send + Sync>,
+    map_provider: Arc<dyn MapProvider + Send + Sync>,
+    metrics: Metrics,
+    analytics: Analytics,
+    // The admin API is disabled without a token
+    admin_token: Option<String>,
+    // Authenticates connections relayed by other instances, see socket/proxy.rs
+    relay_secret: Option<String>,
+    puzzles: PuzzleStore,
+}
+
+impl AppState {
+    // Locks the room store, recording how long that took
+    async fn read_room_store(&self) -> RwLockReadGuard<'_, SocketRoomStore> {
+        let start = Instant::now();
+        let room_store = self.room_store.read().await;
+        self.metrics.record_lock_wait(start.elapsed());
+        room_store
+    }
+
+    async fn write_room_store(&self) -> RwLockWriteGuard<'_, SocketRoomStore> {
+        let start = Instant::now();
+        let room_store = self.room_store.write().await;
+        self.metrics.record_lock_wait(start.elapsed());
+        room_store
+    }
 }
 
 async fn hello() -> &'static str {
     "Hello!"
 }
 
+fn create_router(app_state: Arc<AppState>) -> Router<Arc<AppState>> {
+    Router::with_state(app_state)
+        .route("/", get(hello))
+        .route("/healthz", get(get_health))
+        .route("/metrics", get(get_metrics))
+        .route("/ws", get(SocketHandler::request_handler))
+        .route("/rooms", get(list_rooms))
+        .route("/rooms/:code", get(get_room))
+        .route("/cards", get(list_cards))
+        .route("/maps", get(list_maps))
+        .route("/analytics/cards", get(get_card_statistics))
+        .route("/analytics/maps", get(get_map_statistics))
+        .route("/openapi.json", get(get_openapi))
+        .route("/tournaments", post(create_tournament))
+        .route("/tournaments/:id", get(get_tournament))
+        .route("/tournaments/:id/start", post(start_tournament))
+        .route("/puzzles", get(list_puzzles))
+        .route("/puzzles/:id", get(get_puzzle))
+        .route("/puzzles/:id/solutions", post(check_solution))
+        .route("/admin/rooms", get(admin::list_rooms))
+        .route("/admin/rooms/:code", delete(admin::close_room))
+        .route("/admin/rooms/:code/game", get(admin::get_game))
+        .route("/admin/rooms/:code/endgame", get(admin::get_endgame))
+        .route("/admin/rooms/:code/end-game", post(admin::end_game))
+        .route("/admin/announcements", post(admin::announce))
+        .route("/admin/tournaments/:id", get(get_admin_tournament))
+}
+
+// Also returns the task writing room updates to the persistent store, if there is one
+async fn create_room_store(config: &Configuration) -> (SocketRoomStore, Option<JoinHandle<()>>) {
+    let Some(redis) = config.redis() else {
+        if config.cluster.is_some() {
+            panic!("Running several instances requires Redis to be enabled");
+        }
+
+        return (SocketRoomStore::default(), None);
+    };
+
+    log::info!("Connecting to Redis at {}:{}", redis.host, redis.port);
+    let redis_url = redis.url();
+    let persistence = RedisRoomPersistence::connect(&redis_url).await
+        .expect("Failed to connect to Redis");
+    let registry: Option<Arc<dyn RoomRegistry>> = match &config.cluster {
+        Some(cluster) => {
+            if cluster.secret.is_empty() {
+                panic!("Running several instances requires a cluster secret");
+            }
+
+            log::info!("Registering rooms as hosted at {}", cluster.address);
+            Some(Arc::new(RedisRoomRegistry::connect(&redis_url, cluster.address.clone()).await
+                .expect("Failed to connect to Redis")))
+        }
+        None => None,
+    };
+
+    let snapshots = persistence.load_all().await.expect("Failed to load rooms from Redis");
+    let (update_sender, update_receiver) = mpsc::unbounded_channel();
+    let writer = tokio::spawn(persistence::write_room_updates(Arc::new(persistence), update_receiver));
+
+    let mut room_store = SocketRoomStore::new(Some(update_sender), registry);
+    room_store.restore(snapshots).await;
+    (room_store, Some(writer))
+}
+
+// Also returns the task saving statistics to the persistent store, if there is one
+async fn create_analytics(config: &Configuration) -> (Analytics, Option<JoinHandle<()>>) {
+    let Some(redis) = config.redis() else {
+        return (Analytics::default(), None);
+    };
+
+    let persistence = RedisRoomPersistence::connect(&redis.url()).await
+        .expect("Failed to connect to Redis");
+    let counters = persistence.load_counters().await.expect("Failed to load analytics from Redis");
+
+    let (update_sender, update_receiver) = mpsc::unbounded_channel();
+    let writer = tokio::spawn(persistence::write_analytics(Arc::new(persistence), update_receiver));
+    (Analytics::new(counters, Some(update_sender)), Some(writer))
+}
+
+// Tournaments are restored after rooms, as the rooms of unfinished matches have to report to them again
+async fn create_tournament_store(config: &Configuration, room_store: &mut SocketRoomStore, result_sender: RoomResultSender) -> (TournamentStore, Option<JoinHandle<()>>) {
+    let Some(redis) = config.redis() else {
+        return (TournamentStore::new(result_sender, None), None);
+    };
+
+    let persistence = RedisRoomPersistence::connect(&redis.url()).await
+        .expect("Failed to connect to Redis");
+    let tournaments = persistence.load_tournaments().await.expect("Failed to load tournaments from Redis");
+
+    let (update_sender, update_receiver) = mpsc::unbounded_channel();
+    let writer = tokio::spawn(persistence::write_tournaments(Arc::new(persistence), update_receiver));
+    let mut tournament_store = TournamentStore::new(result_sender, Some(update_sender));
+    tournament_store.restore(tournaments, room_store);
+    (tournament_store, Some(writer))
+}
+
 #[tokio::main]
 async fn main() {
     let config = app_config::load_config();
@@ -30,20 +180,50 @@ async fn main() {
         .parse_write_style(&config.logger.write_style)
         .init();
 
-    // log::info!("Connecting to Redis at {}:{}", config.redis.host, config.redis.port);
-    // let redis = redis::Client::open(format!("redis://{}:{}", config.redis.host, config.redis.port)).unwrap();
+    let (mut room_store, persistence_writer) = create_room_store(&config).await;
+    let (analytics, analytics_writer) = create_analytics(&config).await;
+    let (room_result_sender, room_result_receiver) = mpsc::unbounded_channel();
+    let (tournament_store, tournament_writer) = create_tournament_store(&config, &mut room_store, room_result_sender).await;
+    let room_store = RwLock::new(room_store);
+    let tournament_store = RwLock::new(tournament_store);
+    let app_state = Arc::new(AppState {
+        room_store,
+        tournament_store,
+        limits: config.limits.clone(),
+        connection_limiter: Arc::new(ConnectionLimiter::new(config.limits.max_connections_per_ip)),
+        card_provider: Arc::new(CardSquareProviderImpl::new()),
+        map_provider: Arc::new(MapProviderImpl::new()),
+        metrics: Metrics::default(),
+        analytics,
+        admin_token: config.admin.as_ref().map(|admin| admin.token.clone()).filter(|token| !token.is_empty()),
+        relay_secret: config.cluster.as_ref().map(|cluster| cluster.secret.clone()),
+        puzzles: PuzzleStore::new(config.puzzles.as_ref().map(|puzzles| PathBuf::from(&puzzles.directory))),
+    });
 
-    let room_store = RwLock::new(SocketRoomStore::default());
-    let app_state = Arc::new(AppState { room_store });
+    tokio::spawn(tournament::handle_room_results(app_state.clone(), room_result_receiver));
+    tokio::spawn(socket::reaper::reap_idle_rooms(app_state.clone(), config.rooms.clone()));
+    if config.cluster.is_some() {
+        tokio::spawn(persistence::registry::refresh_room_claims(app_state.clone()));
+    }
 
-    let router = Router::with_state(app_state)
-        .route("/", get(hello))
-        .route("/ws", get(SocketHandler::request_handler));
+    let router = create_router(app_state.clone());
 
     let addr = SocketAddr::from((IpAddr::from_str(&config.app.host).unwrap(), config.app.port));
     log::info!("Starting server at {addr}");
     axum::Server::bind(&addr)
-        .serve(router.into_make_service())
+        .serve(router.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(shutdown::signal())
         .await
         .unwrap();
+
+    // No new connections are accepted at this point
+    shutdown::drain(app_state.clone(), config.shutdown.clone()).await;
+    app_state.analytics.shut_down();
+    app_state.tournament_store.write().await.shut_down();
+    for writer in [persistence_writer, analytics_writer, tournament_writer].into_iter().flatten() {
+        if tokio::time::timeout(PERSISTENCE_FLUSH_TIMEOUT, writer).await.is_err() {
+            log::error!("Timed out saving rooms, game records and tournaments before shutting down");
+        }
+    }
+    log::info!("Server stopped");
 }
@@ -35,14 +35,14 @@ pub enum MatrixRotation {
     Deg270,
 }
 
-#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(Clone, Eq, Hash, PartialEq, Debug)]
 pub struct Matrix<T: Clone + Copy> {
     rows: Vec<Vec<T>>,
 }
 
 impl<T: Clone + Copy> Matrix<T> {
     pub fn new(rows: Vec<Vec<T>>) -> Self {
-        let first_row_len = rows.get(0).map_or(0, |row| row.len());
+        let first_row_len = rows.first().map_or(0, |row| row.len());
         if rows.iter().any(|row| row.len() != first_row_len) {
             panic!("All rows of a matrix must be the same length");
         }
@@ -53,7 +53,7 @@ impl<T: Clone + Copy> Matrix<T> {
     }
 
     pub fn filled_with(size: MatrixSize, element: T) -> Self {
-        Self::new((0..size.h).into_iter().map(|_| (0..size.w).into_iter().map(|_| element).collect()).collect())
+        Self::new((0..size.h).map(|_| (0..size.w).map(|_| element).collect()).collect())
     }
 
     pub fn empty() -> Self {
@@ -63,15 +63,15 @@ impl<T: Clone + Copy> Matrix<T> {
     }
 
     pub fn size(&self) -> MatrixSize {
-        MatrixSize::new(self.rows.len(), self.rows.get(0).map_or(0, |row| row.len()))
+        MatrixSize::new(self.rows.len(), self.rows.first().map_or(0, |row| row.len()))
     }
 
     pub fn rotate_clockwise(self, r: MatrixRotation) -> Self {
         match r {
             MatrixRotation::None => self,
             MatrixRotation::Deg90 => {
-                let first_row_len = self.rows.get(0).map_or(0, |row| row.len());
-                Self::new((0..first_row_len).into_iter().map(|index| {
+                let first_row_len = self.rows.first().map_or(0, |row| row.len());
+                Self::new((0..first_row_len).map(|index| {
                     self.rows.iter().map(|row| row[index]).rev().collect()
                 }).collect())
             }
@@ -79,8 +79,8 @@ impl<T: Clone + Copy> Matrix<T> {
                 Self::new(self.rows.clone().into_iter().map(|row| row.into_iter().rev().collect()).rev().collect())
             }
             MatrixRotation::Deg270 => {
-                let first_row_len = self.rows.get(0).map_or(0, |row| row.len());
-                Self::new((0..first_row_len).into_iter().map(|index| {
+                let first_row_len = self.rows.first().map_or(0, |row| row.len());
+                Self::new((0..first_row_len).map(|index| {
                     self.rows.iter().map(|row| row[index]).collect()
                 }).rev().collect())
             }
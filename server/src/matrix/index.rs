@@ -1,6 +1,6 @@
 use std::ops::{Index, IndexMut, Range, RangeInclusive};
 use crate::matrix::Matrix;
-use crate::position::{UNamedPosition, UPosition};
+use crate::position::UPosition;
 
 pub trait Slice<Idx> {
     fn slice(&self, index: Idx) -> Self;
@@ -22,14 +22,6 @@ impl<T: Clone + Copy> Slice<RangeInclusive<UPosition>> for Matrix<T> {
     }
 }
 
-impl<T: Clone + Copy> Matrix<T> {
-    pub fn replace(&mut self, start: UNamedPosition, items: Matrix<T>) {
-        items.into_iter().for_each(|(item, position)| {
-            self[(start.x + position.0, start.y + position.1)] = item;
-        });
-    }
-}
-
 impl<T: Clone + Copy> Index<UPosition> for Matrix<T> {
     type Output = T;
 
@@ -62,29 +54,6 @@ mod tests {
         assert_eq!(matrix[(2, 1)], 1);
     }
 
-    #[test]
-    fn matrix_replace() {
-        let mut matrix = Matrix::new(vec!(
-            vec!(16, 15, 14, 13),
-            vec!(12, 11, 10, 9),
-            vec!(8, 7, 6, 5),
-            vec!(4, 3, 2, 1),
-        ));
-
-        matrix.replace(UNamedPosition::new(1, 0), Matrix::new(vec!(
-            vec!(99, 98),
-            vec!(97, 96),
-            vec!(95, 94),
-        )));
-
-        assert_eq!(matrix, Matrix::new(vec!(
-            vec!(16, 99, 98, 13),
-            vec!(12, 97, 96, 9),
-            vec!(8, 95, 94, 5),
-            vec!(4, 3, 2, 1),
-        )));
-    }
-
     #[test]
     fn matrix_slice() {
         let matrix = Matrix::new(vec!(
@@ -123,8 +92,8 @@ mod tests {
             vec!(3, 2, 1),
         ));
 
-        matrix[(1, 1).into()] = 9;
-        matrix[(2, 0).into()] = 100;
+        matrix[(1, 1)] = 9;
+        matrix[(2, 0)] = 100;
 
         assert_eq!(matrix, Matrix::new(vec!(
             vec!(6, 5, 100),
@@ -22,7 +22,7 @@ impl<T: Clone + Copy> FromIterator<(T, UPosition)> for Matrix<T> {
             .next()
             .map_or(0, |(_item, position)| position.0 + 1);
 
-        if row_size <= 0 {
+        if row_size == 0 {
             // With how this iterator is implemented, if the width of a matrix is 0, we don't have a
             // way to know how many rows it had from the iterator. Therefore, we return an empty matrix.
             Matrix::empty()
@@ -57,7 +57,7 @@ impl<T: Clone + Copy> Iterator for MatrixIter<T> {
     type Item = (T, UPosition);
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.position.y >= self.size.h || self.size.w <= 0 {
+        if self.position.y >= self.size.h || self.size.w == 0 {
             None
         } else {
             let item = self.matrix[(self.position.x, self.position.y)];
@@ -1,7 +1,8 @@
 use std::fmt::Formatter;
 use std::marker::PhantomData;
-use serde::{Deserialize, Deserializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde::de::{SeqAccess, Visitor};
+use serde::ser::SerializeSeq;
 use crate::matrix::Matrix;
 
 struct MatrixVisitor<'de, T: Clone + Copy + Deserialize<'de>> {
@@ -40,11 +41,21 @@ impl<'de, T: Clone + Copy + Deserialize<'de> + 'de> Deserialize<'de> for Matrix<
     }
 }
 
+impl<T: Clone + Copy + Serialize> Serialize for Matrix<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        let mut seq = serializer.serialize_seq(Some(self.rows.len()))?;
+        for row in &self.rows {
+            seq.serialize_element(row)?;
+        }
+        seq.end()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[derive(Deserialize)]
+    #[derive(Deserialize, Serialize)]
     struct StructWithMatrix {
         content: Matrix<u32>
     }
@@ -60,6 +71,18 @@ mod tests {
         )))
     }
 
+    #[test]
+    fn serialize() {
+        let value = StructWithMatrix {
+            content: Matrix::new(vec!(
+                vec!(1, 2, 3),
+                vec!(4, 5, 6),
+            ))
+        };
+
+        assert_eq!("{\"content\":[[1,2,3],[4,5,6]]}", serde_json::to_string(&value).unwrap());
+    }
+
     #[test]
     #[should_panic(expected = "expected a sequence")]
     fn deserialize_not_enough_dimensions() {
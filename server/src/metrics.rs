@@ -0,0 +1,166 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
+use crate::AppState;
+use crate::socket::messages::SocketError;
+
+// Health checks fail if the room store can't be locked within this time, e.g. because of a deadlock
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Counters for the Prometheus endpoint. Values that can be read from the room store are collected when scraping.
+#[derive(Default)]
+pub struct Metrics {
+    connected_sockets: Arc<AtomicUsize>,
+    games_completed: AtomicU64,
+    moves: AtomicU64,
+    action_errors: Mutex<BTreeMap<&'static str, u64>>,
+    lock_wait_nanos: AtomicU64,
+    lock_acquisitions: AtomicU64,
+}
+
+// Counts as a connected socket until dropped.
+pub struct SocketGuard {
+    connected_sockets: Arc<AtomicUsize>,
+}
+
+impl Drop for SocketGuard {
+    fn drop(&mut self) {
+        self.connected_sockets.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+pub struct RoomStoreStats {
+    pub rooms: usize,
+    pub games_in_progress: usize,
+}
+
+impl Metrics {
+    pub fn socket_connected(&self) -> SocketGuard {
+        self.connected_sockets.fetch_add(1, Ordering::Relaxed);
+        SocketGuard { connected_sockets: self.connected_sockets.clone() }
+    }
+
+    pub fn connected_sockets(&self) -> usize {
+        self.connected_sockets.load(Ordering::Relaxed)
+    }
+
+    pub fn record_move(&self) {
+        self.moves.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_game_completed(&self) {
+        self.games_completed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_action_error(&self, err: &SocketError) {
+        *self.action_errors.lock().unwrap().entry(err.into()).or_insert(0) += 1;
+    }
+
+    pub fn record_lock_wait(&self, wait: Duration) {
+        self.lock_wait_nanos.fetch_add(wait.as_nanos() as u64, Ordering::Relaxed);
+        self.lock_acquisitions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // Renders all metrics in the Prometheus text format
+    pub fn render(&self, stats: RoomStoreStats) -> String {
+        let mut output = String::new();
+
+        write_metric(&mut output, "tableturfer_rooms", "gauge", "Rooms hosted by this instance", stats.rooms);
+        write_metric(&mut output, "tableturfer_connected_sockets", "gauge", "Open WS connections, including relayed ones",
+            self.connected_sockets());
+        write_metric(&mut output, "tableturfer_games_in_progress", "gauge", "Games that have started but not ended yet",
+            stats.games_in_progress);
+        write_metric(&mut output, "tableturfer_games_completed_total", "counter", "Games played until the end",
+            self.games_completed.load(Ordering::Relaxed));
+        write_metric(&mut output, "tableturfer_moves_total", "counter", "Moves accepted from players",
+            self.moves.load(Ordering::Relaxed));
+
+        write_header(&mut output, "tableturfer_action_errors_total", "counter", "Errors sent to clients by error code");
+        for (code, count) in self.action_errors.lock().unwrap().iter() {
+            writeln!(output, "tableturfer_action_errors_total{{code=\"{code}\"}} {count}").unwrap();
+        }
+
+        write_header(&mut output, "tableturfer_room_store_lock_wait_seconds", "summary", "Time spent waiting to lock the room store");
+        let wait_seconds = Duration::from_nanos(self.lock_wait_nanos.load(Ordering::Relaxed)).as_secs_f64();
+        writeln!(output, "tableturfer_room_store_lock_wait_seconds_sum {wait_seconds}").unwrap();
+        writeln!(output, "tableturfer_room_store_lock_wait_seconds_count {}", self.lock_acquisitions.load(Ordering::Relaxed)).unwrap();
+
+        output
+    }
+}
+
+fn write_header(output: &mut String, name: &str, metric_type: &str, help: &str) {
+    writeln!(output, "# HELP {name} {help}").unwrap();
+    writeln!(output, "# TYPE {name} {metric_type}").unwrap();
+}
+
+fn write_metric(output: &mut String, name: &str, metric_type: &str, help: &str, value: impl std::fmt::Display) {
+    write_header(output, name, metric_type, help);
+    writeln!(output, "{name} {value}").unwrap();
+}
+
+pub async fn get_metrics(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let stats = {
+        let room_store = state.read_room_store().await;
+        RoomStoreStats {
+            rooms: room_store.room_count(),
+            games_in_progress: room_store.games_in_progress(),
+        }
+    };
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], state.metrics.render(stats))
+}
+
+pub async fn get_health(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    match tokio::time::timeout(HEALTH_CHECK_TIMEOUT, state.room_store.read()).await {
+        Ok(_) => (StatusCode::OK, "OK"),
+        Err(_) => {
+            log::error!("Health check failed, the room store could not be locked within {HEALTH_CHECK_TIMEOUT:?}");
+            (StatusCode::SERVICE_UNAVAILABLE, "Room store unavailable")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_connected_sockets() {
+        let metrics = Metrics::default();
+
+        let first = metrics.socket_connected();
+        let _second = metrics.socket_connected();
+        drop(first);
+
+        assert_eq!(1, metrics.connected_sockets.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn render() {
+        let metrics = Metrics::default();
+        metrics.record_move();
+        metrics.record_move();
+        metrics.record_game_completed();
+        metrics.record_action_error(&SocketError::UserNotRoomOwner);
+        metrics.record_action_error(&SocketError::UserNotRoomOwner);
+        metrics.record_action_error(&SocketError::RoomNotFound("ABCD".to_string()));
+        metrics.record_lock_wait(Duration::from_millis(1500));
+
+        let output = metrics.render(RoomStoreStats { rooms: 3, games_in_progress: 1 });
+
+        assert!(output.contains("# TYPE tableturfer_rooms gauge\ntableturfer_rooms 3\n"));
+        assert!(output.contains("tableturfer_games_in_progress 1\n"));
+        assert!(output.contains("tableturfer_games_completed_total 1\n"));
+        assert!(output.contains("tableturfer_moves_total 2\n"));
+        assert!(output.contains("tableturfer_action_errors_total{code=\"RoomNotFound\"} 1\n"));
+        assert!(output.contains("tableturfer_action_errors_total{code=\"UserNotRoomOwner\"} 2\n"));
+        assert!(output.contains("tableturfer_room_store_lock_wait_seconds_sum 1.5\n"));
+        assert!(output.contains("tableturfer_room_store_lock_wait_seconds_count 1\n"));
+    }
+}
@@ -0,0 +1,210 @@
+pub mod redis_store;
+pub mod registry;
+#[cfg(test)]
+mod test_server;
+
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+use crate::analytics::AnalyticsCounters;
+use crate::socket::room_store::RoomSnapshot;
+use crate::tournament::Tournament;
+
+#[derive(Debug)]
+pub enum PersistenceError {
+    Backend(String),
+    Serialization(serde_json::Error),
+}
+
+impl Display for PersistenceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PersistenceError::Backend(err) => write!(f, "Backend error: {err}"),
+            PersistenceError::Serialization(err) => write!(f, "Serialization error: {err}"),
+        }
+    }
+}
+
+impl From<serde_json::Error> for PersistenceError {
+    fn from(err: serde_json::Error) -> Self {
+        PersistenceError::Serialization(err)
+    }
+}
+
+#[async_trait]
+pub trait RoomPersistence: Send + Sync {
+    async fn save(&self, room: &RoomSnapshot) -> Result<(), PersistenceError>;
+    async fn delete(&self, room_code: &str) -> Result<(), PersistenceError>;
+    async fn load_all(&self) -> Result<Vec<RoomSnapshot>, PersistenceError>;
+}
+
+#[async_trait]
+pub trait AnalyticsPersistence: Send + Sync {
+    async fn save_counters(&self, counters: &AnalyticsCounters) -> Result<(), PersistenceError>;
+    async fn load_counters(&self) -> Result<AnalyticsCounters, PersistenceError>;
+}
+
+#[async_trait]
+pub trait TournamentPersistence: Send + Sync {
+    async fn save_tournament(&self, tournament: &Tournament) -> Result<(), PersistenceError>;
+    async fn load_tournaments(&self) -> Result<Vec<Tournament>, PersistenceError>;
+}
+
+pub enum RoomUpdate {
+    Save(Box<RoomSnapshot>),
+    Delete(String),
+}
+
+pub type RoomUpdateSender = mpsc::UnboundedSender<RoomUpdate>;
+
+// Updates are written one at a time in the order they were sent, so an older snapshot never overwrites a newer one.
+pub async fn write_room_updates(persistence: Arc<dyn RoomPersistence>, mut receiver: mpsc::UnboundedReceiver<RoomUpdate>) {
+    while let Some(update) = receiver.recv().await {
+        let result = match &update {
+            RoomUpdate::Save(room) => persistence.save(room).await,
+            RoomUpdate::Delete(room_code) => persistence.delete(room_code).await,
+        };
+
+        if let Err(err) = result {
+            log::error!("Failed to persist room update: {err}");
+        }
+    }
+}
+
+pub type TournamentUpdateSender = mpsc::UnboundedSender<Box<Tournament>>;
+
+// Tournaments are saved whole after every change, in the order they changed.
+pub async fn write_tournaments(persistence: Arc<dyn TournamentPersistence>, mut receiver: mpsc::UnboundedReceiver<Box<Tournament>>) {
+    while let Some(tournament) = receiver.recv().await {
+        if let Err(err) = persistence.save_tournament(&tournament).await {
+            log::error!("Failed to save tournament {}: {err}", tournament.id);
+        }
+    }
+}
+
+pub type AnalyticsUpdateSender = mpsc::UnboundedSender<Box<AnalyticsCounters>>;
+
+// Every update contains all counters, so only the latest one waiting is saved.
+pub async fn write_analytics(persistence: Arc<dyn AnalyticsPersistence>, mut receiver: mpsc::UnboundedReceiver<Box<AnalyticsCounters>>) {
+    while let Some(mut counters) = receiver.recv().await {
+        while let Ok(newer_counters) = receiver.try_recv() {
+            counters = newer_counters;
+        }
+
+        if let Err(err) = persistence.save_counters(&counters).await {
+            log::error!("Failed to save analytics: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use uuid::Uuid;
+    use crate::socket::close_code::SocketCloseCode;
+    use crate::socket::room_settings::RoomSettings;
+    use crate::socket::room_store::{JoinRequest, SocketRoomStore};
+    use super::*;
+
+    #[derive(Default)]
+    pub struct MemoryRoomPersistence {
+        pub rooms: Mutex<HashMap<String, String>>,
+    }
+
+    #[async_trait]
+    impl RoomPersistence for MemoryRoomPersistence {
+        async fn save(&self, room: &RoomSnapshot) -> Result<(), PersistenceError> {
+            self.rooms.lock().unwrap().insert(room.code.clone(), serde_json::to_string(room)?);
+            Ok(())
+        }
+
+        async fn delete(&self, room_code: &str) -> Result<(), PersistenceError> {
+            self.rooms.lock().unwrap().remove(room_code);
+            Ok(())
+        }
+
+        async fn load_all(&self) -> Result<Vec<RoomSnapshot>, PersistenceError> {
+            self.rooms.lock().unwrap().values()
+                .map(|room| serde_json::from_str(room).map_err(PersistenceError::from))
+                .collect()
+        }
+    }
+
+    #[tokio::test]
+    async fn writes_updates_in_order() {
+        let persistence = Arc::new(MemoryRoomPersistence::default());
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let mut room_store = SocketRoomStore::default();
+        let room_code = "WXYZ".to_string();
+        let room = room_store.create(room_code.clone(), Uuid::new_v4(), JoinRequest::named("user"), mpsc::channel(1).0).unwrap();
+
+        sender.send(RoomUpdate::Save(Box::new(room.snapshot()))).unwrap();
+        sender.send(RoomUpdate::Save(Box::new(room.snapshot()))).unwrap();
+        sender.send(RoomUpdate::Delete("ABCD".to_string())).unwrap();
+        drop(sender);
+        write_room_updates(persistence.clone(), receiver).await;
+
+        let rooms = persistence.load_all().await.unwrap();
+        assert_eq!(1, rooms.len());
+        assert_eq!(room_code, rooms[0].code);
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        sender.send(RoomUpdate::Delete(room_code)).unwrap();
+        drop(sender);
+        write_room_updates(persistence.clone(), receiver).await;
+
+        assert!(persistence.load_all().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn restored_rooms_return_seats_to_their_players() {
+        let mut room_store = SocketRoomStore::default();
+        let room_code = "ABCD".to_string();
+        let room = room_store.create(room_code.clone(), Uuid::new_v4(), JoinRequest::named("owner"), mpsc::channel(1).0).unwrap();
+        let owner_token = room.reconnect_token(room.owner_id).unwrap().clone();
+        room_store.get_and_join_if_exists(&room_code, Uuid::new_v4(), JoinRequest::named("opponent"), mpsc::channel(1).0).unwrap();
+        let snapshot = room_store.get(&room_code).unwrap().snapshot();
+
+        let mut restored_store = SocketRoomStore::default();
+        restored_store.restore(vec!(snapshot)).await;
+        let spectator_id = Uuid::new_v4();
+        let owner_id = Uuid::new_v4();
+        restored_store.get_and_join_if_exists(&room_code, spectator_id, JoinRequest::named("spectator"), mpsc::channel(1).0).unwrap();
+        // Anyone can pick the same username, so it takes the token to reclaim the seat
+        let impostor = restored_store.get_and_join_if_exists(&room_code, Uuid::new_v4(), JoinRequest::named("owner"), mpsc::channel(1).0);
+        assert!(matches!(impostor, Err(SocketCloseCode::InvalidToken)));
+        let request = JoinRequest { token: Some(&owner_token), ..JoinRequest::named("owner") };
+        restored_store.get_and_join_if_exists(&room_code, owner_id, request, mpsc::channel(1).0).unwrap();
+
+        let room = restored_store.get(&room_code).unwrap();
+        assert_eq!(owner_id, room.owner_id);
+        assert!(!room.is_opponent(spectator_id));
+
+        // The room is kept until the opponent returns as well
+        restored_store.remove_user_from_room(&room_code, spectator_id);
+        restored_store.remove_user_from_room(&room_code, owner_id);
+        assert!(restored_store.get(&room_code).is_some());
+    }
+
+    #[tokio::test]
+    async fn restored_rooms_still_require_password() {
+        let mut room_store = SocketRoomStore::default();
+        let room_code = "ABCD".to_string();
+        let room = room_store.create(room_code.clone(), Uuid::new_v4(), JoinRequest::named("owner"), mpsc::channel(1).0).unwrap();
+        let owner_token = room.reconnect_token(room.owner_id).unwrap().clone();
+        let settings = RoomSettings { password: Some("hunter2".to_string()), ..RoomSettings::default() };
+        room_store.get_mut(&room_code).unwrap().set_settings(settings).unwrap();
+        let snapshot = room_store.get(&room_code).unwrap().snapshot();
+
+        let mut restored_store = SocketRoomStore::default();
+        restored_store.restore(vec!(snapshot)).await;
+        let without_password = JoinRequest { token: Some(&owner_token), ..JoinRequest::named("owner") };
+        let result = restored_store.get_and_join_if_exists(&room_code, Uuid::new_v4(), without_password, mpsc::channel(1).0);
+        assert!(matches!(result, Err(SocketCloseCode::WrongPassword)));
+
+        let request = JoinRequest { password: Some("hunter2"), ..without_password };
+        assert!(restored_store.get_and_join_if_exists(&room_code, Uuid::new_v4(), request, mpsc::channel(1).0).is_ok());
+    }
+}
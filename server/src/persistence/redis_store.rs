@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use async_trait::async_trait;
+use redis::AsyncCommands;
+use redis::aio::ConnectionManager;
+use crate::analytics::{AnalyticsCounters, GameRecord};
+use crate::persistence::{AnalyticsPersistence, PersistenceError, RoomPersistence, TournamentPersistence};
+use crate::socket::room_store::RoomSnapshot;
+use crate::tournament::Tournament;
+
+// All rooms are stored as JSON in a single hash, keyed by their room code.
+const ROOMS_KEY: &str = "tableturfer:rooms";
+// The aggregated statistics of completed games, as JSON
+const ANALYTICS_KEY: &str = "tableturfer:analytics";
+// Records of completed games, keyed by their ID. Earlier versions kept every record; they are only read to build the
+// aggregates once.
+const LEGACY_GAMES_KEY: &str = "tableturfer:games";
+// Tournaments and their brackets, keyed by their ID
+const TOURNAMENTS_KEY: &str = "tableturfer:tournaments";
+
+impl From<redis::RedisError> for PersistenceError {
+    fn from(err: redis::RedisError) -> Self {
+        PersistenceError::Backend(err.to_string())
+    }
+}
+
+pub struct RedisRoomPersistence {
+    connection: ConnectionManager,
+}
+
+impl RedisRoomPersistence {
+    pub async fn connect(url: &str) -> Result<Self, PersistenceError> {
+        let client = redis::Client::open(url)?;
+        let connection = ConnectionManager::new(client).await?;
+        Ok(Self { connection })
+    }
+}
+
+#[async_trait]
+impl RoomPersistence for RedisRoomPersistence {
+    async fn save(&self, room: &RoomSnapshot) -> Result<(), PersistenceError> {
+        let room_json = serde_json::to_string(room)?;
+        let _: () = self.connection.clone().hset(ROOMS_KEY, &room.code, room_json).await?;
+        Ok(())
+    }
+
+    async fn delete(&self, room_code: &str) -> Result<(), PersistenceError> {
+        let _: () = self.connection.clone().hdel(ROOMS_KEY, room_code).await?;
+        Ok(())
+    }
+
+    async fn load_all(&self) -> Result<Vec<RoomSnapshot>, PersistenceError> {
+        let rooms: HashMap<String, String> = self.connection.clone().hgetall(ROOMS_KEY).await?;
+        rooms.into_iter()
+            .filter_map(|(room_code, room_json)| match serde_json::from_str(&room_json) {
+                Ok(room) => Some(Ok(room)),
+                Err(err) => {
+                    log::warn!("Skipping room {room_code} that could not be restored: {err}");
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl AnalyticsPersistence for RedisRoomPersistence {
+    async fn save_counters(&self, counters: &AnalyticsCounters) -> Result<(), PersistenceError> {
+        let counters_json = serde_json::to_string(counters)?;
+        let _: () = self.connection.clone().set(ANALYTICS_KEY, counters_json).await?;
+        Ok(())
+    }
+
+    async fn load_counters(&self) -> Result<AnalyticsCounters, PersistenceError> {
+        let counters_json: Option<String> = self.connection.clone().get(ANALYTICS_KEY).await?;
+        if let Some(counters_json) = counters_json {
+            return Ok(serde_json::from_str(&counters_json)?);
+        }
+
+        let mut counters = AnalyticsCounters::default();
+        let records: HashMap<String, String> = self.connection.clone().hgetall(LEGACY_GAMES_KEY).await?;
+        for (id, record_json) in records {
+            match serde_json::from_str::<GameRecord>(&record_json) {
+                Ok(record) => counters.add(&record),
+                Err(err) => log::warn!("Skipping game record {id} that could not be loaded: {err}"),
+            }
+        }
+        Ok(counters)
+    }
+}
+
+#[async_trait]
+impl TournamentPersistence for RedisRoomPersistence {
+    async fn save_tournament(&self, tournament: &Tournament) -> Result<(), PersistenceError> {
+        let tournament_json = serde_json::to_string(tournament)?;
+        let _: () = self.connection.clone().hset(TOURNAMENTS_KEY, tournament.id.to_string(), tournament_json).await?;
+        Ok(())
+    }
+
+    async fn load_tournaments(&self) -> Result<Vec<Tournament>, PersistenceError> {
+        let tournaments: HashMap<String, String> = self.connection.clone().hgetall(TOURNAMENTS_KEY).await?;
+        tournaments.into_iter()
+            .filter_map(|(id, tournament_json)| match serde_json::from_str(&tournament_json) {
+                Ok(tournament) => Some(Ok(tournament)),
+                Err(err) => {
+                    log::warn!("Skipping tournament {id} that could not be restored: {err}");
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc;
+    use uuid::Uuid;
+    use crate::analytics::Analytics;
+    use crate::game::rules::RuleVariant;
+    use crate::persistence::test_server::start_server;
+    use crate::socket::room_store::{JoinRequest, SocketRoomStore};
+    use crate::tournament::bracket::{Bracket, TournamentFormat};
+    use super::*;
+
+    async fn create_room() -> RoomSnapshot {
+        let mut room_store = SocketRoomStore::default();
+        let room = room_store.create("ABCD".to_string(), Uuid::new_v4(), JoinRequest::named("user"), mpsc::channel(1).0).unwrap();
+        room.snapshot()
+    }
+
+    #[tokio::test]
+    async fn saves_and_loads_rooms() {
+        let persistence = RedisRoomPersistence::connect(&start_server().await).await.unwrap();
+        let room = create_room().await;
+
+        persistence.save(&room).await.unwrap();
+        persistence.save(&room).await.unwrap();
+
+        let rooms = persistence.load_all().await.unwrap();
+        let restored_room = rooms.iter().find(|restored_room| restored_room.code == room.code).unwrap();
+        assert_eq!(serde_json::to_value(&room).unwrap(), serde_json::to_value(restored_room).unwrap());
+    }
+
+    #[tokio::test]
+    async fn deletes_rooms() {
+        let persistence = RedisRoomPersistence::connect(&start_server().await).await.unwrap();
+        let room = create_room().await;
+        persistence.save(&room).await.unwrap();
+
+        persistence.delete(&room.code).await.unwrap();
+
+        assert!(persistence.load_all().await.unwrap().iter().all(|restored_room| restored_room.code != room.code));
+    }
+
+    #[tokio::test]
+    async fn saves_and_loads_analytics() {
+        let persistence = RedisRoomPersistence::connect(&start_server().await).await.unwrap();
+        let record = GameRecord {
+            id: Uuid::new_v4(),
+            map_name: "Main Street".to_string(),
+            finished_at: chrono::Utc::now(),
+            score: HashMap::new(),
+            decks: HashMap::new(),
+            turns: Vec::new(),
+        };
+        let _: () = persistence.connection.clone().hset(LEGACY_GAMES_KEY, record.id.to_string(), serde_json::to_string(&record).unwrap()).await.unwrap();
+
+        let mut counters = persistence.load_counters().await.unwrap();
+        assert_eq!(1, Analytics::new(counters.clone(), None).maps()[0].games);
+
+        counters.add(&record);
+        persistence.save_counters(&counters).await.unwrap();
+        assert_eq!(2, Analytics::new(persistence.load_counters().await.unwrap(), None).maps()[0].games);
+    }
+
+    #[tokio::test]
+    async fn saves_and_loads_tournaments() {
+        let persistence = RedisRoomPersistence::connect(&start_server().await).await.unwrap();
+        let tournament = Tournament {
+            id: Uuid::new_v4(),
+            name: "Weekly".to_string(),
+            created_at: chrono::Utc::now(),
+            series: None,
+            max_spectators: None,
+            rules: RuleVariant::Standard,
+            bracket: Bracket::new(TournamentFormat::SingleElimination, vec!("a".to_string(), "b".to_string())).unwrap(),
+            player_tokens: HashMap::from([("a".to_string(), "token".to_string())]),
+        };
+
+        persistence.save_tournament(&tournament).await.unwrap();
+
+        let tournaments = persistence.load_tournaments().await.unwrap();
+        let restored_tournament = tournaments.into_iter().find(|restored_tournament| restored_tournament.id == tournament.id).unwrap();
+        assert_eq!(tournament.player_tokens, restored_tournament.player_tokens);
+    }
+}
@@ -0,0 +1,343 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use async_trait::async_trait;
+use chrono::Utc;
+use redis::{AsyncCommands, Script};
+use redis::aio::ConnectionManager;
+use crate::AppState;
+use crate::persistence::PersistenceError;
+
+// Each room code is claimed with its own key, holding the address of the instance hosting the room.
+const ROOM_INSTANCE_KEY_PREFIX: &str = "tableturfer:room-instance:";
+// Maps the address of every instance hosting rooms to when its claims expire, in milliseconds since the epoch, so that
+// room listings can ask every instance for its rooms.
+const INSTANCES_KEY: &str = "tableturfer:instances";
+// Claims expire unless their instance keeps refreshing them, so the rooms of an instance that went away for good can
+// be hosted by other instances again.
+pub const CLAIM_TTL: Duration = Duration::from_secs(60);
+pub const CLAIM_REFRESH_INTERVAL: Duration = Duration::from_secs(20);
+
+// Both scripts only touch claims that still belong to the given address, as a claim may have expired and been taken
+// over by another instance in the meantime.
+pub const RELEASE_SCRIPT: &str = r"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+";
+pub const REFRESH_SCRIPT: &str = r"
+local refreshed = 0
+for _, key in ipairs(KEYS) do
+    if redis.call('GET', key) == ARGV[1] then
+        refreshed = refreshed + redis.call('PEXPIRE', key, ARGV[2])
+    end
+end
+return refreshed
+";
+
+// Keeps track of which server instance hosts each room, so that several instances can share the same room codes.
+#[async_trait]
+pub trait RoomRegistry: Send + Sync {
+    // The address other instances can reach this instance at.
+    fn address(&self) -> &str;
+    // Claims the given room code for this instance. Returns false if another instance already hosts the room.
+    async fn claim(&self, room_code: &str) -> Result<bool, PersistenceError>;
+    // Extends the claims on the given room codes, which expire otherwise.
+    async fn refresh(&self, room_codes: &[String]) -> Result<(), PersistenceError>;
+    async fn owner(&self, room_code: &str) -> Result<Option<String>, PersistenceError>;
+    async fn release(&self, room_code: &str) -> Result<(), PersistenceError>;
+    // The addresses of all instances that currently host rooms, possibly including this one.
+    async fn hosts(&self) -> Result<Vec<String>, PersistenceError>;
+}
+
+pub struct RedisRoomRegistry {
+    connection: ConnectionManager,
+    address: String,
+    claim_ttl: Duration,
+}
+
+impl RedisRoomRegistry {
+    pub async fn connect(url: &str, address: String) -> Result<Self, PersistenceError> {
+        let client = redis::Client::open(url)?;
+        let connection = ConnectionManager::new(client).await?;
+        Ok(Self { connection, address, claim_ttl: CLAIM_TTL })
+    }
+
+    #[cfg(test)]
+    fn with_claim_ttl(self, claim_ttl: Duration) -> Self {
+        Self { claim_ttl, ..self }
+    }
+
+    fn key(room_code: &str) -> String {
+        format!("{ROOM_INSTANCE_KEY_PREFIX}{room_code}")
+    }
+
+    // Lists this instance as a host for as long as the claims it just made or refreshed are valid
+    async fn record_host(&self) -> Result<(), PersistenceError> {
+        let expires_at = Utc::now().timestamp_millis() + self.claim_ttl.as_millis() as i64;
+        let _: () = self.connection.clone().hset(INSTANCES_KEY, &self.address, expires_at).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RoomRegistry for RedisRoomRegistry {
+    fn address(&self) -> &str {
+        &self.address
+    }
+
+    async fn claim(&self, room_code: &str) -> Result<bool, PersistenceError> {
+        let claimed: Option<String> = redis::cmd("SET")
+            .arg(Self::key(room_code))
+            .arg(&self.address)
+            .arg("NX")
+            .arg("PX")
+            .arg(self.claim_ttl.as_millis() as u64)
+            .query_async(&mut self.connection.clone())
+            .await?;
+        if claimed.is_some() {
+            self.record_host().await?;
+            return Ok(true);
+        }
+
+        // A claim left behind by this instance before it restarted is taken over again
+        if self.owner(room_code).await?.as_deref() == Some(self.address.as_str()) {
+            self.refresh(&[room_code.to_string()]).await?;
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    async fn refresh(&self, room_codes: &[String]) -> Result<(), PersistenceError> {
+        if room_codes.is_empty() {
+            return Ok(());
+        }
+
+        let script = Script::new(REFRESH_SCRIPT);
+        let mut invocation = script.prepare_invoke();
+        for room_code in room_codes {
+            invocation.key(Self::key(room_code));
+        }
+        let refreshed: usize = invocation.arg(&self.address).arg(self.claim_ttl.as_millis() as u64)
+            .invoke_async(&mut self.connection.clone())
+            .await?;
+        if refreshed < room_codes.len() {
+            log::warn!("{} room claim(s) expired or were taken over before they could be refreshed", room_codes.len() - refreshed);
+        }
+
+        self.record_host().await
+    }
+
+    async fn owner(&self, room_code: &str) -> Result<Option<String>, PersistenceError> {
+        Ok(self.connection.clone().get(Self::key(room_code)).await?)
+    }
+
+    async fn release(&self, room_code: &str) -> Result<(), PersistenceError> {
+        let _: usize = Script::new(RELEASE_SCRIPT)
+            .key(Self::key(room_code))
+            .arg(&self.address)
+            .invoke_async(&mut self.connection.clone())
+            .await?;
+        Ok(())
+    }
+
+    async fn hosts(&self) -> Result<Vec<String>, PersistenceError> {
+        let hosts: HashMap<String, i64> = self.connection.clone().hgetall(INSTANCES_KEY).await?;
+        let now = Utc::now().timestamp_millis();
+        let (hosts, expired_hosts): (Vec<_>, Vec<_>) = hosts.into_iter().partition(|(_address, expires_at)| *expires_at > now);
+
+        if !expired_hosts.is_empty() {
+            let addresses: Vec<String> = expired_hosts.into_iter().map(|(address, _expires_at)| address).collect();
+            let _: usize = self.connection.clone().hdel(INSTANCES_KEY, addresses).await?;
+        }
+
+        Ok(hosts.into_iter().map(|(address, _expires_at)| address).collect())
+    }
+}
+
+// The address of the instance hosting the given room, if that is not this instance
+pub async fn remote_host(registry: &dyn RoomRegistry, room_code: &str) -> Option<String> {
+    match registry.owner(room_code).await {
+        Ok(owner) => owner.filter(|owner| owner != registry.address()),
+        Err(err) => {
+            log::error!("Failed to look up the host of room {room_code}: {err}");
+            None
+        }
+    }
+}
+
+// Refreshes the claims on all rooms hosted by this instance for as long as it runs
+pub async fn refresh_room_claims(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(CLAIM_REFRESH_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let Some((registry, room_codes)) = state.read_room_store().await.hosted_room_codes() else {
+            return;
+        };
+        if let Err(err) = registry.refresh(&room_codes).await {
+            log::error!("Failed to refresh the claims on {} room(s): {err}", room_codes.len());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use tokio::sync::{mpsc, RwLock};
+    use uuid::Uuid;
+    use crate::persistence::test_server::start_server;
+    use crate::socket::room_store::{JoinRequest, SocketRoomStore};
+    use super::*;
+
+    struct UnreachableRegistry;
+
+    #[async_trait]
+    impl RoomRegistry for UnreachableRegistry {
+        fn address(&self) -> &str {
+            "ws://instance-a"
+        }
+
+        async fn claim(&self, _room_code: &str) -> Result<bool, PersistenceError> {
+            Err(PersistenceError::Backend("Connection refused".to_string()))
+        }
+
+        async fn refresh(&self, _room_codes: &[String]) -> Result<(), PersistenceError> {
+            Err(PersistenceError::Backend("Connection refused".to_string()))
+        }
+
+        async fn owner(&self, _room_code: &str) -> Result<Option<String>, PersistenceError> {
+            Err(PersistenceError::Backend("Connection refused".to_string()))
+        }
+
+        async fn release(&self, _room_code: &str) -> Result<(), PersistenceError> {
+            Err(PersistenceError::Backend("Connection refused".to_string()))
+        }
+
+        async fn hosts(&self) -> Result<Vec<String>, PersistenceError> {
+            Err(PersistenceError::Backend("Connection refused".to_string()))
+        }
+    }
+
+    async fn room_store(url: &str, address: &str) -> SocketRoomStore {
+        let registry = RedisRoomRegistry::connect(url, address.to_string()).await.unwrap();
+        SocketRoomStore::new(None, Some(Arc::new(registry)))
+    }
+
+    async fn lookup_host(room_store: &SocketRoomStore, room_code: &str) -> Option<String> {
+        remote_host(room_store.registry_for(room_code)?.as_ref(), room_code).await
+    }
+
+    #[tokio::test]
+    async fn claims_rooms_once() {
+        let url = start_server().await;
+        let instance_a = RedisRoomRegistry::connect(&url, "ws://instance-a".to_string()).await.unwrap();
+        let instance_b = RedisRoomRegistry::connect(&url, "ws://instance-b".to_string()).await.unwrap();
+
+        assert!(instance_a.claim("AAAA").await.unwrap());
+        assert!(instance_a.claim("AAAA").await.unwrap());
+        assert!(!instance_b.claim("AAAA").await.unwrap());
+        assert_eq!(Some("ws://instance-a".to_string()), instance_b.owner("AAAA").await.unwrap());
+        assert_eq!(None, instance_b.owner("BBBB").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn only_owner_releases_rooms() {
+        let url = start_server().await;
+        let instance_a = RedisRoomRegistry::connect(&url, "ws://instance-a".to_string()).await.unwrap();
+        let instance_b = RedisRoomRegistry::connect(&url, "ws://instance-b".to_string()).await.unwrap();
+        instance_a.claim("CCCC").await.unwrap();
+
+        instance_b.release("CCCC").await.unwrap();
+        assert_eq!(Some("ws://instance-a".to_string()), instance_a.owner("CCCC").await.unwrap());
+
+        instance_a.release("CCCC").await.unwrap();
+        assert_eq!(None, instance_a.owner("CCCC").await.unwrap());
+        assert!(instance_b.claim("CCCC").await.unwrap());
+        instance_b.release("CCCC").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn claims_expire_unless_refreshed() {
+        let url = start_server().await;
+        let instance_a = RedisRoomRegistry::connect(&url, "ws://instance-a".to_string()).await.unwrap()
+            .with_claim_ttl(Duration::from_millis(300));
+        let instance_b = RedisRoomRegistry::connect(&url, "ws://instance-b".to_string()).await.unwrap();
+        assert!(instance_a.claim("DDDD").await.unwrap());
+        assert!(instance_a.claim("EEEE").await.unwrap());
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        instance_a.refresh(&["DDDD".to_string()]).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        assert!(!instance_b.claim("DDDD").await.unwrap());
+        assert!(instance_b.claim("EEEE").await.unwrap());
+        // Refreshing a claim that was taken over leaves it with its new owner
+        instance_a.refresh(&["EEEE".to_string()]).await.unwrap();
+        instance_a.release("EEEE").await.unwrap();
+        assert_eq!(Some("ws://instance-b".to_string()), instance_a.owner("EEEE").await.unwrap());
+        instance_a.release("DDDD").await.unwrap();
+        instance_b.release("EEEE").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn lists_hosts_while_their_claims_are_valid() {
+        let url = start_server().await;
+        let instance_a = RedisRoomRegistry::connect(&url, "ws://instance-a".to_string()).await.unwrap()
+            .with_claim_ttl(Duration::from_millis(300));
+        let instance_b = RedisRoomRegistry::connect(&url, "ws://instance-b".to_string()).await.unwrap();
+        assert!(instance_a.claim("FFFF").await.unwrap());
+        assert!(instance_b.claim("GGGG").await.unwrap());
+
+        let hosts = instance_b.hosts().await.unwrap();
+        assert!(hosts.contains(&"ws://instance-a".to_string()));
+        assert!(hosts.contains(&"ws://instance-b".to_string()));
+
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        let hosts = instance_b.hosts().await.unwrap();
+        assert!(!hosts.contains(&"ws://instance-a".to_string()));
+        assert!(hosts.contains(&"ws://instance-b".to_string()));
+        instance_b.release("GGGG").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn rooms_are_not_created_or_restored_without_registry() {
+        let mut room_store = SocketRoomStore::default();
+        let room_code = "ABCD".to_string();
+        let room = room_store.create(room_code.clone(), Uuid::new_v4(), JoinRequest::named("user"), mpsc::channel(1).0).unwrap();
+        let mut unreachable_store = SocketRoomStore::new(None, Some(Arc::new(UnreachableRegistry)));
+
+        unreachable_store.restore(vec!(room.snapshot())).await;
+        assert!(unreachable_store.get(&room_code).is_none());
+        assert_eq!(None, SocketRoomStore::claim_new_room_code(&RwLock::new(unreachable_store)).await);
+    }
+
+    #[tokio::test]
+    async fn room_stores_resolve_rooms_of_other_instances() {
+        let url = start_server().await;
+        let store_a = RwLock::new(room_store(&url, "ws://instance-a").await);
+        let mut store_b = room_store(&url, "ws://instance-b").await;
+        let owner_id = Uuid::new_v4();
+        let room_code = SocketRoomStore::claim_new_room_code(&store_a).await.unwrap();
+        let room = store_a.write().await.create(room_code.clone(), owner_id, JoinRequest::named("user"), mpsc::channel(1).0).unwrap();
+
+        assert_eq!(None, lookup_host(&*store_a.read().await, &room_code).await);
+        assert_eq!(Some("ws://instance-a".to_string()), lookup_host(&store_b, &room_code).await);
+
+        store_b.restore(vec!(room.snapshot())).await;
+        assert!(store_b.get(&room_code).is_none());
+
+        // The claim is released in the background
+        store_a.write().await.remove_user_from_room(&room_code, owner_id);
+        for _ in 0..50 {
+            if lookup_host(&store_b, &room_code).await.is_none() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("Room {room_code} was not released");
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use crate::persistence::registry::{REFRESH_SCRIPT, RELEASE_SCRIPT};
+
+#[derive(Default)]
+struct Data {
+    hashes: HashMap<String, HashMap<String, String>>,
+    // Values along with when they expire, if ever
+    strings: HashMap<String, (String, Option<Instant>)>,
+    // Loaded scripts by their hash. Only the registry's scripts can be run, see `run_script`.
+    scripts: HashMap<String, String>,
+}
+
+impl Data {
+    fn get(&mut self, key: &str) -> Option<&String> {
+        if self.strings.get(key).is_some_and(|(_, expiry)| expiry.is_some_and(|expiry| expiry <= Instant::now())) {
+            self.strings.remove(key);
+        }
+
+        self.strings.get(key).map(|(value, _)| value)
+    }
+}
+
+type Store = Arc<Mutex<Data>>;
+
+// A minimal stand-in for a Redis server that understands just enough of the protocol for the room store and registry.
+// Set TBLT_TEST_REDIS_URL to run the tests against a real server instead.
+pub async fn start_server() -> String {
+    if let Ok(url) = std::env::var("TBLT_TEST_REDIS_URL") {
+        return url;
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("redis://{}", listener.local_addr().unwrap());
+    let store = Store::default();
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(handle_connection(stream, store.clone()));
+        }
+    });
+
+    url
+}
+
+async fn handle_connection(stream: TcpStream, store: Store) {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    while let Some(command) = read_command(&mut reader).await {
+        let response = execute(&store, &command);
+        if writer.write_all(response.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+async fn read_line(reader: &mut BufReader<tokio::net::tcp::OwnedReadHalf>) -> Option<String> {
+    let mut line = String::new();
+    match reader.read_line(&mut line).await {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line.trim_end().to_string()),
+    }
+}
+
+async fn read_command(reader: &mut BufReader<tokio::net::tcp::OwnedReadHalf>) -> Option<Vec<String>> {
+    let arg_count: usize = read_line(reader).await?.strip_prefix('*')?.parse().ok()?;
+    let mut args = Vec::with_capacity(arg_count);
+
+    for _ in 0..arg_count {
+        let length: usize = read_line(reader).await?.strip_prefix('$')?.parse().ok()?;
+        let mut arg = vec![0; length + 2];
+        reader.read_exact(&mut arg).await.ok()?;
+        arg.truncate(length);
+        args.push(String::from_utf8(arg).ok()?);
+    }
+
+    Some(args)
+}
+
+fn bulk_string(value: &str) -> String {
+    format!("${}\r\n{value}\r\n", value.len())
+}
+
+fn execute(store: &Store, command: &[String]) -> String {
+    let mut data = store.lock().unwrap();
+    let hashes = &mut data.hashes;
+    match command {
+        [name] if name.eq_ignore_ascii_case("PING") => "+PONG\r\n".to_string(),
+        [name, key, fields @ ..] if name.eq_ignore_ascii_case("HSET") && fields.len() % 2 == 0 => {
+            let hash = hashes.entry(key.clone()).or_default();
+            let added = fields.chunks(2)
+                .filter(|field| hash.insert(field[0].clone(), field[1].clone()).is_none())
+                .count();
+            format!(":{added}\r\n")
+        }
+        [name, key, field] if name.eq_ignore_ascii_case("HGET") => {
+            match hashes.get(key).and_then(|hash| hash.get(field)) {
+                Some(value) => bulk_string(value),
+                None => "$-1\r\n".to_string(),
+            }
+        }
+        [name, key, fields @ ..] if name.eq_ignore_ascii_case("HDEL") => {
+            let removed = hashes.get_mut(key)
+                .map_or(0, |hash| fields.iter().filter(|field| hash.remove(*field).is_some()).count());
+            format!(":{removed}\r\n")
+        }
+        [name, key] if name.eq_ignore_ascii_case("HGETALL") => {
+            let hash = hashes.get(key).cloned().unwrap_or_default();
+            let entries: String = hash.iter().map(|(field, value)| bulk_string(field) + &bulk_string(value)).collect();
+            format!("*{}\r\n{entries}", hash.len() * 2)
+        }
+        [name, key] if name.eq_ignore_ascii_case("GET") => {
+            match data.get(key) {
+                Some(value) => bulk_string(value),
+                None => "$-1\r\n".to_string(),
+            }
+        }
+        [name, key, value] if name.eq_ignore_ascii_case("SET") => {
+            data.strings.insert(key.clone(), (value.clone(), None));
+            "+OK\r\n".to_string()
+        }
+        // Besides plain values, only the form used to claim rooms is supported
+        [name, key, value, nx, px, milliseconds] if name.eq_ignore_ascii_case("SET") && nx.eq_ignore_ascii_case("NX") && px.eq_ignore_ascii_case("PX") => {
+            let Ok(milliseconds) = milliseconds.parse() else {
+                return "-ERR value is not an integer or out of range\r\n".to_string();
+            };
+            if data.get(key).is_some() {
+                return "$-1\r\n".to_string();
+            }
+
+            data.strings.insert(key.clone(), (value.clone(), Some(Instant::now() + Duration::from_millis(milliseconds))));
+            "+OK\r\n".to_string()
+        }
+        [name, subcommand, script] if name.eq_ignore_ascii_case("SCRIPT") && subcommand.eq_ignore_ascii_case("LOAD") => {
+            let hash = redis::Script::new(script).get_hash().to_string();
+            data.scripts.insert(hash.clone(), script.clone());
+            bulk_string(&hash)
+        }
+        [name, hash, key_count, rest @ ..] if name.eq_ignore_ascii_case("EVALSHA") => {
+            let Some(script) = data.scripts.get(hash).cloned() else {
+                return "-NOSCRIPT No matching script. Please use EVAL.\r\n".to_string();
+            };
+            let Some(key_count) = key_count.parse().ok().filter(|key_count| *key_count <= rest.len()) else {
+                return "-ERR Number of keys can't be greater than number of args\r\n".to_string();
+            };
+
+            let (keys, args) = rest.split_at(key_count);
+            run_script(&mut data, &script, keys, args)
+        }
+        _ => "-ERR unknown command\r\n".to_string(),
+    }
+}
+
+// Lua is not supported; the registry's scripts are emulated instead.
+fn run_script(data: &mut Data, script: &str, keys: &[String], args: &[String]) -> String {
+    match (script, keys, args) {
+        (RELEASE_SCRIPT, [key], [address]) => {
+            if data.get(key) == Some(address) {
+                data.strings.remove(key);
+                ":1\r\n".to_string()
+            } else {
+                ":0\r\n".to_string()
+            }
+        }
+        (REFRESH_SCRIPT, keys, [address, milliseconds]) => {
+            let Ok(milliseconds) = milliseconds.parse() else {
+                return "-ERR value is not an integer or out of range\r\n".to_string();
+            };
+
+            let expiry = Instant::now() + Duration::from_millis(milliseconds);
+            let mut refreshed = 0;
+            for key in keys {
+                if data.get(key) == Some(address) {
+                    data.strings.insert(key.clone(), (address.clone(), Some(expiry)));
+                    refreshed += 1;
+                }
+            }
+            format!(":{refreshed}\r\n")
+        }
+        _ => "-ERR unknown script\r\n".to_string(),
+    }
+}
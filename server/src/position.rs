@@ -1,9 +1,11 @@
 use std::num::TryFromIntError;
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use ts_rs::TS;
 
 pub type IPosition = (isize, isize);
 
-#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, JsonSchema, TS)]
 pub struct INamedPosition {
     pub x: isize,
     pub y: isize,
@@ -0,0 +1,155 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use axum::extract::{Path, State};
+use axum::Json;
+use chrono::Utc;
+use schemars::JsonSchema;
+use serde::Serialize;
+use crate::AppState;
+use crate::api::ApiError;
+use crate::game::puzzle::{Puzzle, PuzzleObjective, PuzzleOutcome};
+use crate::game::state::PlayerMove;
+
+// Fetching this ID returns the puzzle named after the current date
+pub const DAILY_PUZZLE_ID: &str = "daily";
+
+#[derive(Clone, Debug, Serialize, Eq, PartialEq, JsonSchema)]
+pub struct PuzzleSummary {
+    pub id: String,
+    pub name: String,
+    pub objective: PuzzleObjective,
+}
+
+#[derive(Clone, Debug, Serialize, Eq, PartialEq, JsonSchema)]
+pub struct PuzzleEntry {
+    pub id: String,
+    #[serde(flatten)]
+    pub puzzle: Puzzle,
+}
+
+// Puzzles are JSON files in the configured directory, named after their ID. They are read on every request, so new
+// puzzles can be published without restarting the server. Daily puzzles are named after their date, e.g. 2024-05-01.
+pub struct PuzzleStore {
+    directory: Option<PathBuf>,
+}
+
+impl PuzzleStore {
+    pub fn new(directory: Option<PathBuf>) -> Self {
+        Self { directory }
+    }
+
+    // IDs are used as file names, so they may not contain path separators or dots
+    fn valid_id(id: &str) -> bool {
+        !id.is_empty() && id.chars().all(|char| char.is_ascii_alphanumeric() || char == '-' || char == '_')
+    }
+
+    pub async fn get(&self, id: &str) -> Option<PuzzleEntry> {
+        let id = match id {
+            DAILY_PUZZLE_ID => Utc::now().format("%Y-%m-%d").to_string(),
+            id => id.to_string(),
+        };
+        let directory = self.directory.as_ref().filter(|_| Self::valid_id(&id))?;
+
+        let content = tokio::fs::read_to_string(directory.join(format!("{id}.json"))).await.ok()?;
+        match serde_json::from_str(&content) {
+            Ok(puzzle) => Some(PuzzleEntry { id, puzzle }),
+            Err(err) => {
+                log::warn!("Failed to parse puzzle {id}: {err}");
+                None
+            }
+        }
+    }
+
+    // Sorted by ID, so daily puzzles are sorted by date
+    pub async fn list(&self) -> Vec<PuzzleSummary> {
+        let Some(directory) = &self.directory else {
+            return Vec::new();
+        };
+        let Ok(mut entries) = tokio::fs::read_dir(directory).await else {
+            log::warn!("Failed to read the puzzle directory {}", directory.display());
+            return Vec::new();
+        };
+
+        let mut ids = Vec::new();
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            if let (Some(id), Some("json")) = (path.file_stem().and_then(|stem| stem.to_str()), path.extension().and_then(|ext| ext.to_str())) {
+                ids.push(id.to_string());
+            }
+        }
+        ids.sort();
+
+        let mut summaries = Vec::new();
+        for id in ids {
+            if let Some(PuzzleEntry { id, puzzle }) = self.get(&id).await {
+                summaries.push(PuzzleSummary { id, name: puzzle.name, objective: puzzle.objective });
+            }
+        }
+        summaries
+    }
+}
+
+pub async fn list_puzzles(State(state): State<Arc<AppState>>) -> Json<Vec<PuzzleSummary>> {
+    Json(state.puzzles.list().await)
+}
+
+pub async fn get_puzzle(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> Result<Json<PuzzleEntry>, ApiError> {
+    state.puzzles.get(&id).await
+        .map(Json)
+        .ok_or(ApiError::PuzzleNotFound(id))
+}
+
+pub async fn check_solution(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Json(solution): Json<Vec<PlayerMove>>,
+) -> Result<Json<PuzzleOutcome>, ApiError> {
+    let entry = state.puzzles.get(&id).await.ok_or(ApiError::PuzzleNotFound(id))?;
+    entry.puzzle.check_solution(&solution, state.card_provider.clone())
+        .map(Json)
+        .map_err(ApiError::InvalidSolution)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::puzzle::PuzzleCards;
+    use crate::game::rules::RuleVariant;
+    use crate::game::squares::MST;
+    use super::*;
+
+    fn puzzle(name: &str) -> Puzzle {
+        Puzzle {
+            name: name.to_string(),
+            description: String::new(),
+            board: vec!(vec!(MST::SpecialAlpha, MST::Empty, MST::SpecialBravo)),
+            rules: RuleVariant::Standard,
+            cards: PuzzleCards::Sequence(vec!("HeroShooter".to_string())),
+            opponent_moves: vec!(PlayerMove::Pass { card_name: "HeroShooter".to_string() }),
+            objective: PuzzleObjective::InkAtLeast(1),
+        }
+    }
+
+    #[tokio::test]
+    async fn reads_puzzles_from_directory() {
+        let directory = std::env::temp_dir().join(format!("tableturfer-puzzles-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let today = Utc::now().format("%Y-%m-%d").to_string();
+        for (id, name) in [("b-puzzle", "B"), ("a-puzzle", "A"), (today.as_str(), "Daily")] {
+            std::fs::write(directory.join(format!("{id}.json")), serde_json::to_string(&puzzle(name)).unwrap()).unwrap();
+        }
+        std::fs::write(directory.join("broken.json"), "{").unwrap();
+        std::fs::write(directory.join("notes.txt"), "").unwrap();
+
+        let store = PuzzleStore::new(Some(directory.clone()));
+        let names: Vec<String> = store.list().await.into_iter().map(|summary| summary.name).collect();
+        let daily = store.get(DAILY_PUZZLE_ID).await.unwrap();
+        let missing = store.get("../a-puzzle").await;
+        std::fs::remove_dir_all(directory).unwrap();
+
+        assert_eq!(vec!("Daily", "A", "B"), names);
+        assert_eq!(today, daily.id);
+        assert_eq!(puzzle("Daily"), daily.puzzle);
+        assert!(missing.is_none());
+        assert!(PuzzleStore::new(None).get("a-puzzle").await.is_none());
+    }
+}
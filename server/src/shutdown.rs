@@ -0,0 +1,63 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+use crate::AppState;
+use crate::app_config::ShutdownConfiguration;
+use crate::socket::messages::RoomEvent;
+
+// How often to check whether all games have ended while draining
+const DRAIN_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+// How long to wait for sockets to close after telling them to
+const SOCKET_CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Resolves once the process is asked to stop, either with SIGTERM or Ctrl+C.
+pub async fn signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c().await.expect("Failed to listen for Ctrl+C");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+// Tells every room that the server is restarting and disconnects everyone once users had time to see the notice.
+// Without a persistent store, running games get up to the drain period to end first.
+pub async fn drain(state: Arc<AppState>, config: ShutdownConfiguration) {
+    let (persistent, games_in_progress) = {
+        let room_store = state.read_room_store().await;
+        (room_store.is_persistent(), room_store.games_in_progress())
+    };
+    let notice_period = Duration::from_secs(config.notice_period);
+    let period = if persistent || games_in_progress == 0 { notice_period } else { Duration::from_secs(config.drain_period) };
+    let start = Instant::now();
+    let deadline = start + period;
+
+    log::info!("Shutting down within {period:?}, {games_in_progress} game(s) in progress");
+    state.read_room_store().await.notify_all(RoomEvent::ServerRestarting { seconds_remaining: period.as_secs() });
+
+    tokio::time::sleep(notice_period.min(period)).await;
+    while Instant::now() < deadline && state.read_room_store().await.games_in_progress() > 0 {
+        tokio::time::sleep_until(deadline.min(Instant::now() + DRAIN_CHECK_INTERVAL)).await;
+    }
+
+    state.write_room_store().await.shut_down();
+
+    let close_deadline = Instant::now() + SOCKET_CLOSE_TIMEOUT;
+    while state.metrics.connected_sockets() > 0 && Instant::now() < close_deadline {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    log::info!("Drained all rooms in {:?}, {} socket(s) still open", start.elapsed(), state.metrics.connected_sockets());
+}
@@ -1,11 +1,20 @@
 pub mod room_store;
 pub mod messages;
-mod close_code;
+pub mod reaper;
+pub mod room_settings;
+pub mod chat;
+pub mod limits;
+pub mod protocol;
+pub mod wire_format;
+pub mod close_code;
 mod action_handler;
+pub mod proxy;
 
+use std::net::{IpAddr, SocketAddr};
 use std::sync::{Arc};
-use axum::extract::{Query, State, WebSocketUpgrade};
+use axum::extract::{ConnectInfo, Query, State, WebSocketUpgrade};
 use axum::extract::ws::{Message, WebSocket};
+use axum::http::HeaderMap;
 use axum::response::IntoResponse;
 use futures::{SinkExt, StreamExt};
 use futures::stream::{SplitSink, SplitStream};
@@ -14,15 +23,26 @@ use tokio::sync::{broadcast, mpsc};
 use tokio::task::JoinHandle;
 use uuid::Uuid;
 use crate::AppState;
+use crate::persistence::registry;
 use crate::socket::action_handler::SocketActionHandler;
 use crate::socket::close_code::SocketCloseCode;
+use crate::socket::limits::{ActionRateLimiter, ConnectionPermit};
 use crate::socket::messages::{RoomEvent, SocketError, SocketEvent};
-use crate::socket::room_store::Room;
+use crate::socket::room_store::{JoinRequest, Room, SocketRoomStore};
+use crate::socket::wire_format::{decode_action, WireFormat};
 
 #[derive(Debug, Deserialize)]
 pub struct SocketRouteParams {
     room: Option<String>,
     username: Option<String>,
+    password: Option<String>,
+    // The reconnect token from an earlier Welcome, needed to reclaim a seat in a restored room
+    token: Option<String>,
+    // The protocol version the client was built against, see protocol.rs
+    version: Option<u32>,
+    // How events are serialized, see wire_format.rs
+    #[serde(default)]
+    format: WireFormat,
 }
 
 pub type SocketSender = mpsc::Sender<SocketEvent>;
@@ -33,11 +53,36 @@ pub struct SocketHandler {
     room_channel: broadcast::Sender<RoomEvent>,
     state: Arc<AppState>,
     room_code: String,
+    format: WireFormat,
 }
 
 impl SocketHandler {
-    pub async fn request_handler(ws: WebSocketUpgrade, State(state): State<Arc<AppState>>, Query(params): Query<SocketRouteParams>) -> impl IntoResponse {
-        ws.on_upgrade(move |socket| Self::try_init(socket, params, state))
+    pub async fn request_handler(
+        ws: WebSocketUpgrade,
+        State(state): State<Arc<AppState>>,
+        Query(params): Query<SocketRouteParams>,
+        ConnectInfo(address): ConnectInfo<SocketAddr>,
+        headers: HeaderMap,
+    ) -> impl IntoResponse {
+        let relayed = proxy::is_relayed(&headers, state.relay_secret.as_deref());
+        let client_ip = Self::client_ip(address, &headers, relayed || state.limits.trust_forwarded_for);
+        let permit = state.connection_limiter.try_acquire(client_ip);
+
+        ws.max_message_size(state.limits.max_message_size)
+            .max_frame_size(state.limits.max_message_size)
+            .on_upgrade(move |socket| Self::try_init(socket, params, state, client_ip, relayed, permit))
+    }
+
+    // Behind a load balancer (or when relayed by another instance), the client's address is taken from the
+    // X-Forwarded-For header. This must only be trusted if the header can't be set by clients themselves.
+    fn client_ip(address: SocketAddr, headers: &HeaderMap, trust_forwarded_for: bool) -> IpAddr {
+        let forwarded_ip = headers.get("x-forwarded-for")
+            .filter(|_| trust_forwarded_for)
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| header.split(',').next())
+            .and_then(|ip| ip.trim().parse().ok());
+
+        forwarded_ip.unwrap_or(address.ip())
     }
 
     fn username_is_valid(username: Option<String>) -> bool {
@@ -49,58 +94,100 @@ impl SocketHandler {
         !username.is_empty() && username.len() <= 25
     }
 
-    async fn try_init(socket: WebSocket, query: SocketRouteParams, state: Arc<AppState>) {
-        // Split the stream so we can create two separate tasks for getting data to and from the socket
-        let (mut sender, receiver) = socket.split();
+    async fn try_init(mut socket: WebSocket, query: SocketRouteParams, state: Arc<AppState>, client_ip: IpAddr, relayed: bool, permit: Option<ConnectionPermit>) {
         let id = Uuid::new_v4();
-        // As the socket's stream requires a mutable reference to send messages, we create a new channel
-        // here that as many separate threads can send messages into as needed
-        let socket_channel = mpsc::channel(8);
+
+        // The permit is held until the connection closes
+        let Some(_permit) = permit else {
+            let connection_count = state.connection_limiter.connection_count(client_ip);
+            log::debug!("Rejecting WS connection as {client_ip} already has {connection_count} open connections");
+            socket.send(Message::Close(Some(SocketCloseCode::TooManyConnections.into()))).await.ok();
+            return;
+        };
+        let _socket_guard = state.metrics.socket_connected();
+
+        if !protocol::is_supported(query.version) {
+            log::debug!("Rejecting WS connection for using protocol version {:?}", query.version);
+            socket.send(Message::Close(Some(SocketCloseCode::UnsupportedProtocol.into()))).await.ok();
+            return;
+        }
 
         if !Self::username_is_valid(query.username.clone()) {
             log::debug!("Rejecting WS connection as for having an invalid username");
-            sender.send(Message::Close(Some(SocketCloseCode::InvalidUsername.into()))).await.unwrap();
+            socket.send(Message::Close(Some(SocketCloseCode::InvalidUsername.into()))).await.unwrap();
             return;
         }
 
         let username = query.username.unwrap();
+        let request = JoinRequest { username: &username, client_ip, password: query.password.as_deref(), token: query.token.as_deref() };
 
-        let (room_code, room) = Self::get_and_join_room(id, &username, state.clone(), query.room, socket_channel.0.clone()).await;
-        if room.is_none() {
-            log::debug!("Rejecting WS connection as it attempted to join a non-existent room");
-            sender.send(Message::Close(Some(SocketCloseCode::RoomNotFound(room_code).into()))).await.unwrap();
-            return;
+        // Rooms hosted by another instance are joined through that instance
+        if let Some(room_code) = query.room.as_ref().filter(|_| !relayed) {
+            let room_code = room_code.to_uppercase();
+            let registry = state.read_room_store().await.registry_for(&room_code);
+            let remote_host = match registry {
+                Some(registry) => registry::remote_host(registry.as_ref(), &room_code).await,
+                None => None,
+            };
+            if let Some(remote_host) = remote_host {
+                log::debug!("Relaying WS connection {id} to {remote_host}, which hosts room {room_code}");
+                match proxy::connect_to_host(&remote_host, &room_code, &request, query.format, client_ip, state.relay_secret.as_deref().unwrap_or_default()).await {
+                    Some(host_socket) => proxy::relay(socket, host_socket).await,
+                    None => {
+                        socket.send(Message::Close(Some(SocketCloseCode::RoomNotFound(room_code).into()))).await.ok();
+                    }
+                }
+                return;
+            }
         }
 
-        let room = room.unwrap();
+        // Split the stream so we can create two separate tasks for getting data to and from the socket
+        let (mut sender, receiver) = socket.split();
+        // As the socket's stream requires a mutable reference to send messages, we create a new channel
+        // here that as many separate threads can send messages into as needed
+        let socket_channel = mpsc::channel(8);
+
+        let (room_code, room) = Self::get_and_join_room(id, request, state.clone(), query.room, socket_channel.0.clone()).await;
+        let room = match room {
+            Ok(room) => room,
+            Err(close_code) => {
+                log::debug!("Rejecting WS connection as it could not join room {room_code}: {close_code:?}");
+                sender.send(Message::Close(Some(close_code.into()))).await.unwrap();
+                return;
+            }
+        };
 
         Self {
             id,
             socket_channel: socket_channel.0,
             room_channel: room.sender.clone(),
             state,
-            room_code
+            room_code,
+            format: query.format,
         }.init(room, sender, receiver, socket_channel.1).await;
     }
 
     async fn get_and_join_room(
         id: Uuid,
-        username: &str,
+        request: JoinRequest<'_>,
         state: Arc<AppState>,
         room_code: Option<String>,
         event_sender: SocketSender
-    ) -> (String, Option<Room>)
+    ) -> (String, Result<Room, SocketCloseCode>)
     {
-        let mut room_store = state.room_store.write().await;
-
         match room_code {
             Some(room_code) => {
                 let room_code = room_code.to_uppercase();
-                (room_code.to_owned(), room_store.get_and_join_if_exists(&room_code, id, username, event_sender))
+                let room = state.write_room_store().await.get_and_join_if_exists(&room_code, id, request, event_sender);
+                (room_code, room)
             }
             None => {
-                let (room_code, room) = room_store.create(id.clone(), username, event_sender);
-                (room_code, Some(room))
+                log::debug!("Connection {id} is creating a new room");
+                let Some(room_code) = SocketRoomStore::claim_new_room_code(&state.room_store).await else {
+                    return (String::new(), Err(SocketCloseCode::RoomUnavailable));
+                };
+                let room = state.write_room_store().await.create(room_code.clone(), id, request, event_sender);
+                (room_code, room)
             }
         }
     }
@@ -110,7 +197,7 @@ impl SocketHandler {
 
         let mut receive_from_client_task = self.listen_to_client(receiver);
         let receive_from_room_task = self.receive_from_room();
-        let mut return_to_client_task = Self::return_to_client(socket_channel_receiver, sender);
+        let mut return_to_client_task = Self::return_to_client(socket_channel_receiver, sender, self.format);
 
         self.socket_channel.send(self.create_welcome_event(room)).await.unwrap();
 
@@ -121,7 +208,7 @@ impl SocketHandler {
         }
         receive_from_room_task.abort();
         {
-            let mut room_store = self.state.room_store.write().await;
+            let mut room_store = self.state.write_room_store().await;
             room_store.remove_user_from_room(&self.room_code, self.id);
         }
 
@@ -132,32 +219,70 @@ impl SocketHandler {
         SocketEvent::Welcome {
             id: self.id,
             room_code: self.room_code.clone(),
+            reconnect_token: room.reconnect_token(self.id).cloned().unwrap_or_default(),
             started: room.game_started(),
+            game: room.game_progress(self.id).map(Box::new),
+            map_pool: Box::new(room.map_pool()),
             users: room.users,
             owner: room.owner_id,
             opponent: room.opponent_id,
             map: room.map,
+            series: room.series,
+            settings: room.settings.summary(),
+            chat: room.chat.history(),
+            draft: room.draft.map(Box::new),
+            map_veto: room.map_veto.map(Box::new),
+            sandbox: room.sandbox.map(|sandbox| Box::new(sandbox.state())),
+            puzzle: room.puzzle.map(|puzzle| Box::new(puzzle.state())),
         }
     }
 
     fn listen_to_client(&self, mut receiver: SplitStream<WebSocket>) -> JoinHandle<()> {
         let action_handler = SocketActionHandler::new(
-            self.id.clone(),
+            self.id,
             self.socket_channel.clone(),
             self.state.clone(),
             self.room_code.clone());
 
+        let limits = self.state.limits.clone();
+        let mut rate_limiter = ActionRateLimiter::new(limits.action_burst, limits.actions_per_second);
+        let socket_channel = self.socket_channel.clone();
+        let state = self.state.clone();
+        let id = self.id;
+
         tokio::spawn(async move {
-            // Ignore non-text messages, keep listening until we get an error
+            let mut rate_limit_violations = 0;
+
+            // Ignore control frames, keep listening until we get an error
             while let Some(Ok(message)) = receiver.next().await {
-                if let Message::Text(text) = message {
-                    match serde_json::from_str(&text) {
-                        Ok(action) => action_handler.handle_action(action).await,
-                        Err(_) => {
-                            log::debug!("Failed to parse message from client");
-                            action_handler.send_error(SocketError::MessageParsingFailed).await.unwrap();
-                        }
+                if !matches!(message, Message::Text(_) | Message::Binary(_)) {
+                    continue;
+                }
+
+                // Messages over the rate limit are dropped; clients that keep sending them anyway get disconnected
+                if !rate_limiter.try_acquire() {
+                    rate_limit_violations += 1;
+                    if rate_limit_violations > limits.max_rate_limit_violations {
+                        log::debug!("Closing WS connection {} for exceeding its rate limit", id);
+                        socket_channel.send(SocketEvent::Close(SocketCloseCode::Abusive)).await.ok();
+                        // Wait for the close frame to be sent out before ending the connection
+                        socket_channel.closed().await;
+                        break;
+                    }
+
+                    state.metrics.record_action_error(&SocketError::RateLimited);
+                    socket_channel.try_send(SocketEvent::Error(SocketError::RateLimited)).ok();
+                    continue;
+                }
+
+                rate_limit_violations = 0;
+                match decode_action(&message) {
+                    Some(Ok(action)) => action_handler.handle_action(action).await,
+                    Some(Err(err)) => {
+                        log::debug!("Failed to parse message from client: {err}");
+                        action_handler.send_error(SocketError::MessageParsingFailed).await.unwrap();
                     }
+                    None => {}
                 }
             }
         })
@@ -176,12 +301,17 @@ impl SocketHandler {
         })
     }
 
-    fn return_to_client(mut socket_rx: mpsc::Receiver<SocketEvent>, mut sender: SplitSink<WebSocket, Message>) -> JoinHandle<()> {
+    fn return_to_client(mut socket_rx: mpsc::Receiver<SocketEvent>, mut sender: SplitSink<WebSocket, Message>, format: WireFormat) -> JoinHandle<()> {
         tokio::spawn(async move {
             while let Some(msg) = socket_rx.recv().await {
-                match serde_json::to_string(&msg) {
-                    Ok(msg_string) => {
-                        if sender.send(Message::Text(msg_string)).await.is_err() {
+                if let SocketEvent::Close(close_code) = msg {
+                    sender.send(Message::Close(Some(close_code.into()))).await.ok();
+                    break;
+                }
+
+                match format.encode(&msg) {
+                    Ok(message) => {
+                        if sender.send(message).await.is_err() {
                             socket_rx.close();
                             break;
                         }
@@ -194,3 +324,211 @@ impl SocketHandler {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use chrono::Utc;
+    use indexmap::IndexSet;
+    use serde_json::{json, Value};
+    use crate::game::card::{CardProvider, CardSquareProviderImpl};
+    use crate::game::map::{DEFAULT_GAME_MAP, MapProvider, MapProviderImpl};
+    use crate::game::state::{DECK_SIZE, HAND_SIZE, PlayerMove, TURN_COUNT};
+    use crate::game::team::PlayerTeam;
+    use crate::socket::room_store::{RoomUser, RoomUserDeck};
+    use axum::extract::ws::CloseFrame;
+    use crate::test_harness::{TestClient, TestServer};
+    use super::*;
+
+    fn deck() -> IndexSet<String> {
+        CardSquareProviderImpl::new().get_all().into_iter().take(DECK_SIZE).map(|card| card.name).collect()
+    }
+
+    fn user(username: &str, deck: Option<&IndexSet<String>>) -> RoomUser {
+        RoomUser {
+            username: username.to_string(),
+            joined_at: Utc::now(),
+            deck: deck.map(|cards| RoomUserDeck { id: username.to_string(), cards: cards.clone() }),
+            muted: false,
+        }
+    }
+
+    fn room_event(event: RoomEvent) -> SocketEvent {
+        SocketEvent::RoomEvent(event)
+    }
+
+    fn initial_score() -> HashMap<PlayerTeam, usize> {
+        let map = MapProviderImpl::new().get(DEFAULT_GAME_MAP).unwrap();
+        let score = |team| map.squares.clone().into_iter().filter(|(square, _)| square.team().as_ref() == Some(&team)).count();
+        HashMap::from([(PlayerTeam::Alpha, score(PlayerTeam::Alpha)), (PlayerTeam::Bravo, score(PlayerTeam::Bravo))])
+    }
+
+    fn hand(event: &Value) -> IndexSet<String> {
+        serde_json::from_value(event["detail"]["detail"].clone()).unwrap()
+    }
+
+    // Creates a room with an owner and an opponent
+    async fn join_room(server: &TestServer) -> (TestClient, TestClient) {
+        let mut owner = server.connect("owner", None).await;
+        let opponent = server.connect("opponent", Some(&owner.room_code)).await;
+        owner.expect(room_event(RoomEvent::UserJoin { id: opponent.id, user: user("opponent", None) })).await;
+        owner.expect(room_event(RoomEvent::OpponentChange(Some(opponent.id)))).await;
+        (owner, opponent)
+    }
+
+    async fn set_deck(client: &mut TestClient, username: &str, others: &mut [&mut TestClient]) {
+        let deck = deck();
+        client.send(json!({ "action": "SetDeck", "args": { "id": username, "cards": deck } })).await;
+
+        let event = RoomEvent::UserUpdate { id: client.id, user: user(username, Some(&deck)) };
+        client.expect(room_event(event.clone())).await;
+        for other in others {
+            other.expect(room_event(event.clone())).await;
+        }
+    }
+
+    #[test]
+    fn only_trusts_relays_with_the_cluster_secret() {
+        let address = SocketAddr::from(([10, 0, 0, 2], 1234));
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "203.0.113.7".parse().unwrap());
+        assert!(!proxy::is_relayed(&headers, Some("secret")));
+
+        headers.insert(proxy::RELAY_SECRET_HEADER, "guess".parse().unwrap());
+        assert!(!proxy::is_relayed(&headers, Some("secret")));
+
+        headers.insert(proxy::RELAY_SECRET_HEADER, "secret".parse().unwrap());
+        assert!(proxy::is_relayed(&headers, Some("secret")));
+        assert!(!proxy::is_relayed(&headers, None));
+
+        assert_eq!(address.ip(), SocketHandler::client_ip(address, &headers, false));
+        assert_eq!(IpAddr::from([203, 0, 113, 7]), SocketHandler::client_ip(address, &headers, true));
+    }
+
+    #[tokio::test]
+    async fn plays_game() {
+        let server = TestServer::start().await;
+        let (mut owner, mut opponent) = join_room(&server).await;
+        set_deck(&mut owner, "owner", &mut [&mut opponent]).await;
+        set_deck(&mut opponent, "opponent", &mut [&mut owner]).await;
+
+        owner.send(json!({ "action": "StartGame" })).await;
+        let start = RoomEvent::StartGame { score: initial_score(), map_name: DEFAULT_GAME_MAP.to_string() };
+        let mut hands = HashMap::new();
+        for (team, client) in [(PlayerTeam::Alpha, &mut owner), (PlayerTeam::Bravo, &mut opponent)] {
+            let hand = hand(&client.expect_unordered_with(vec!(room_event(start.clone())), "HandAssigned").await);
+            assert_eq!(HAND_SIZE, hand.len());
+            assert!(hand.is_subset(&deck()));
+            hands.insert(team, hand);
+        }
+
+        // Both players pass every turn, so the score never changes
+        for remaining_turns in (1..=TURN_COUNT).rev() {
+            let moves: HashMap<PlayerTeam, PlayerMove> = hands.iter()
+                .map(|(team, hand)| (team.clone(), PlayerMove::Pass { card_name: hand[0].clone() }))
+                .collect();
+
+            owner.send(json!({ "action": "ProposeMove", "args": moves[&PlayerTeam::Alpha] })).await;
+            let alpha_moved = RoomEvent::MoveReceived { team: PlayerTeam::Alpha, remaining_turns };
+            owner.expect(room_event(alpha_moved.clone())).await;
+            opponent.expect(room_event(alpha_moved)).await;
+
+            opponent.send(json!({ "action": "ProposeMove", "args": moves[&PlayerTeam::Bravo] })).await;
+            let mut events = vec!(
+                RoomEvent::MoveReceived { team: PlayerTeam::Bravo, remaining_turns },
+                RoomEvent::MovesApplied { moves: moves.clone(), score: initial_score() },
+            );
+            if remaining_turns == 1 {
+                events.push(RoomEvent::EndGame { score: initial_score() });
+            }
+
+            for (team, client) in [(PlayerTeam::Alpha, &mut owner), (PlayerTeam::Bravo, &mut opponent)] {
+                let events = events.iter().cloned().map(room_event).collect();
+                if remaining_turns == 1 {
+                    client.expect_unordered(events).await;
+                    continue;
+                }
+
+                let next_card = client.expect_unordered_with(events, "NextCardDrawn").await;
+                let hand = hands.get_mut(&team).unwrap();
+                assert_eq!(json!(hand[0]), next_card["detail"]["detail"]["replacing"]);
+                hand.shift_remove_index(0);
+                hand.insert(next_card["detail"]["detail"]["newCard"].as_str().unwrap().to_string());
+            }
+        }
+
+        owner.send(json!({ "action": "ReturnToRoom" })).await;
+        owner.expect(room_event(RoomEvent::ReturnToRoom)).await;
+        opponent.expect(room_event(RoomEvent::ReturnToRoom)).await;
+        owner.expect_nothing().await;
+        opponent.expect_nothing().await;
+
+        // The result is recorded before the room store is unlocked again
+        let maps = server.state.analytics.maps();
+        assert_eq!((DEFAULT_GAME_MAP, 1, 1), (maps[0].name.as_str(), maps[0].games, maps[0].draws));
+    }
+
+    #[tokio::test]
+    async fn rejects_actions_before_game_can_start() {
+        let server = TestServer::start().await;
+        let mut owner = server.connect("owner", None).await;
+
+        owner.send(json!({ "action": "StartGame" })).await;
+        owner.expect(SocketEvent::Error(SocketError::MissingOpponent)).await;
+
+        let mut opponent = server.connect("opponent", Some(&owner.room_code)).await;
+        owner.expect(room_event(RoomEvent::UserJoin { id: opponent.id, user: user("opponent", None) })).await;
+        owner.expect(room_event(RoomEvent::OpponentChange(Some(opponent.id)))).await;
+
+        owner.send(json!({ "action": "StartGame" })).await;
+        owner.expect(SocketEvent::Error(SocketError::DecksNotChosen)).await;
+        opponent.send(json!({ "action": "StartGame" })).await;
+        opponent.expect(SocketEvent::Error(SocketError::UserNotRoomOwner)).await;
+        opponent.send(json!({ "action": "ProposeMove", "args": { "type": "Pass", "cardName": deck()[0] } })).await;
+        opponent.expect(SocketEvent::Error(SocketError::RoomNotStarted)).await;
+        opponent.send(json!({ "action": "Unknown" })).await;
+        opponent.expect(SocketEvent::Error(SocketError::MessageParsingFailed)).await;
+
+        owner.expect_nothing().await;
+    }
+
+    #[tokio::test]
+    async fn changes_owner_when_users_leave() {
+        let server = TestServer::start().await;
+        let (mut owner, mut opponent) = join_room(&server).await;
+        let mut spectator = server.connect("spectator", Some(&owner.room_code)).await;
+        let spectator_join = RoomEvent::UserJoin { id: spectator.id, user: user("spectator", None) };
+        owner.expect(room_event(spectator_join.clone())).await;
+        opponent.expect(room_event(spectator_join)).await;
+
+        // The owner hands the room to the opponent, who becomes the owner while the previous owner takes their seat
+        let (owner_id, opponent_id, spectator_id) = (owner.id, opponent.id, spectator.id);
+        owner.send(json!({ "action": "TransferOwnership", "args": opponent_id })).await;
+        for client in [&mut owner, &mut opponent, &mut spectator] {
+            client.expect(room_event(RoomEvent::OpponentChange(None))).await;
+            client.expect(room_event(RoomEvent::OwnerChange(opponent_id))).await;
+            client.expect(room_event(RoomEvent::OpponentChange(Some(owner_id)))).await;
+        }
+
+        // When the new owner leaves, the user who joined first takes over and the next one gets the empty seat
+        opponent.leave().await;
+        for client in [&mut owner, &mut spectator] {
+            client.expect(room_event(RoomEvent::UserLeave(opponent_id))).await;
+            client.expect(room_event(RoomEvent::OpponentChange(None))).await;
+            client.expect(room_event(RoomEvent::OwnerChange(owner_id))).await;
+            client.expect(room_event(RoomEvent::OpponentChange(Some(spectator_id)))).await;
+        }
+
+        owner.expect_nothing().await;
+        spectator.expect_nothing().await;
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_rooms() {
+        let server = TestServer::start().await;
+
+        let close_code = server.connect_rejected("user", Some("ZZZZ")).await;
+
+        assert_eq!(CloseFrame::from(SocketCloseCode::RoomNotFound("ZZZZ".to_string())).code, close_code);
+    }
+}
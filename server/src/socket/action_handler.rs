@@ -27,9 +27,17 @@ impl SocketActionHandler {
     }
 
     pub async fn handle_action(&self, action: SocketAction) {
+        // Puzzles are read from disk, which shouldn't hold up every other room while the room store is locked
+        let puzzle = match &action {
+            SocketAction::StartPuzzle(id) => self.state.puzzles.get(id).await,
+            _ => None,
+        };
+
         let result: ActionHandlerResult = {
-            let mut room_store = self.state.room_store.write().await;
-            if let Some(room) = room_store.get_mut(&self.room_code) {
+            let mut room_store = self.state.write_room_store().await;
+            let changes_room = !matches!(action, SocketAction::Ping);
+            let changes_persisted_state = action.changes_persisted_state();
+            let result = if let Some(room) = room_store.get_mut(&self.room_code) {
                 let auth_result = self.authorize_action(action.clone(), room);
 
                 if auth_result.is_ok() {
@@ -42,13 +50,52 @@ impl SocketActionHandler {
                         },
                         SocketAction::ProposeMove(player_move) => {
                             let team = self.team(room);
-                            room.propose_move(team.unwrap(), player_move).await
+                            let result = room.propose_move(team.unwrap(), player_move).await;
+                            if result.is_ok() {
+                                self.state.metrics.record_move();
+                                // The last move of a game completes it
+                                if let Some(record) = room.game_record() {
+                                    self.state.metrics.record_game_completed();
+                                    self.state.analytics.record(record);
+                                }
+                            }
+                            result
                         },
                         SocketAction::SetDeck { id, cards } => room.set_deck(self.id, id, cards),
                         SocketAction::ReturnToRoom => {
                             room.return_to_room();
                             Ok(())
                         },
+                        SocketAction::SetSeries(settings) => room.set_series(settings),
+                        SocketAction::PickMap(map) => room.pick_map(self.id, map),
+                        SocketAction::SetRoomSettings(settings) => room.set_settings(settings),
+                        SocketAction::Chat(message) => room.send_chat_message(self.id, message),
+                        SocketAction::Emote(emote) => room.send_emote(self.id, emote),
+                        SocketAction::SetMuted { id, muted } => room.set_muted(id, muted),
+                        SocketAction::KickUser(id) => room.kick_user(id),
+                        SocketAction::TransferOwnership(id) => room.transfer_ownership(id),
+                        SocketAction::ChooseOpponent(id) => room.choose_opponent(id),
+                        SocketAction::StartDraft => room.start_draft(),
+                        SocketAction::StartMapVeto => room.start_map_veto(),
+                        SocketAction::SetMapPool(map_pool) => room.set_map_pool(map_pool),
+                        SocketAction::StartSandbox => room.start_sandbox(),
+                        SocketAction::SandboxTurn(moves) => room.play_sandbox_turn(moves),
+                        SocketAction::SandboxSetSquare { position, square } => room.set_sandbox_square(position, square),
+                        SocketAction::SandboxUndo => room.undo_sandbox(),
+                        SocketAction::SandboxRedo => room.redo_sandbox(),
+                        SocketAction::StartPuzzle(id) => match &puzzle {
+                            Some(entry) => room.start_puzzle(&entry.puzzle),
+                            None => Err(SocketError::PuzzleNotFound(id)),
+                        },
+                        SocketAction::PuzzleMove(player_move) => room.play_puzzle_move(player_move),
+                        SocketAction::BanMap(map) => {
+                            let team = self.team(room);
+                            room.ban_map(team.unwrap(), map)
+                        },
+                        SocketAction::DraftPick(card) => {
+                            let team = self.team(room);
+                            room.pick_draft_card(team.unwrap(), card).await
+                        },
                         SocketAction::Ping => {
                             self.socket_channel.send(SocketEvent::Pong).await.ok();
                             Ok(())
@@ -59,7 +106,18 @@ impl SocketActionHandler {
                 }
             } else {
                 Err(SocketError::RoomNotFound(self.room_code.clone()))
+            };
+
+            if result.is_ok() && changes_room {
+                if let Some(room) = room_store.get_mut(&self.room_code) {
+                    room.touch();
+                }
+                if changes_persisted_state {
+                    room_store.persist(&self.room_code);
+                }
             }
+
+            result
         };
 
         if let Err(err) = result {
@@ -81,16 +139,11 @@ impl SocketActionHandler {
     }
 
     fn team(&self, room: &Room) -> Option<PlayerTeam> {
-        if room.owner_id == self.id {
-            Some(PlayerTeam::Alpha)
-        } else if room.opponent_id == Some(self.id) {
-            Some(PlayerTeam::Bravo)
-        } else {
-            None
-        }
+        room.team(self.id)
     }
 
     pub async fn send_error(&self, err: SocketError) -> Result<(), SendError<SocketEvent>> {
+        self.state.metrics.record_action_error(&err);
         self.socket_channel.send(SocketEvent::Error(err)).await
     }
 }
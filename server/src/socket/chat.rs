@@ -0,0 +1,183 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use ts_rs::TS;
+use uuid::Uuid;
+use crate::socket::messages::SocketError;
+
+pub const MAX_MESSAGE_LENGTH: usize = 200;
+pub const HISTORY_SIZE: usize = 50;
+// Every user may send this many chat messages and emotes combined within the rate limit window
+pub const RATE_LIMIT_MESSAGES: usize = 5;
+pub const RATE_LIMIT_WINDOW_SECONDS: i64 = 5;
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, Eq, PartialEq, JsonSchema, TS)]
+pub enum Emote {
+    Hello,
+    GoodLuck,
+    WellPlayed,
+    GoodGame,
+    Thanks,
+    Oops,
+    Wow,
+    Rematch,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatMessage {
+    pub user_id: Uuid,
+    pub username: String,
+    pub message: String,
+    pub sent_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct RoomChat {
+    history: VecDeque<ChatMessage>,
+    // Mutes are kept by username, so reconnecting does not lift them
+    muted_users: HashSet<String>,
+    #[serde(skip)]
+    recent_messages: HashMap<Uuid, VecDeque<DateTime<Utc>>>,
+}
+
+impl RoomChat {
+    pub fn history(&self) -> Vec<ChatMessage> {
+        self.history.iter().cloned().collect()
+    }
+
+    pub fn is_muted(&self, username: &str) -> bool {
+        self.muted_users.contains(username)
+    }
+
+    pub fn set_muted(&mut self, username: &str, muted: bool) {
+        if muted {
+            self.muted_users.insert(username.to_string());
+        } else {
+            self.muted_users.remove(username);
+        }
+    }
+
+    pub fn remove_user(&mut self, user_id: Uuid) {
+        self.recent_messages.remove(&user_id);
+    }
+
+    pub fn add_message(&mut self, user_id: Uuid, username: &str, message: String, now: DateTime<Utc>) -> Result<ChatMessage, SocketError> {
+        let message = message.trim();
+        if message.is_empty() || message.chars().count() > MAX_MESSAGE_LENGTH {
+            return Err(SocketError::InvalidChatMessage);
+        }
+
+        self.check_sender(user_id, username, now)?;
+
+        let message = ChatMessage {
+            user_id,
+            username: username.to_string(),
+            message: message.to_string(),
+            sent_at: now,
+        };
+
+        if self.history.len() >= HISTORY_SIZE {
+            self.history.pop_front();
+        }
+        self.history.push_back(message.clone());
+        Ok(message)
+    }
+
+    pub fn add_emote(&mut self, user_id: Uuid, username: &str, now: DateTime<Utc>) -> Result<(), SocketError> {
+        self.check_sender(user_id, username, now)
+    }
+
+    fn check_sender(&mut self, user_id: Uuid, username: &str, now: DateTime<Utc>) -> Result<(), SocketError> {
+        if self.is_muted(username) {
+            return Err(SocketError::UserMuted);
+        }
+
+        let recent_messages = self.recent_messages.entry(user_id).or_default();
+        let window_start = now - Duration::seconds(RATE_LIMIT_WINDOW_SECONDS);
+        while recent_messages.front().is_some_and(|sent_at| *sent_at <= window_start) {
+            recent_messages.pop_front();
+        }
+
+        if recent_messages.len() >= RATE_LIMIT_MESSAGES {
+            return Err(SocketError::ChatRateLimited);
+        }
+
+        recent_messages.push_back(now);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_message() {
+        let mut chat = RoomChat::default();
+        let user_id = Uuid::new_v4();
+        let now = Utc::now();
+
+        let message = chat.add_message(user_id, "user", "  gg  ".to_string(), now).unwrap();
+
+        assert_eq!(ChatMessage { user_id, username: "user".to_string(), message: "gg".to_string(), sent_at: now }, message);
+        assert_eq!(vec!(message), chat.history());
+    }
+
+    #[test]
+    fn add_message_rejects_invalid_messages() {
+        let mut chat = RoomChat::default();
+        let user_id = Uuid::new_v4();
+
+        assert!(matches!(chat.add_message(user_id, "user", "   ".to_string(), Utc::now()), Err(SocketError::InvalidChatMessage)));
+        assert!(matches!(
+            chat.add_message(user_id, "user", "a".repeat(MAX_MESSAGE_LENGTH + 1), Utc::now()),
+            Err(SocketError::InvalidChatMessage)));
+        assert!(chat.add_message(user_id, "user", "a".repeat(MAX_MESSAGE_LENGTH), Utc::now()).is_ok());
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let mut chat = RoomChat::default();
+        let start = Utc::now();
+
+        for i in 0..HISTORY_SIZE + 10 {
+            // Use a new user for every message to stay clear of the rate limit
+            chat.add_message(Uuid::new_v4(), "user", i.to_string(), start).unwrap();
+        }
+
+        let history = chat.history();
+        assert_eq!(HISTORY_SIZE, history.len());
+        assert_eq!("10", history[0].message);
+    }
+
+    #[test]
+    fn rate_limit() {
+        let mut chat = RoomChat::default();
+        let user_id = Uuid::new_v4();
+        let start = Utc::now();
+
+        for _ in 0..RATE_LIMIT_MESSAGES - 1 {
+            chat.add_message(user_id, "user", "hi".to_string(), start).unwrap();
+        }
+        chat.add_emote(user_id, "user", start).unwrap();
+
+        assert!(matches!(chat.add_emote(user_id, "user", start), Err(SocketError::ChatRateLimited)));
+        assert!(chat.add_emote(Uuid::new_v4(), "other", start).is_ok());
+        assert!(chat.add_emote(user_id, "user", start + Duration::seconds(RATE_LIMIT_WINDOW_SECONDS)).is_ok());
+    }
+
+    #[test]
+    fn muted_users_cannot_chat() {
+        let mut chat = RoomChat::default();
+        let user_id = Uuid::new_v4();
+        chat.set_muted("user", true);
+
+        assert!(matches!(chat.add_message(user_id, "user", "hi".to_string(), Utc::now()), Err(SocketError::UserMuted)));
+        assert!(matches!(chat.add_emote(user_id, "user", Utc::now()), Err(SocketError::UserMuted)));
+
+        chat.set_muted("user", false);
+        assert!(chat.add_emote(user_id, "user", Utc::now()).is_ok());
+    }
+}
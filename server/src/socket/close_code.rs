@@ -1,14 +1,28 @@
 use std::borrow::Cow;
 use axum::extract::ws::CloseFrame;
+use crate::socket::protocol::PROTOCOL_VERSION;
 
+#[derive(Clone, Debug)]
 pub enum SocketCloseCode {
     RoomNotFound(String),
     InvalidUsername,
+    RoomExpired,
+    WrongPassword,
+    RoomFull,
+    Kicked,
+    Banned,
+    Abusive,
+    TooManyConnections,
+    UnsupportedProtocol,
+    ServerRestarting,
+    RoomClosed,
+    RoomUnavailable,
+    InvalidToken,
 }
 
-impl Into<CloseFrame<'_>> for SocketCloseCode {
-    fn into(self) -> CloseFrame<'static> {
-        match self {
+impl From<SocketCloseCode> for CloseFrame<'_> {
+    fn from(code: SocketCloseCode) -> Self {
+        match code {
             SocketCloseCode::RoomNotFound(room_code) => {
                 CloseFrame {
                     code: 4000,
@@ -20,6 +34,78 @@ impl Into<CloseFrame<'_>> for SocketCloseCode {
                     code: 4001,
                     reason: Cow::from("Either no username or an invalid username was supplied.")
                 }
+            },
+            SocketCloseCode::RoomExpired => {
+                CloseFrame {
+                    code: 4002,
+                    reason: Cow::from("The room was closed after being inactive for too long.")
+                }
+            },
+            SocketCloseCode::WrongPassword => {
+                CloseFrame {
+                    code: 4003,
+                    reason: Cow::from("The room's password was either missing or incorrect.")
+                }
+            },
+            SocketCloseCode::RoomFull => {
+                CloseFrame {
+                    code: 4004,
+                    reason: Cow::from("The room has no space for more spectators.")
+                }
+            },
+            SocketCloseCode::Kicked => {
+                CloseFrame {
+                    code: 4005,
+                    reason: Cow::from("You were kicked from the room and may not rejoin it.")
+                }
+            },
+            SocketCloseCode::Banned => {
+                CloseFrame {
+                    code: 4006,
+                    reason: Cow::from("You were kicked from this room and may not rejoin it.")
+                }
+            },
+            SocketCloseCode::Abusive => {
+                CloseFrame {
+                    code: 4007,
+                    reason: Cow::from("Too many messages were sent in a short amount of time.")
+                }
+            },
+            SocketCloseCode::TooManyConnections => {
+                CloseFrame {
+                    code: 4008,
+                    reason: Cow::from("There are too many open connections from your address.")
+                }
+            },
+            SocketCloseCode::UnsupportedProtocol => {
+                CloseFrame {
+                    code: 4009,
+                    reason: Cow::from(format!("This client is out of date, the server expects protocol version {PROTOCOL_VERSION}."))
+                }
+            },
+            SocketCloseCode::ServerRestarting => {
+                CloseFrame {
+                    code: 4010,
+                    reason: Cow::from("The server is restarting.")
+                }
+            },
+            SocketCloseCode::RoomClosed => {
+                CloseFrame {
+                    code: 4011,
+                    reason: Cow::from("The room was closed by an administrator.")
+                }
+            },
+            SocketCloseCode::RoomUnavailable => {
+                CloseFrame {
+                    code: 4012,
+                    reason: Cow::from("A new room could not be created, please try again later.")
+                }
+            },
+            SocketCloseCode::InvalidToken => {
+                CloseFrame {
+                    code: 4013,
+                    reason: Cow::from("This username belongs to a player of the room, who has to join with their token.")
+                }
             }
         }
     }
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+// Limits how many WS connections may be open from the same IP address at once.
+pub struct ConnectionLimiter {
+    max_connections_per_ip: usize,
+    connections: Mutex<HashMap<IpAddr, usize>>,
+}
+
+// Counts as an open connection until dropped.
+pub struct ConnectionPermit {
+    limiter: Arc<ConnectionLimiter>,
+    ip: IpAddr,
+}
+
+impl ConnectionLimiter {
+    pub fn new(max_connections_per_ip: usize) -> Self {
+        Self {
+            max_connections_per_ip,
+            connections: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn try_acquire(self: &Arc<Self>, ip: IpAddr) -> Option<ConnectionPermit> {
+        let mut connections = self.connections.lock().unwrap();
+        let count = connections.entry(ip).or_insert(0);
+        if *count >= self.max_connections_per_ip {
+            return None;
+        }
+
+        *count += 1;
+        Some(ConnectionPermit { limiter: self.clone(), ip })
+    }
+
+    pub fn connection_count(&self, ip: IpAddr) -> usize {
+        self.connections.lock().unwrap().get(&ip).copied().unwrap_or(0)
+    }
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        let mut connections = self.limiter.connections.lock().unwrap();
+        if let Some(count) = connections.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                connections.remove(&self.ip);
+            }
+        }
+    }
+}
+
+// A token bucket: allows short bursts of actions, refilling at a steady rate.
+pub struct ActionRateLimiter {
+    capacity: f64,
+    refill_per_second: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl ActionRateLimiter {
+    pub fn new(burst: usize, actions_per_second: f64) -> Self {
+        Self::starting_at(burst, actions_per_second, Instant::now())
+    }
+
+    fn starting_at(burst: usize, actions_per_second: f64, now: Instant) -> Self {
+        Self {
+            capacity: burst as f64,
+            refill_per_second: actions_per_second,
+            tokens: burst as f64,
+            last_refill: now,
+        }
+    }
+
+    pub fn try_acquire(&mut self) -> bool {
+        self.try_acquire_at(Instant::now())
+    }
+
+    fn try_acquire_at(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_second).min(self.capacity);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use super::*;
+
+    #[test]
+    fn connection_limiter() {
+        let limiter = Arc::new(ConnectionLimiter::new(2));
+        let ip: IpAddr = "127.0.0.1".parse().unwrap();
+        let other_ip: IpAddr = "127.0.0.2".parse().unwrap();
+
+        let first = limiter.try_acquire(ip).unwrap();
+        let _second = limiter.try_acquire(ip).unwrap();
+        assert!(limiter.try_acquire(ip).is_none());
+        assert!(limiter.try_acquire(other_ip).is_some());
+
+        drop(first);
+        assert_eq!(1, limiter.connection_count(ip));
+        assert!(limiter.try_acquire(ip).is_some());
+    }
+
+    #[test]
+    fn connection_limiter_forgets_closed_connections() {
+        let limiter = Arc::new(ConnectionLimiter::new(1));
+        let ip: IpAddr = "::1".parse().unwrap();
+
+        drop(limiter.try_acquire(ip));
+
+        assert_eq!(0, limiter.connection_count(ip));
+        assert!(limiter.connections.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn action_rate_limiter_allows_bursts() {
+        let start = Instant::now();
+        let mut limiter = ActionRateLimiter::starting_at(3, 1.0, start);
+
+        assert!(limiter.try_acquire_at(start));
+        assert!(limiter.try_acquire_at(start));
+        assert!(limiter.try_acquire_at(start));
+        assert!(!limiter.try_acquire_at(start));
+    }
+
+    #[test]
+    fn action_rate_limiter_refills() {
+        let start = Instant::now();
+        let mut limiter = ActionRateLimiter::starting_at(2, 2.0, start);
+        limiter.try_acquire_at(start);
+        limiter.try_acquire_at(start);
+
+        assert!(!limiter.try_acquire_at(start + Duration::from_millis(100)));
+        assert!(limiter.try_acquire_at(start + Duration::from_millis(600)));
+        assert!(!limiter.try_acquire_at(start + Duration::from_millis(600)));
+
+        // Tokens never exceed the burst size, however long the connection was idle
+        let later = start + Duration::from_secs(60);
+        assert!(limiter.try_acquire_at(later));
+        assert!(limiter.try_acquire_at(later));
+        assert!(!limiter.try_acquire_at(later));
+    }
+}
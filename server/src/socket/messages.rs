@@ -1,12 +1,27 @@
 use std::collections::HashMap;
 use indexmap::IndexSet;
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use ts_rs::TS;
+use strum::IntoStaticStr;
 use uuid::Uuid;
+use crate::game::draft::{Draft, DraftError};
+use crate::game::map_veto::{MapVeto, MapVetoError};
+use crate::game::puzzle::{PuzzleError, PuzzleState};
+use crate::game::sandbox::{SandboxError, SandboxState};
+use crate::game::series::{Series, SeriesSettings};
+use crate::game::squares::MapSquareType;
 use crate::game::state::{GameError, PlayerMove};
 use crate::game::team::PlayerTeam;
-use crate::socket::room_store::RoomUser;
+use crate::matrix::Matrix;
+use crate::position::INamedPosition;
+use crate::socket::chat::{ChatMessage, Emote};
+use crate::socket::close_code::SocketCloseCode;
+use crate::socket::room_settings::{RoomSettings, RoomSettingsSummary};
+use crate::socket::room_store::{MapPool, RoomUser};
+use crate::tournament::TournamentSummary;
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, JsonSchema, TS, IntoStaticStr)]
 #[serde(tag = "code", content = "detail")]
 pub enum SocketError {
     MessageParsingFailed,
@@ -17,50 +32,137 @@ pub enum SocketError {
     RoomStarted,
     RoomNotStarted,
     DecksNotChosen,
+    InvalidSeriesLength,
+    SeriesInProgress,
+    NotMapPicker,
+    RoomReserved,
+    InvalidRoomSettings,
+    InvalidMapPool,
+    InvalidChatMessage,
+    ChatRateLimited,
+    UserMuted,
+    UserNotFound,
+    UserCannotPlay,
+    CannotTargetSelf,
+    RateLimited,
+    DraftError(DraftError),
+    MapVetoError(MapVetoError),
+    SandboxError(SandboxError),
+    PuzzleNotFound(String),
+    PuzzleNotRunning,
+    PuzzleError(PuzzleError),
     GameError(GameError),
 }
 
-#[derive(Deserialize, Clone)]
+#[derive(Deserialize, Clone, JsonSchema, TS)]
 #[serde(tag = "action", content = "args")]
 pub enum SocketAction {
     SetMap(String),
     StartGame,
     RequestRedraw,
     ProposeMove(PlayerMove),
-    SetDeck { id: String, cards: IndexSet<String> },
+    SetDeck {
+        id: String,
+        #[ts(as = "Vec<String>")]
+        cards: IndexSet<String>,
+    },
     ReturnToRoom,
+    SetSeries(Option<SeriesSettings>),
+    PickMap(String),
+    SetRoomSettings(RoomSettings),
+    Chat(String),
+    Emote(Emote),
+    SetMuted { id: Uuid, muted: bool },
+    KickUser(Uuid),
+    TransferOwnership(Uuid),
+    ChooseOpponent(Option<Uuid>),
+    StartDraft,
+    DraftPick(String),
+    StartMapVeto,
+    BanMap(String),
+    SetMapPool(MapPool),
+    StartSandbox,
+    SandboxTurn(HashMap<PlayerTeam, PlayerMove>),
+    SandboxSetSquare { position: INamedPosition, square: MapSquareType },
+    SandboxUndo,
+    SandboxRedo,
+    // Starts the puzzle with the given ID, which may also be the daily puzzle's
+    StartPuzzle(String),
+    PuzzleMove(PlayerMove),
     Ping,
 }
 
 impl SocketAction {
     pub fn is_owner_action(&self) -> bool {
-        matches!(self, SocketAction::SetMap(_) | SocketAction::StartGame | SocketAction::ReturnToRoom)
+        matches!(self, SocketAction::SetMap(_) | SocketAction::StartGame | SocketAction::ReturnToRoom | SocketAction::SetSeries(_)
+            | SocketAction::SetRoomSettings(_) | SocketAction::SetMuted { .. } | SocketAction::KickUser(_)
+            | SocketAction::TransferOwnership(_) | SocketAction::ChooseOpponent(_) | SocketAction::StartDraft
+            | SocketAction::StartMapVeto | SocketAction::SetMapPool(_) | SocketAction::StartSandbox
+            | SocketAction::SandboxTurn(_) | SocketAction::SandboxSetSquare { .. } | SocketAction::SandboxUndo
+            | SocketAction::SandboxRedo | SocketAction::StartPuzzle(_) | SocketAction::PuzzleMove(_))
     }
 
     pub fn is_player_action(&self) -> bool {
-        matches!(self, SocketAction::SetDeck { id: _, cards: _ } | SocketAction::ProposeMove(_) | SocketAction::RequestRedraw)
+        matches!(self, SocketAction::SetDeck { id: _, cards: _ } | SocketAction::ProposeMove(_) | SocketAction::RequestRedraw | SocketAction::PickMap(_)
+            | SocketAction::DraftPick(_) | SocketAction::BanMap(_))
+    }
+
+    // Chat messages and emotes are only saved along with the next change to the room, or when shutting down, so that
+    // chatty rooms don't write a snapshot for every message.
+    pub fn changes_persisted_state(&self) -> bool {
+        !matches!(self, SocketAction::Ping | SocketAction::Chat(_) | SocketAction::Emote(_))
     }
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, JsonSchema, TS)]
 #[serde(tag = "event", content = "detail")]
 pub enum SocketEvent {
     #[serde(rename_all = "camelCase")]
     Welcome {
         id: Uuid,
         room_code: String,
+        // Lets the user reclaim their seat when rejoining the room after a server restart
+        reconnect_token: String,
+        #[ts(type = "Record<string, RoomUser>")]
         users: HashMap<Uuid, RoomUser>,
         owner: Uuid,
         opponent: Option<Uuid>,
         map: String,
+        map_pool: Box<MapPool>,
         started: bool,
+        series: Option<Series>,
+        settings: RoomSettingsSummary,
+        chat: Vec<ChatMessage>,
+        game: Option<Box<GameProgress>>,
+        draft: Option<Box<Draft>>,
+        map_veto: Option<Box<MapVeto>>,
+        sandbox: Option<Box<SandboxState>>,
+        puzzle: Option<Box<PuzzleState>>,
     },
     Error(SocketError),
     RoomEvent(RoomEvent),
     Pong,
+    // Closes the connection instead of being sent to the client
+    #[serde(skip)]
+    Close(SocketCloseCode),
+}
+
+// The state of a running game, sent to users joining a room mid-game, e.g. after reconnecting.
+#[derive(Clone, Debug, Serialize, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct GameProgress {
+    pub map_name: String,
+    #[schemars(with = "Vec<Vec<MapSquareType>>")]
+    #[ts(as = "Vec<Vec<MapSquareType>>")]
+    pub board: Matrix<MapSquareType>,
+    #[ts(type = "{ [key in PlayerTeam]: number }")]
+    pub score: HashMap<PlayerTeam, usize>,
+    pub remaining_turns: usize,
+    #[ts(as = "Option<Vec<String>>")]
+    pub hand: Option<IndexSet<String>>,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, JsonSchema, TS)]
 #[serde(tag = "event", content = "detail")]
 pub enum RoomEvent {
     UserJoin { id: Uuid, user: RoomUser },
@@ -69,14 +171,46 @@ pub enum RoomEvent {
     OwnerChange(Uuid),
     OpponentChange(Option<Uuid>),
     MapChange(String),
+    MapPoolChange(MapPool),
     #[serde(rename_all = "camelCase")]
-    StartGame { score: HashMap<PlayerTeam, usize>, map_name: String },
+    StartGame { #[ts(type = "{ [key in PlayerTeam]: number }")] score: HashMap<PlayerTeam, usize>, map_name: String },
     #[serde(rename_all = "camelCase")]
     MoveReceived { team: PlayerTeam, remaining_turns: usize },
-    MovesApplied { moves: HashMap<PlayerTeam, PlayerMove>, score: HashMap<PlayerTeam, usize> },
-    HandAssigned(IndexSet<String>),
+    MovesApplied {
+        #[ts(type = "{ [key in PlayerTeam]: PlayerMove }")]
+        moves: HashMap<PlayerTeam, PlayerMove>,
+        #[ts(type = "{ [key in PlayerTeam]: number }")]
+        score: HashMap<PlayerTeam, usize>,
+    },
+    HandAssigned(#[ts(as = "Vec<String>")] IndexSet<String>),
     #[serde(rename_all = "camelCase")]
     NextCardDrawn { new_card: String, replacing: String },
-    EndGame { score: HashMap<PlayerTeam, usize> },
+    EndGame { #[ts(type = "{ [key in PlayerTeam]: number }")] score: HashMap<PlayerTeam, usize> },
     ReturnToRoom,
+    RoomSettingsChange(RoomSettingsSummary),
+    ChatMessage(ChatMessage),
+    #[serde(rename_all = "camelCase")]
+    Emote { user_id: Uuid, emote: Emote },
+    SeriesChange(Option<Series>),
+    SeriesEnd { winner: Uuid, wins: HashMap<Uuid, usize> },
+    TournamentUpdate(TournamentSummary),
+    // A new pack was opened during a draft, see Draft
+    DraftPack { round: usize, #[ts(as = "Vec<String>")] cards: IndexSet<String>, picker: PlayerTeam },
+    // The picker is missing once the draft has completed and the game starts
+    #[serde(rename_all = "camelCase")]
+    DraftPick { team: PlayerTeam, card: String, next_picker: Option<PlayerTeam> },
+    // Sent when a map veto starts, and with None once it completes or is cancelled
+    MapVetoChange(Option<MapVeto>),
+    // The next team is missing once the map has been decided
+    #[serde(rename_all = "camelCase")]
+    MapBan { team: PlayerTeam, map: String, next_team: Option<PlayerTeam> },
+    // Sent whenever a sandbox starts or changes
+    SandboxUpdate(SandboxState),
+    // Sent whenever a puzzle starts or a turn of it is played
+    PuzzleUpdate(PuzzleState),
+    // Sent when the server shuts down; users are disconnected within the given time
+    #[serde(rename_all = "camelCase")]
+    ServerRestarting { #[ts(type = "number")] seconds_remaining: u64 },
+    // A server-wide message from the operators
+    Announcement(String),
 }
@@ -0,0 +1,113 @@
+pub use tableturfer_server::PROTOCOL_VERSION;
+
+pub fn is_supported(version: Option<u32>) -> bool {
+    version == Some(PROTOCOL_VERSION)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::TypeId;
+    use std::collections::{BTreeMap, HashSet};
+    use std::{env, fs};
+    use std::path::{Path, PathBuf};
+    use schemars::gen::SchemaSettings;
+    use ts_rs::TS;
+    use ts_rs::TypeVisitor;
+    use crate::socket::messages::{SocketAction, SocketEvent};
+    use super::*;
+
+    // Set this variable when running the tests to regenerate the files after changing the socket messages
+    const UPDATE_VARIABLE: &str = "TBLT_UPDATE_PROTOCOL";
+
+    fn output_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../web/types/socket/generated")
+    }
+
+    // Collects the declarations of every named type the protocol uses
+    #[derive(Default)]
+    struct DeclarationCollector {
+        visited: HashSet<TypeId>,
+        declarations: BTreeMap<String, String>,
+    }
+
+    impl TypeVisitor for DeclarationCollector {
+        fn visit<T: TS + 'static + ?Sized>(&mut self) {
+            if !self.visited.insert(TypeId::of::<T>()) {
+                return;
+            }
+
+            if T::output_path().is_some() {
+                self.declarations.insert(T::name(), format!("export {}", T::decl()));
+            }
+            T::visit_dependencies(self);
+            T::visit_generics(self);
+        }
+    }
+
+    fn typescript() -> String {
+        let mut collector = DeclarationCollector::default();
+        collector.visit::<SocketAction>();
+        collector.visit::<SocketEvent>();
+
+        let mut output = String::from("// Generated from the server's socket messages, do not edit.\n\n");
+        output += &format!("export const PROTOCOL_VERSION = {PROTOCOL_VERSION};\n");
+        for declaration in collector.declarations.values() {
+            output += &format!("\n{declaration}\n");
+        }
+        output
+    }
+
+    fn json_schema() -> String {
+        let mut generator = SchemaSettings::draft07().into_generator();
+        let action = generator.subschema_for::<SocketAction>();
+        let event = generator.subschema_for::<SocketEvent>();
+
+        let schema = serde_json::json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "title": "Tableturfer socket protocol",
+            "protocolVersion": PROTOCOL_VERSION,
+            "properties": {
+                "action": action,
+                "event": event,
+            },
+            "definitions": generator.definitions(),
+        });
+        serde_json::to_string_pretty(&schema).unwrap() + "\n"
+    }
+
+    #[test]
+    fn is_supported() {
+        assert!(super::is_supported(Some(PROTOCOL_VERSION)));
+        assert!(!super::is_supported(Some(PROTOCOL_VERSION + 1)));
+        assert!(!super::is_supported(None));
+    }
+
+    #[test]
+    fn typescript_declares_all_types() {
+        let typescript = typescript();
+
+        for name in ["SocketAction", "SocketEvent", "RoomEvent", "SocketError", "GameError", "PlayerMove", "CardRotation", "MapSquareType"] {
+            assert!(typescript.contains(&format!("export type {name} = ")), "{name} is not declared");
+        }
+    }
+
+    #[test]
+    fn generated_files_are_up_to_date() {
+        let files = [("Protocol.ts", typescript()), ("protocol.schema.json", json_schema())];
+
+        if env::var_os(UPDATE_VARIABLE).is_some() {
+            fs::create_dir_all(output_dir()).unwrap();
+            for (name, content) in files {
+                fs::write(output_dir().join(name), content).unwrap();
+            }
+            return;
+        }
+
+        for (name, content) in files {
+            let existing = fs::read_to_string(output_dir().join(name)).unwrap_or_default();
+            assert!(existing == content,
+                "{name} does not match the socket messages. Run the tests with {UPDATE_VARIABLE}=1 to regenerate it, \
+                and bump PROTOCOL_VERSION if the change breaks existing clients.");
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use std::borrow::Cow;
+use std::net::IpAddr;
+use axum::extract::ws::{CloseFrame, Message, WebSocket};
+use axum::http::HeaderMap;
+use futures::{SinkExt, StreamExt};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::tungstenite;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame as HostCloseFrame;
+use url::Url;
+use crate::api::admin::tokens_match;
+use crate::socket::protocol::PROTOCOL_VERSION;
+use crate::socket::room_store::JoinRequest;
+use crate::socket::wire_format::WireFormat;
+
+pub type HostSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+// Carries the cluster's shared secret on connections relayed between instances
+pub const RELAY_SECRET_HEADER: &str = "x-tableturfer-relay-secret";
+
+// Connections and requests relayed by another instance carry the cluster's secret
+pub fn is_relayed(headers: &HeaderMap, relay_secret: Option<&str>) -> bool {
+    let secret = headers.get(RELAY_SECRET_HEADER).and_then(|header| header.to_str().ok());
+    matches!((secret, relay_secret), (Some(secret), Some(relay_secret)) if tokens_match(secret, relay_secret))
+}
+
+// Connects to the instance hosting a room on behalf of a client. The connection is authenticated with the cluster's
+// secret so that the host never relays it any further, and trusts the client's address for its connection limits.
+pub async fn connect_to_host(host: &str, room_code: &str, request: &JoinRequest<'_>, format: WireFormat, client_ip: IpAddr, relay_secret: &str) -> Option<HostSocket> {
+    let version = PROTOCOL_VERSION.to_string();
+    let mut params = vec!(("room", room_code), ("username", request.username), ("version", &version), ("format", format.as_str()));
+    if let Some(password) = request.password {
+        params.push(("password", password));
+    }
+    if let Some(token) = request.token {
+        params.push(("token", token));
+    }
+
+    let url = Url::parse_with_params(&format!("{}/ws", host.trim_end_matches('/')), &params);
+
+    let url = match url {
+        Ok(url) => url,
+        Err(err) => {
+            log::error!("Invalid host address {host}: {err}");
+            return None;
+        }
+    };
+
+    let mut request = match url.into_client_request() {
+        Ok(request) => request,
+        Err(err) => {
+            log::error!("Failed to create request to {host}: {err}");
+            return None;
+        }
+    };
+    if let Ok(client_ip) = HeaderValue::from_str(&client_ip.to_string()) {
+        request.headers_mut().insert("x-forwarded-for", client_ip);
+    }
+    match HeaderValue::from_str(relay_secret) {
+        Ok(relay_secret) => {
+            request.headers_mut().insert(RELAY_SECRET_HEADER, relay_secret);
+        }
+        Err(err) => {
+            log::error!("The cluster secret can't be sent as a header: {err}");
+            return None;
+        }
+    }
+
+    match tokio_tungstenite::connect_async(request).await {
+        Ok((host_socket, _response)) => Some(host_socket),
+        Err(err) => {
+            log::warn!("Failed to connect to {host} to join room {room_code}: {err}");
+            None
+        }
+    }
+}
+
+// Relays messages between a client and the instance hosting their room until either side disconnects.
+pub async fn relay(socket: WebSocket, host_socket: HostSocket) {
+    let (mut client_sender, mut client_receiver) = socket.split();
+    let (mut host_sender, mut host_receiver) = host_socket.split();
+
+    let mut client_to_host = tokio::spawn(async move {
+        while let Some(Ok(message)) = client_receiver.next().await {
+            if host_sender.send(to_host_message(message)).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut host_to_client = tokio::spawn(async move {
+        while let Some(Ok(message)) = host_receiver.next().await {
+            let Some(message) = to_client_message(message) else {
+                continue;
+            };
+
+            if client_sender.send(message).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    tokio::select! {
+        _ = (&mut client_to_host) => host_to_client.abort(),
+        _ = (&mut host_to_client) => client_to_host.abort(),
+    }
+}
+
+fn to_host_message(message: Message) -> tungstenite::Message {
+    match message {
+        Message::Text(text) => tungstenite::Message::Text(text),
+        Message::Binary(data) => tungstenite::Message::Binary(data),
+        Message::Ping(data) => tungstenite::Message::Ping(data),
+        Message::Pong(data) => tungstenite::Message::Pong(data),
+        Message::Close(frame) => tungstenite::Message::Close(frame.map(|frame| HostCloseFrame {
+            code: frame.code.into(),
+            reason: frame.reason,
+        })),
+    }
+}
+
+fn to_client_message(message: tungstenite::Message) -> Option<Message> {
+    match message {
+        tungstenite::Message::Text(text) => Some(Message::Text(text)),
+        tungstenite::Message::Binary(data) => Some(Message::Binary(data)),
+        tungstenite::Message::Ping(data) => Some(Message::Ping(data)),
+        tungstenite::Message::Pong(data) => Some(Message::Pong(data)),
+        tungstenite::Message::Close(frame) => Some(Message::Close(frame.map(|frame| CloseFrame {
+            code: frame.code.into(),
+            reason: Cow::from(frame.reason.into_owned()),
+        }))),
+        tungstenite::Message::Frame(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+    use axum::extract::WebSocketUpgrade;
+    use axum::response::IntoResponse;
+    use axum::Router;
+    use axum::routing::get;
+    use tokio::net::TcpListener;
+    use super::*;
+
+    async fn start_echo_host() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    while let Some(Ok(message)) = socket.next().await {
+                        if let tungstenite::Message::Text(text) = message {
+                            socket.send(tungstenite::Message::Text(format!("echo: {text}"))).await.unwrap();
+                        }
+                    }
+                });
+            }
+        });
+
+        format!("ws://{address}")
+    }
+
+    async fn start_relay(host: String) -> SocketAddr {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let router = Router::new().route("/ws", get(move |ws: WebSocketUpgrade| async move {
+            ws.on_upgrade(move |socket| async move {
+                let host_socket = connect_to_host(&host, "ABCD", &JoinRequest::named("user"), WireFormat::Json, [127, 0, 0, 1].into(), "secret").await.unwrap();
+                relay(socket, host_socket).await;
+            }).into_response()
+        }));
+
+        tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(router.into_make_service()));
+        address
+    }
+
+    #[tokio::test]
+    async fn relays_messages_to_host() {
+        let relay_address = start_relay(start_echo_host().await).await;
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{relay_address}/ws")).await.unwrap();
+
+        client.send(tungstenite::Message::Text("hello".to_string())).await.unwrap();
+
+        assert_eq!(tungstenite::Message::Text("echo: hello".to_string()), client.next().await.unwrap().unwrap());
+    }
+
+    #[tokio::test]
+    async fn fails_to_connect_to_unreachable_host() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        drop(listener);
+
+        assert!(connect_to_host(&format!("ws://{address}"), "ABCD", &JoinRequest::named("user"), WireFormat::Json, [127, 0, 0, 1].into(), "secret").await.is_none());
+    }
+}
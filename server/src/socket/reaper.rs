@@ -0,0 +1,75 @@
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::Utc;
+use crate::AppState;
+use crate::app_config::RoomConfiguration;
+
+// Periodically closes rooms that have been idle for longer than the configured timeout. This also cleans up rooms
+// that were left behind by crashed connections or games that were never finished.
+pub async fn reap_idle_rooms(state: Arc<AppState>, config: RoomConfiguration) {
+    let idle_timeout = chrono::Duration::seconds(config.idle_timeout as i64);
+    let mut interval = tokio::time::interval(Duration::from_secs(config.reaper_interval));
+    let mut total_expired_rooms = 0;
+
+    loop {
+        interval.tick().await;
+
+        let mut room_store = state.write_room_store().await;
+        let expired_rooms = room_store.expire_idle_rooms(Utc::now() - idle_timeout);
+        if expired_rooms.is_empty() {
+            continue;
+        }
+
+        total_expired_rooms += expired_rooms.len();
+        let disconnected_users: usize = expired_rooms.iter().map(|room| room.users.len()).sum();
+        let abandoned_games = expired_rooms.iter().filter(|room| room.game_started()).count();
+        log::info!(
+            "Reclaimed {} idle room(s) ({} in game, {} user(s) disconnected); {} room(s) remain, {} reclaimed since startup",
+            expired_rooms.len(), abandoned_games, disconnected_users, room_store.room_count(), total_expired_rooms);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use tokio::sync::mpsc;
+    use uuid::Uuid;
+    use crate::socket::close_code::SocketCloseCode;
+    use crate::socket::messages::SocketEvent;
+    use crate::socket::room_settings::RoomSettings;
+    use crate::socket::room_store::{JoinRequest, SocketRoomStore};
+    use super::*;
+
+    #[tokio::test]
+    async fn expires_idle_rooms() {
+        let mut room_store = SocketRoomStore::default();
+        let (sender, mut receiver) = mpsc::channel(8);
+        let room_code = "ABCD".to_string();
+        room_store.create(room_code.clone(), Uuid::new_v4(), JoinRequest::named("user"), sender).unwrap();
+
+        assert!(room_store.expire_idle_rooms(Utc::now() - chrono::Duration::minutes(1)).is_empty());
+        assert!(room_store.get(&room_code).is_some());
+
+        let expired_rooms = room_store.expire_idle_rooms(Utc::now() + chrono::Duration::minutes(1));
+
+        assert_eq!(vec!(room_code.clone()), expired_rooms.iter().map(|room| room.code.clone()).collect::<Vec<_>>());
+        assert!(room_store.get(&room_code).is_none());
+        assert!(matches!(receiver.recv().await, Some(SocketEvent::Close(SocketCloseCode::RoomExpired))));
+    }
+
+    #[tokio::test]
+    async fn keeps_reserved_rooms() {
+        let mut room_store = SocketRoomStore::default();
+        let room_code = "ABCD".to_string();
+        room_store.create_reserved(
+            room_code.clone(),
+            vec!("a".to_string(), "b".to_string()),
+            HashMap::new(),
+            None,
+            RoomSettings::default(),
+            mpsc::unbounded_channel().0).unwrap();
+
+        assert!(room_store.expire_idle_rooms(Utc::now() + chrono::Duration::minutes(1)).is_empty());
+        assert!(room_store.get(&room_code).is_some());
+    }
+}
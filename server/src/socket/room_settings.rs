@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use ts_rs::TS;
+use crate::game::rules::RuleVariant;
+
+pub const MAX_PASSWORD_LENGTH: usize = 64;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, Eq, PartialEq, JsonSchema, TS)]
+pub enum RoomVisibility {
+    #[default]
+    Public,
+    Private,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct RoomSettings {
+    #[serde(default)]
+    pub visibility: RoomVisibility,
+    pub password: Option<String>,
+    pub max_spectators: Option<usize>,
+    // Can't be changed while a game is running
+    #[serde(default)]
+    pub rules: RuleVariant,
+}
+
+// What users in the room get to see of its settings; the password itself is never sent out.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct RoomSettingsSummary {
+    pub visibility: RoomVisibility,
+    pub has_password: bool,
+    pub max_spectators: Option<usize>,
+    pub rules: RuleVariant,
+}
+
+impl RoomSettings {
+    pub fn is_valid(&self) -> bool {
+        self.password.as_ref().is_none_or(|password| !password.is_empty() && password.len() <= MAX_PASSWORD_LENGTH)
+    }
+
+    pub fn accepts_password(&self, password: Option<&str>) -> bool {
+        self.password.as_deref().is_none_or(|expected| password == Some(expected))
+    }
+
+    pub fn is_full(&self, spectator_count: usize) -> bool {
+        self.max_spectators.is_some_and(|max_spectators| spectator_count >= max_spectators)
+    }
+
+    pub fn summary(&self) -> RoomSettingsSummary {
+        RoomSettingsSummary {
+            visibility: self.visibility,
+            has_password: self.password.is_some(),
+            max_spectators: self.max_spectators,
+            rules: self.rules,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc;
+    use uuid::Uuid;
+    use crate::socket::close_code::SocketCloseCode;
+    use crate::socket::messages::SocketError;
+    use crate::socket::room_store::{JoinRequest, SocketRoomStore};
+    use super::*;
+
+    fn with_password(password: &str) -> RoomSettings {
+        RoomSettings { password: Some(password.to_string()), ..RoomSettings::default() }
+    }
+
+    #[test]
+    fn is_valid() {
+        assert!(RoomSettings::default().is_valid());
+        assert!(with_password("hunter2").is_valid());
+        assert!(!with_password("").is_valid());
+        assert!(!with_password(&"a".repeat(MAX_PASSWORD_LENGTH + 1)).is_valid());
+    }
+
+    #[test]
+    fn accepts_password() {
+        assert!(RoomSettings::default().accepts_password(None));
+        assert!(RoomSettings::default().accepts_password(Some("anything")));
+        assert!(with_password("hunter2").accepts_password(Some("hunter2")));
+        assert!(!with_password("hunter2").accepts_password(Some("hunter3")));
+        assert!(!with_password("hunter2").accepts_password(None));
+    }
+
+    #[test]
+    fn is_full() {
+        let settings = RoomSettings { max_spectators: Some(2), ..RoomSettings::default() };
+
+        assert!(!settings.is_full(1));
+        assert!(settings.is_full(2));
+        assert!(!RoomSettings::default().is_full(100));
+    }
+
+    #[test]
+    fn summary_hides_password() {
+        let summary = with_password("hunter2").summary();
+
+        assert!(summary.has_password);
+        assert!(!serde_json::to_string(&summary).unwrap().contains("hunter2"));
+    }
+
+    async fn room_with_settings(settings: RoomSettings) -> (SocketRoomStore, String) {
+        let mut room_store = SocketRoomStore::default();
+        let room_code = "ABCD".to_string();
+        room_store.create(room_code.clone(), Uuid::new_v4(), JoinRequest::named("owner"), mpsc::channel(1).0).unwrap();
+        room_store.get_mut(&room_code).unwrap().set_settings(settings).unwrap();
+        (room_store, room_code)
+    }
+
+    #[tokio::test]
+    async fn join_requires_password() {
+        let (mut room_store, room_code) = room_with_settings(with_password("hunter2")).await;
+
+        let wrong_password = room_store.get_and_join_if_exists(&room_code, Uuid::new_v4(), JoinRequest { password: Some("hunter3"), ..JoinRequest::named("user") }, mpsc::channel(1).0);
+        assert!(matches!(wrong_password, Err(SocketCloseCode::WrongPassword)));
+        let missing_password = room_store.get_and_join_if_exists(&room_code, Uuid::new_v4(), JoinRequest::named("user"), mpsc::channel(1).0);
+        assert!(matches!(missing_password, Err(SocketCloseCode::WrongPassword)));
+        assert!(room_store.get_and_join_if_exists(&room_code, Uuid::new_v4(), JoinRequest { password: Some("hunter2"), ..JoinRequest::named("user") }, mpsc::channel(1).0).is_ok());
+    }
+
+    #[tokio::test]
+    async fn join_limits_spectators() {
+        let (mut room_store, room_code) = room_with_settings(RoomSettings { max_spectators: Some(1), ..RoomSettings::default() }).await;
+
+        // The opponent does not count as a spectator
+        assert!(room_store.get_and_join_if_exists(&room_code, Uuid::new_v4(), JoinRequest::named("opponent"), mpsc::channel(1).0).is_ok());
+        assert!(room_store.get_and_join_if_exists(&room_code, Uuid::new_v4(), JoinRequest::named("spectator"), mpsc::channel(1).0).is_ok());
+
+        let full = room_store.get_and_join_if_exists(&room_code, Uuid::new_v4(), JoinRequest::named("spectator-2"), mpsc::channel(1).0);
+        assert!(matches!(full, Err(SocketCloseCode::RoomFull)));
+    }
+
+    #[tokio::test]
+    async fn set_settings_rejects_invalid_password() {
+        let (mut room_store, room_code) = room_with_settings(RoomSettings::default()).await;
+
+        let result = room_store.get_mut(&room_code).unwrap().set_settings(with_password(""));
+        assert!(matches!(result, Err(SocketError::InvalidRoomSettings)));
+    }
+}
@@ -1,37 +1,62 @@
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
 use std::string::ToString;
 use std::sync::Arc;
 use chrono::{DateTime, Utc};
 use indexmap::IndexSet;
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, mpsc, RwLock};
 use rand::distributions::{Alphanumeric, DistString};
 use uuid::Uuid;
 use itertools::Itertools;
-use rand::prelude::IteratorRandom;
-use serde::Serialize;
+use rand::prelude::SliceRandom;
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use ts_rs::TS;
+use crate::analytics::GameRecord;
+use crate::api::admin::tokens_match;
 use crate::game::card::{CardProvider, CardSquareProviderImpl};
+use crate::game::draft::{Draft, DraftError, DraftPickResult};
+use crate::game::endgame::MAX_ENDGAME_TURNS;
 use crate::game::map::{DEFAULT_GAME_MAP, MapProvider, MapProviderImpl};
-use crate::game::move_validator::MoveValidatorImpl;
-use crate::game::state::{DECK_SIZE, GameError, GameState, PlayerMove};
+use crate::game::map_veto::{MapVeto, MapVetoError};
+use crate::game::puzzle::{Puzzle, PuzzleRun, PuzzleSnapshot};
+use crate::game::sandbox::{Sandbox, SandboxError};
+use crate::game::squares::MapSquareType;
+use crate::game::series::{Series, SeriesSettings};
+use crate::game::state::{DECK_SIZE, GameError, GameSnapshot, GameState, PlayerMove};
 use crate::game::team::PlayerTeam;
-use crate::socket::messages::{RoomEvent, SocketError, SocketEvent};
+use crate::position::INamedPosition;
+use crate::persistence::{RoomUpdate, RoomUpdateSender};
+use crate::persistence::registry::RoomRegistry;
+use crate::socket::messages::{GameProgress, RoomEvent, SocketError, SocketEvent};
+use crate::socket::chat::{Emote, RoomChat};
+use crate::socket::close_code::SocketCloseCode;
+use crate::socket::room_settings::{RoomSettings, RoomSettingsSummary, RoomVisibility};
 use crate::socket::SocketSender;
 
 const ROOM_CODE_SIZE: usize = 4;
+const ROOM_CODE_ATTEMPTS: usize = 10;
+// Keeps the sum of all weights in a map pool far from overflowing when picking a random map
+const MAX_MAP_WEIGHT: u32 = 1000;
 pub const RANDOM_MAP_NAME: &str = "random";
+const TOKEN_SIZE: usize = 32;
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema, TS)]
 pub struct RoomUserDeck {
     pub id: String,
+    #[ts(as = "Vec<String>")]
     pub cards: IndexSet<String>,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema, TS)]
 #[serde(rename_all = "camelCase")]
 pub struct RoomUser {
     pub username: String,
     pub joined_at: DateTime<Utc>,
     pub deck: Option<RoomUserDeck>,
+    #[serde(default)]
+    pub muted: bool,
 }
 
 impl RoomUser {
@@ -40,14 +65,145 @@ impl RoomUser {
             username: username.to_owned(),
             joined_at: Utc::now(),
             deck: None,
+            muted: false,
         }
     }
 }
 
+// The maps a random map is chosen from. Maps without a weight have a weight of 1.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq, JsonSchema, TS)]
+pub struct MapPool {
+    pub maps: Vec<String>,
+    #[serde(default)]
+    pub weights: HashMap<String, u32>,
+}
+
 pub type RoomSender = broadcast::Sender<RoomEvent>;
 
+// What the REST API shows of a room, see api.rs
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RoomSummary {
+    pub code: String,
+    pub owner: Option<String>,
+    pub opponent: Option<String>,
+    pub player_count: usize,
+    pub spectator_count: usize,
+    pub map: String,
+    pub started: bool,
+    pub series: Option<Series>,
+    pub settings: RoomSettingsSummary,
+}
+
+// What the admin API shows of a room, including everything needed to investigate stuck rooms
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminRoomSummary {
+    #[serde(flatten)]
+    pub summary: RoomSummary,
+    pub users: HashMap<Uuid, RoomUser>,
+    pub reserved_players: Option<Vec<String>>,
+    pub game: Option<GameProgress>,
+    pub last_activity: DateTime<Utc>,
+}
+
+// Sent when a player wins a room's series, or a game when no series is being played.
+#[derive(Clone, Debug)]
+pub struct RoomResult {
+    pub room_code: String,
+    pub winner: String,
+    pub loser: String,
+}
+
+pub type RoomResultSender = mpsc::UnboundedSender<RoomResult>;
+
+#[derive(Clone, Deserialize, Serialize)]
+struct ActiveGame {
+    map_name: String,
+    players: HashMap<PlayerTeam, (Uuid, String)>,
+}
+
+// A player that was seated in a room before it was restored. Their seat is given back to them once a user
+// with the same username and reconnect token joins the room.
+#[derive(Clone, Deserialize, Serialize)]
+struct PendingPlayer {
+    id: Uuid,
+    team: PlayerTeam,
+    user: RoomUser,
+    // Players saved without a token can't reclaim their seat
+    #[serde(default)]
+    reconnect_token: Option<String>,
+}
+
+// What a connection presents when joining a room
+#[derive(Clone, Copy, Debug)]
+pub struct JoinRequest<'a> {
+    pub username: &'a str,
+    pub client_ip: IpAddr,
+    pub password: Option<&'a str>,
+    // The reconnect token sent with the Welcome of an earlier connection, see Room::check_join
+    pub token: Option<&'a str>,
+}
+
+#[cfg(test)]
+impl<'a> JoinRequest<'a> {
+    pub fn named(username: &'a str) -> Self {
+        Self { username, client_ip: IpAddr::from([127, 0, 0, 1]), password: None, token: None }
+    }
+}
+
+// Closes a connection once its channel has room, as the close must not be dropped like other events when it is full
+fn send_close(channel: &SocketSender, close_code: SocketCloseCode) {
+    let channel = channel.clone();
+    tokio::spawn(async move {
+        channel.send(SocketEvent::Close(close_code)).await.ok();
+    });
+}
+
+// Random tokens that users prove who they are with, long enough not to be guessed
+pub fn generate_token() -> String {
+    Alphanumeric.sample_string(&mut rand::thread_rng(), TOKEN_SIZE)
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct RoomSnapshot {
+    pub code: String,
+    players: Vec<PendingPlayer>,
+    map: String,
+    map_pool: Vec<String>,
+    #[serde(default)]
+    map_weights: HashMap<String, u32>,
+    series: Option<Series>,
+    reserved_players: Option<Vec<String>>,
+    #[serde(default)]
+    join_tokens: HashMap<String, String>,
+    #[serde(default)]
+    settings: RoomSettings,
+    #[serde(default)]
+    chat: RoomChat,
+    #[serde(default, alias = "kicked_usernames", alias = "banned_users")]
+    banned_usernames: HashSet<String>,
+    #[serde(default)]
+    banned_ips: HashSet<IpAddr>,
+    game: Option<GameSnapshot>,
+    active_game: Option<ActiveGame>,
+    #[serde(default)]
+    draft: Option<Draft>,
+    #[serde(default)]
+    map_veto: Option<MapVeto>,
+    #[serde(default)]
+    last_loser: Option<Uuid>,
+    #[serde(default)]
+    sandbox: Option<GameSnapshot>,
+    #[serde(default)]
+    puzzle: Option<PuzzleSnapshot>,
+    #[serde(default)]
+    endgame: Option<GameSnapshot>,
+}
+
 #[derive(Clone)]
 pub struct Room {
+    pub code: String,
     pub sender: RoomSender,
     pub owner_id: Uuid,
     pub opponent_id: Option<Uuid>,
@@ -55,75 +211,463 @@ pub struct Room {
     pub user_channels: HashMap<Uuid, SocketSender>,
     pub map: String,
     pub game_state: Option<GameState>,
+    // Set while the players are picking their decks, before the game starts
+    pub draft: Option<Draft>,
+    pub map_veto: Option<MapVeto>,
+    // Set while the owner plays both teams in a sandbox instead of a game
+    pub sandbox: Option<Sandbox>,
+    // Set while the owner plays a puzzle against its scripted opponent
+    pub puzzle: Option<PuzzleRun>,
+    // The latest game as it was once only the turns the endgame solver handles were left, kept for post-game analysis
+    endgame: Option<GameSnapshot>,
     pub card_provider: Arc<dyn CardProvider + Send + Sync>,
     pub map_pool: Vec<String>,
+    pub map_weights: HashMap<String, u32>,
     pub map_provider: Arc<dyn MapProvider + Send + Sync>,
+    pub series: Option<Series>,
+    // When set, only users with these usernames may play in the room; everyone else joins as a spectator.
+    pub reserved_players: Option<Vec<String>>,
+    // The token each reserved player has to present, as anyone could join under their username otherwise
+    join_tokens: HashMap<String, String>,
+    pub settings: RoomSettings,
+    pub chat: RoomChat,
+    // Kicked users may not return for as long as the room exists, neither under their username nor from their address
+    banned_usernames: HashSet<String>,
+    banned_ips: HashSet<IpAddr>,
+    // The address each user connected from, so that kicking them can ban it
+    user_ips: HashMap<Uuid, IpAddr>,
+    result_sender: Option<RoomResultSender>,
+    active_game: Option<ActiveGame>,
+    // The loser of the most recent decided game, who gets to pick the map in a map veto
+    last_loser: Option<Uuid>,
+    pending_players: HashMap<String, PendingPlayer>,
+    // Sent to every user with Welcome; seated players need theirs to reclaim their seat once the room was restored
+    reconnect_tokens: HashMap<Uuid, String>,
+    last_activity: DateTime<Utc>,
 }
 
 impl Room {
-    fn new(owner_id: Uuid, owner_channel: SocketSender, owner_username: &str) -> Self {
+    fn new(code: String, owner_id: Uuid, owner_channel: SocketSender, owner: &JoinRequest) -> Self {
+        let mut room = Self::empty(code);
+        room.owner_id = owner_id;
+        room.users.insert(owner_id, RoomUser::new(owner.username));
+        room.user_channels.insert(owner_id, owner_channel);
+        room.user_ips.insert(owner_id, owner.client_ip);
+        room.reconnect_tokens.insert(owner_id, generate_token());
+        room
+    }
+
+    fn reserved(
+        code: String,
+        players: Vec<String>,
+        join_tokens: HashMap<String, String>,
+        series: Option<Series>,
+        settings: RoomSettings,
+        result_sender: RoomResultSender
+    ) -> Self
+    {
+        let mut room = Self::empty(code);
+        room.reserved_players = Some(players);
+        room.join_tokens = join_tokens;
+        room.series = series;
+        room.settings = settings;
+        room.result_sender = Some(result_sender);
+        room
+    }
+
+    fn empty(code: String) -> Self {
         let map_provider = Arc::new(MapProviderImpl::new());
 
         Room {
+            code,
             sender: broadcast::channel(100).0,
-            owner_id,
+            owner_id: Uuid::nil(),
             opponent_id: None,
-            users: HashMap::from([(owner_id, RoomUser::new(owner_username))]),
-            user_channels: HashMap::from([(owner_id, owner_channel)]),
+            users: HashMap::new(),
+            user_channels: HashMap::new(),
             map: DEFAULT_GAME_MAP.to_string(),
             game_state: None,
+            draft: None,
+            map_veto: None,
+            sandbox: None,
+            puzzle: None,
+            endgame: None,
             card_provider: Arc::new(CardSquareProviderImpl::new()),
             map_pool: Self::get_default_map_pool(map_provider.clone()),
+            map_weights: HashMap::new(),
             map_provider,
+            series: None,
+            reserved_players: None,
+            join_tokens: HashMap::new(),
+            settings: RoomSettings::default(),
+            chat: RoomChat::default(),
+            banned_usernames: HashSet::new(),
+            banned_ips: HashSet::new(),
+            user_ips: HashMap::new(),
+            result_sender: None,
+            active_game: None,
+            last_loser: None,
+            pending_players: HashMap::new(),
+            reconnect_tokens: HashMap::new(),
+            last_activity: Utc::now(),
+        }
+    }
+
+    fn from_snapshot(snapshot: RoomSnapshot) -> Self {
+        let mut room = Self::empty(snapshot.code);
+        room.map = snapshot.map;
+        room.map_pool = snapshot.map_pool;
+        room.map_weights = snapshot.map_weights;
+        room.series = snapshot.series;
+        room.reserved_players = snapshot.reserved_players;
+        room.join_tokens = snapshot.join_tokens;
+        room.settings = snapshot.settings;
+        room.chat = snapshot.chat;
+        room.banned_usernames = snapshot.banned_usernames;
+        room.banned_ips = snapshot.banned_ips;
+        room.active_game = snapshot.active_game;
+        room.draft = snapshot.draft;
+        room.map_veto = snapshot.map_veto;
+        room.last_loser = snapshot.last_loser;
+        room.endgame = snapshot.endgame;
+        room.sandbox = snapshot.sandbox.map(|sandbox| Sandbox::from_snapshot(
+            sandbox,
+            room.card_provider.clone(),
+            room.settings.rules.validator(room.card_provider.clone())));
+        room.puzzle = snapshot.puzzle.and_then(|puzzle| puzzle.restore(room.card_provider.clone())
+            .map_err(|err| log::warn!("Failed to restore the puzzle of room {}: {err:?}", room.code))
+            .ok());
+        room.game_state = snapshot.game.map(|game| GameState::from_snapshot(
+            game,
+            room.card_provider.clone(),
+            room.settings.rules.validator(room.card_provider.clone())));
+
+        for player in snapshot.players {
+            match player.team {
+                PlayerTeam::Alpha => room.owner_id = player.id,
+                PlayerTeam::Bravo => room.opponent_id = Some(player.id),
+            }
+            room.pending_players.insert(player.user.username.clone(), player);
+        }
+
+        room
+    }
+
+    pub fn snapshot(&self) -> RoomSnapshot {
+        let seated_players = [(PlayerTeam::Alpha, Some(self.owner_id)), (PlayerTeam::Bravo, self.opponent_id)].into_iter()
+            .filter_map(|(team, id)| id.and_then(|id| self.users.get(&id).map(|user| PendingPlayer {
+                id,
+                team,
+                user: user.clone(),
+                reconnect_token: self.reconnect_tokens.get(&id).cloned(),
+            })));
+
+        RoomSnapshot {
+            code: self.code.clone(),
+            players: seated_players.chain(self.pending_players.values().cloned()).collect(),
+            map: self.map.clone(),
+            map_pool: self.map_pool.clone(),
+            map_weights: self.map_weights.clone(),
+            series: self.series.clone(),
+            reserved_players: self.reserved_players.clone(),
+            join_tokens: self.join_tokens.clone(),
+            settings: self.settings.clone(),
+            chat: self.chat.clone(),
+            banned_usernames: self.banned_usernames.clone(),
+            banned_ips: self.banned_ips.clone(),
+            game: self.game_state.as_ref().map(|game| game.snapshot()),
+            active_game: self.active_game.clone(),
+            draft: self.draft.clone(),
+            map_veto: self.map_veto.clone(),
+            last_loser: self.last_loser,
+            sandbox: self.sandbox.as_ref().map(|sandbox| sandbox.snapshot()),
+            puzzle: self.puzzle.as_ref().map(|puzzle| puzzle.snapshot()),
+            endgame: self.endgame.clone(),
         }
     }
 
+    // Rooms that are reserved or that still have players reconnecting after a restart are kept even without users.
+    fn can_be_removed(&self) -> bool {
+        self.users.is_empty() && self.reserved_players.is_none() && self.pending_players.is_empty()
+    }
+
     fn get_default_map_pool(map_provider: Arc<dyn MapProvider + Send + Sync>) -> Vec<String> {
-        map_provider.get_names().into_iter().filter(|map| map != "SmallSquare").collect()
+        map_provider.get_names().into_iter().filter(|map| map != "SmallSquare").sorted().collect()
     }
 
-    fn add_user(&mut self, id: Uuid, username: &str, channel: SocketSender) {
-        let user = RoomUser::new(username);
+    fn add_user(&mut self, id: Uuid, request: &JoinRequest, channel: SocketSender) {
+        self.touch();
+        let username = request.username;
+        let pending_player = self.pending_players.remove(username);
+        let mut user = match &pending_player {
+            Some(pending_player) => RoomUser { joined_at: Utc::now(), ..pending_player.user.clone() },
+            None => RoomUser::new(username),
+        };
+        user.muted = self.chat.is_muted(username);
 
         self.users.insert(id, user.clone());
         self.user_channels.insert(id, channel);
-        self.sender.send(RoomEvent::UserJoin { id, user }).ok();
+        self.user_ips.insert(id, request.client_ip);
+        self.reconnect_tokens.insert(id, generate_token());
+        self.sender.send(RoomEvent::UserJoin { id, user: user.clone() }).ok();
+
+        if let Some(pending_player) = pending_player {
+            self.reclaim_seat(id, pending_player);
+            return;
+        }
 
-        if self.opponent_id.is_none() {
+        if !self.can_play(&user) {
+            return;
+        }
+
+        if self.owner_seat_free() {
+            self.set_owner(id);
+        } else if self.opponent_id.is_none() {
             self.set_opponent(Some(id));
         }
     }
 
+    // Seats a returning player directly, as their game might still be in progress.
+    fn reclaim_seat(&mut self, id: Uuid, pending_player: PendingPlayer) {
+        log::debug!("User {} reclaims their seat in room {} as {id}", pending_player.user.username, self.code);
+        match pending_player.team {
+            PlayerTeam::Alpha => {
+                self.owner_id = id;
+                self.sender.send(RoomEvent::OwnerChange(id)).ok();
+            }
+            PlayerTeam::Bravo => {
+                self.opponent_id = Some(id);
+                self.sender.send(RoomEvent::OpponentChange(Some(id))).ok();
+            }
+        }
+
+        if let Some(series) = self.series.as_mut() {
+            series.replace_player(pending_player.id, id);
+        }
+
+        if self.last_loser == Some(pending_player.id) {
+            self.last_loser = Some(id);
+        }
+
+        if let Some((player_id, _username)) = self.active_game.as_mut().and_then(|game| game.players.get_mut(&pending_player.team)) {
+            *player_id = id;
+        }
+    }
+
+    fn can_play(&self, user: &RoomUser) -> bool {
+        self.can_play_as(&user.username)
+    }
+
+    fn can_play_as(&self, username: &str) -> bool {
+        self.reserved_players.as_ref().is_none_or(|players| players.iter().any(|player| player == username))
+    }
+
+    fn owner_seat_free(&self) -> bool {
+        !self.users.contains_key(&self.owner_id)
+            && !self.pending_players.values().any(|player| player.team == PlayerTeam::Alpha)
+    }
+
+    fn spectator_count(&self) -> usize {
+        self.users.keys().filter(|id| self.team(**id).is_none()).count()
+    }
+
+    pub fn reconnect_token(&self, id: Uuid) -> Option<&String> {
+        self.reconnect_tokens.get(&id)
+    }
+
+    // Players returning to a restored room skip the capacity check, as they were in the room before. Usernames can
+    // be chosen freely, so they have to prove who they are with their reconnect token, or with their join token if
+    // the room was reserved for them.
+    fn check_join(&self, request: &JoinRequest) -> Result<(), SocketCloseCode> {
+        let username = request.username;
+        if self.banned_usernames.contains(username) || self.banned_ips.contains(&request.client_ip) {
+            return Err(SocketCloseCode::Banned);
+        }
+
+        if !self.settings.accepts_password(request.password) {
+            return Err(SocketCloseCode::WrongPassword);
+        }
+
+        let presents = |expected: Option<&String>| matches!((request.token, expected),
+            (Some(token), Some(expected)) if tokens_match(token, expected));
+        let join_token = self.join_tokens.get(username);
+        if let Some(pending_player) = self.pending_players.get(username) {
+            let token_matches = presents(pending_player.reconnect_token.as_ref()) || presents(join_token);
+            return if token_matches { Ok(()) } else { Err(SocketCloseCode::InvalidToken) };
+        }
+        if join_token.is_some() && !presents(join_token) {
+            return Err(SocketCloseCode::InvalidToken);
+        }
+
+        let joins_as_player = self.can_play_as(username) && (self.owner_seat_free() || self.opponent_id.is_none());
+        if !joins_as_player && self.settings.is_full(self.spectator_count()) {
+            return Err(SocketCloseCode::RoomFull);
+        }
+
+        Ok(())
+    }
+
+    pub fn send_chat_message(&mut self, id: Uuid, message: String) -> Result<(), SocketError> {
+        let username = &self.users.get(&id).ok_or(SocketError::UserNotFound)?.username;
+        let message = self.chat.add_message(id, username, message, Utc::now())?;
+        self.sender.send(RoomEvent::ChatMessage(message)).ok();
+        Ok(())
+    }
+
+    pub fn send_emote(&mut self, id: Uuid, emote: Emote) -> Result<(), SocketError> {
+        let username = &self.users.get(&id).ok_or(SocketError::UserNotFound)?.username;
+        self.chat.add_emote(id, username, Utc::now())?;
+        self.sender.send(RoomEvent::Emote { user_id: id, emote }).ok();
+        Ok(())
+    }
+
+    pub fn set_muted(&mut self, id: Uuid, muted: bool) -> Result<(), SocketError> {
+        let user = self.users.get_mut(&id).ok_or(SocketError::UserNotFound)?;
+        self.chat.set_muted(&user.username, muted);
+        user.muted = muted;
+        self.sender.send(RoomEvent::UserUpdate { id, user: user.clone() }).ok();
+        Ok(())
+    }
+
+    pub fn set_settings(&mut self, settings: RoomSettings) -> Result<(), SocketError> {
+        if !settings.is_valid() {
+            return Err(SocketError::InvalidRoomSettings);
+        }
+
+        // The running game keeps the rules it was started with
+        if self.game_started() && settings.rules != self.settings.rules {
+            return Err(SocketError::RoomStarted);
+        }
+
+        self.settings = settings;
+        self.sender.send(RoomEvent::RoomSettingsChange(self.settings.summary())).ok();
+        Ok(())
+    }
+
     fn remove_user(&mut self, id: Uuid) {
+        self.touch();
         self.user_channels.remove(&id);
+        self.user_ips.remove(&id);
+        self.reconnect_tokens.remove(&id);
+        self.chat.remove_user(id);
 
         if self.users.remove(&id).is_some() {
             self.sender.send(RoomEvent::UserLeave(id)).ok();
 
+            // Only the owner can use the sandbox or play a puzzle, so these end with them leaving and the room is
+            // handed over as usual
+            if self.owner_id == id && (self.sandbox.is_some() || self.puzzle.is_some()) {
+                self.return_to_room();
+            }
+
             if !self.users.is_empty() && !self.game_started() {
                 if self.owner_id == id {
-                    // todo: find a new opponent if the first user was previously the opponent
                     if let Some((first_user_id, _first_user)) = self.users.clone().into_iter()
+                        .filter(|(_id, user)| self.can_play(user))
                         .sorted_by(|(_id_a, user_a), (_id_b, user_b)| Ord::cmp(&user_a.joined_at, &user_b.joined_at))
                         .next() {
                         self.set_owner(first_user_id);
                     }
-                } else if self.is_opponent(id) {
-                    let opponent_candidate = self.users.clone().into_iter()
-                        .filter(|(id, _user)| id != &self.owner_id)
-                        .sorted_by(|(_id_a, user_a), (_id_b, user_b)| Ord::cmp(&user_a.joined_at, &user_b.joined_at))
-                        .next();
 
-                    self.set_opponent(opponent_candidate.map_or(None, |(id, _user)| Some(id)));
+                    // If the opponent took over the room, their seat needs to be filled again
+                    if self.opponent_id.is_none() {
+                        self.set_opponent(self.next_opponent_candidate());
+                    }
+                } else if self.is_opponent(id) {
+                    self.set_opponent(self.next_opponent_candidate());
                 }
             }
         }
     }
 
+    fn next_opponent_candidate(&self) -> Option<Uuid> {
+        self.users.iter()
+            .filter(|(id, user)| **id != self.owner_id && self.can_play(user))
+            .sorted_by(|(_id_a, user_a), (_id_b, user_b)| Ord::cmp(&user_a.joined_at, &user_b.joined_at))
+            .map(|(id, _user)| *id)
+            .next()
+    }
+
+    // Checks whether the owner may move the given user into one of the player seats
+    fn check_seat_change(&self, id: Uuid) -> Result<(), SocketError> {
+        let user = self.users.get(&id).ok_or(SocketError::UserNotFound)?;
+
+        if self.game_started() {
+            Err(SocketError::RoomStarted)
+        } else if !self.can_play(user) {
+            Err(SocketError::UserCannotPlay)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn transfer_ownership(&mut self, id: Uuid) -> Result<(), SocketError> {
+        if id == self.owner_id {
+            return Err(SocketError::CannotTargetSelf);
+        }
+
+        self.check_seat_change(id)?;
+
+        // When the opponent takes over the room, the previous owner takes their place
+        let previous_owner_id = self.owner_id;
+        let swap_seats = self.is_opponent(id);
+        self.set_owner(id);
+        if swap_seats {
+            self.set_opponent(Some(previous_owner_id));
+        }
+
+        Ok(())
+    }
+
+    pub fn choose_opponent(&mut self, id: Option<Uuid>) -> Result<(), SocketError> {
+        match id {
+            Some(id) if id == self.owner_id => return Err(SocketError::CannotTargetSelf),
+            Some(id) => self.check_seat_change(id)?,
+            None if self.game_started() => return Err(SocketError::RoomStarted),
+            None => {}
+        }
+
+        self.set_opponent(id);
+        Ok(())
+    }
+
+    pub fn kick_user(&mut self, id: Uuid) -> Result<(), SocketError> {
+        if id == self.owner_id {
+            return Err(SocketError::CannotTargetSelf);
+        }
+
+        let user = self.users.get(&id).ok_or(SocketError::UserNotFound)?;
+        if self.is_opponent(id) && self.game_started() {
+            return Err(SocketError::RoomStarted);
+        }
+
+        log::debug!("Kicking user {} ({id}) from room {}", user.username, self.code);
+        self.banned_usernames.insert(user.username.clone());
+        if let Some(client_ip) = self.user_ips.get(&id) {
+            self.banned_ips.insert(*client_ip);
+        }
+        if let Some(channel) = self.user_channels.get(&id) {
+            send_close(channel, SocketCloseCode::Kicked);
+        }
+
+        self.remove_user(id);
+        Ok(())
+    }
+
     pub fn is_opponent(&self, id: Uuid) -> bool {
         self.opponent_id.is_some() && self.opponent_id.unwrap() == id
     }
 
+    pub fn team(&self, id: Uuid) -> Option<PlayerTeam> {
+        if self.owner_id == id {
+            Some(PlayerTeam::Alpha)
+        } else if self.is_opponent(id) {
+            Some(PlayerTeam::Bravo)
+        } else {
+            None
+        }
+    }
+
     fn set_owner(&mut self, id: Uuid) {
         if !self.game_started() {
             if self.is_opponent(id) {
@@ -132,6 +676,8 @@ impl Room {
 
             self.owner_id = id;
             self.sender.send(RoomEvent::OwnerChange(id)).ok();
+            self.reset_series_progress();
+            self.cancel_map_veto();
         }
     }
 
@@ -139,6 +685,8 @@ impl Room {
         if !self.game_started() {
             self.opponent_id = id;
             self.sender.send(RoomEvent::OpponentChange(id)).ok();
+            self.reset_series_progress();
+            self.cancel_map_veto();
         }
     }
 
@@ -147,6 +695,16 @@ impl Room {
             return Err(SocketError::GameError(GameError::MapNotFound));
         }
 
+        if self.series.as_ref().is_some_and(|series| series.in_progress()) {
+            return Err(SocketError::SeriesInProgress);
+        } else if self.map_veto.is_some() {
+            return Err(SocketError::MapVetoError(MapVetoError::VetoInProgress));
+        }
+
+        self.update_map(map)
+    }
+
+    fn update_map(&mut self, map: String) -> Result<(), SocketError> {
         if !self.game_started() {
             self.map = map.clone();
             self.sender.send(RoomEvent::MapChange(map)).ok();
@@ -156,44 +714,330 @@ impl Room {
         }
     }
 
+    pub fn pick_map(&mut self, id: Uuid, map: String) -> Result<(), SocketError> {
+        if !self.map_provider.exists(&map) {
+            return Err(SocketError::GameError(GameError::MapNotFound));
+        }
+
+        if let Some(map_veto) = &self.map_veto {
+            let team = self.team(id).ok_or(SocketError::NotMapPicker)?;
+            let map = map_veto.pick(team, &map).map_err(SocketError::MapVetoError)?;
+            return self.finish_map_veto(map);
+        }
+
+        match self.series.as_ref().and_then(|series| series.map_picker()) {
+            Some(picker) if picker == id => self.update_map(map),
+            _ => Err(SocketError::NotMapPicker),
+        }
+    }
+
+    pub fn start_map_veto(&mut self) -> Result<(), SocketError> {
+        if self.opponent_id.filter(|id| self.users.contains_key(id)).is_none() {
+            return Err(SocketError::MissingOpponent);
+        } else if self.game_started() {
+            return Err(SocketError::RoomStarted);
+        }
+
+        let loser = self.last_loser.and_then(|id| self.team(id));
+        let map_veto = MapVeto::new(&self.map_pool, loser).map_err(SocketError::MapVetoError)?;
+        self.sender.send(RoomEvent::MapVetoChange(Some(map_veto.clone()))).ok();
+        self.map_veto = Some(map_veto);
+        Ok(())
+    }
+
+    pub fn ban_map(&mut self, team: PlayerTeam, map: String) -> Result<(), SocketError> {
+        let Some(map_veto) = self.map_veto.as_mut() else {
+            return Err(SocketError::MapVetoError(MapVetoError::VetoNotRunning));
+        };
+
+        let chosen_map = map_veto.ban(team.clone(), &map).map_err(SocketError::MapVetoError)?;
+        let next_team = chosen_map.is_none().then(|| map_veto.next_team());
+        self.sender.send(RoomEvent::MapBan { team, map, next_team }).ok();
+
+        match chosen_map {
+            Some(map) => self.finish_map_veto(map),
+            None => Ok(()),
+        }
+    }
+
+    fn finish_map_veto(&mut self, map: String) -> Result<(), SocketError> {
+        self.cancel_map_veto();
+        self.update_map(map)
+    }
+
+    fn cancel_map_veto(&mut self) {
+        if self.map_veto.take().is_some() {
+            self.sender.send(RoomEvent::MapVetoChange(None)).ok();
+        }
+    }
+
+    pub fn set_series(&mut self, settings: Option<SeriesSettings>) -> Result<(), SocketError> {
+        if self.game_started() {
+            return Err(SocketError::RoomStarted);
+        } else if self.reserved_players.is_some() {
+            return Err(SocketError::RoomReserved);
+        }
+
+        self.series = match settings {
+            Some(settings) => Some(Series::new(settings).ok_or(SocketError::InvalidSeriesLength)?),
+            None => None,
+        };
+        self.sender.send(RoomEvent::SeriesChange(self.series.clone())).ok();
+        Ok(())
+    }
+
+    fn reset_series_progress(&mut self) {
+        if let Some(series) = self.series.as_mut() {
+            if !series.games.is_empty() {
+                series.reset();
+                self.sender.send(RoomEvent::SeriesChange(self.series.clone())).ok();
+            }
+        }
+    }
+
+    fn record_result(&mut self) {
+        let (Some(game), Some(active_game)) = (self.game_state.as_ref(), self.active_game.as_ref()) else {
+            return;
+        };
+
+        let score = game.score();
+        let (winner, loser) = match score[&PlayerTeam::Alpha].cmp(&score[&PlayerTeam::Bravo]) {
+            Ordering::Greater => (Some(&active_game.players[&PlayerTeam::Alpha]), Some(&active_game.players[&PlayerTeam::Bravo])),
+            Ordering::Less => (Some(&active_game.players[&PlayerTeam::Bravo]), Some(&active_game.players[&PlayerTeam::Alpha])),
+            Ordering::Equal => (None, None),
+        };
+        if let Some((loser_id, _)) = loser {
+            self.last_loser = Some(*loser_id);
+        }
+
+        let room_result = match self.series.as_mut() {
+            Some(series) => {
+                series.record_game(active_game.map_name.clone(), winner.map(|(id, _)| *id), loser.map(|(id, _)| *id));
+                self.sender.send(RoomEvent::SeriesChange(Some(series.clone()))).ok();
+
+                series.winner().and_then(|winner_id| {
+                    self.sender.send(RoomEvent::SeriesEnd { winner: winner_id, wins: series.wins.clone() }).ok();
+                    active_game.players.values()
+                        .sorted_by_key(|(id, _username)| *id != winner_id)
+                        .map(|(_id, username)| username.clone())
+                        .collect_tuple()
+                })
+            }
+            None => winner.zip(loser).map(|((_, winner), (_, loser))| (winner.clone(), loser.clone())),
+        };
+
+        if let (Some(result_sender), Some((winner, loser))) = (&self.result_sender, room_result) {
+            result_sender.send(RoomResult { room_code: self.code.clone(), winner, loser }).ok();
+        }
+    }
+
     pub async fn start_game(&mut self) -> Result<(), SocketError> {
-        if self.opponent_id.is_none() {
-            Err(SocketError::MissingOpponent)
-        } else if [self.owner_id, self.opponent_id.unwrap()].iter().any(|user| self.users[user].deck.is_none()) {
+        // The owner may be gone too when the last pick of a draft starts the game
+        let Some(opponent_id) = self.opponent_id.filter(|id| self.users.contains_key(id) && self.users.contains_key(&self.owner_id)) else {
+            return Err(SocketError::MissingOpponent);
+        };
+
+        // A completed draft is kept until its game has started, so the game can still be started with the drafted
+        // decks if that failed at the last pick. Drafted decks don't replace the players' own decks.
+        let drafted_decks = self.draft.as_ref().filter(|draft| draft.completed()).map(|draft| draft.picks.clone());
+
+        if (self.draft.is_some() && drafted_decks.is_none()) || self.sandbox.is_some() || self.puzzle.is_some() {
+            Err(SocketError::RoomStarted)
+        } else if self.map_veto.is_some() {
+            Err(SocketError::MapVetoError(MapVetoError::VetoInProgress))
+        } else if drafted_decks.is_none() && [self.owner_id, opponent_id].iter().any(|user| self.users[user].deck.is_none()) {
             Err(SocketError::DecksNotChosen)
         } else {
             let players = self.get_players();
+            let decks = drafted_decks.unwrap_or_else(|| players.iter()
+                .map(|(team, player)| (team.clone(), player.deck.as_ref().unwrap().cards.clone()))
+                .collect());
 
             let map = self.map_provider.get(&self.get_map_name()).unwrap();
+            let active_game = ActiveGame {
+                map_name: map.name.clone(),
+                players: HashMap::from([
+                    (PlayerTeam::Alpha, (self.owner_id, players[&PlayerTeam::Alpha].username.clone())),
+                    (PlayerTeam::Bravo, (opponent_id, players[&PlayerTeam::Bravo].username.clone())),
+                ]),
+            };
 
             let mut game_state = GameState::new(
                 map.squares,
                 self.card_provider.clone(),
-                Arc::new(MoveValidatorImpl::new(self.card_provider.clone())),
-                players.into_iter().map(|(team, player)| {
-                    (team, player.deck.as_ref().unwrap().cards.clone())
-                }).collect()
+                self.settings.rules.validator(self.card_provider.clone()),
+                decks
             );
 
             self.sender.send(RoomEvent::StartGame { score: game_state.score(), map_name: map.name }).ok();
+            self.active_game = Some(active_game);
+            self.draft = None;
 
             let initial_hands = game_state.assign_initial_hands();
             for (team, hand) in initial_hands {
                 self.send_to_player(team, SocketEvent::RoomEvent(RoomEvent::HandAssigned(hand))).await;
             }
 
+            self.endgame = (game_state.remaining_turns <= MAX_ENDGAME_TURNS).then(|| game_state.snapshot());
             self.game_state = Some(game_state);
             Ok(())
         }
     }
 
+    pub fn start_draft(&mut self) -> Result<(), SocketError> {
+        if self.opponent_id.filter(|id| self.users.contains_key(id)).is_none() {
+            return Err(SocketError::MissingOpponent);
+        } else if self.game_started() || self.map_veto.is_some() {
+            return Err(SocketError::RoomStarted);
+        }
+
+        let draft = Draft::new(&self.card_names()).map_err(SocketError::DraftError)?;
+        self.sender.send(RoomEvent::DraftPack { round: draft.round, cards: draft.pack.clone(), picker: draft.picker.clone() }).ok();
+        self.draft = Some(draft);
+        Ok(())
+    }
+
+    // Once both players have picked a full deck, the game starts with the drafted decks. If the opponent is gone by
+    // then, the completed draft is kept and the owner can start its game with `StartGame` later.
+    pub async fn pick_draft_card(&mut self, team: PlayerTeam, card: String) -> Result<(), SocketError> {
+        let card_names = self.card_names();
+        let Some(draft) = self.draft.as_mut() else {
+            return Err(SocketError::DraftError(DraftError::DraftNotRunning));
+        };
+
+        let result = draft.pick(&team, &card, &card_names).map_err(SocketError::DraftError)?;
+        let next_picker = (result != DraftPickResult::Completed).then(|| draft.picker.clone());
+        self.sender.send(RoomEvent::DraftPick { team, card, next_picker }).ok();
+
+        match result {
+            DraftPickResult::NextPick => Ok(()),
+            DraftPickResult::NextPack => {
+                self.sender.send(RoomEvent::DraftPack { round: draft.round, cards: draft.pack.clone(), picker: draft.picker.clone() }).ok();
+                Ok(())
+            }
+            DraftPickResult::Completed => self.start_game().await,
+        }
+    }
+
+    // Sandboxes don't need an opponent, as the owner plays both teams
+    pub fn start_sandbox(&mut self) -> Result<(), SocketError> {
+        if self.game_started() {
+            return Err(SocketError::RoomStarted);
+        } else if self.map_veto.is_some() {
+            return Err(SocketError::MapVetoError(MapVetoError::VetoInProgress));
+        }
+
+        let map = self.map_provider.get(&self.get_map_name()).unwrap();
+        let sandbox = Sandbox::new(
+            map.squares,
+            self.card_provider.clone(),
+            self.settings.rules.validator(self.card_provider.clone()));
+        self.sender.send(RoomEvent::SandboxUpdate(sandbox.state())).ok();
+        self.sandbox = Some(sandbox);
+        Ok(())
+    }
+
+    pub fn play_sandbox_turn(&mut self, moves: HashMap<PlayerTeam, PlayerMove>) -> Result<(), SocketError> {
+        self.update_sandbox(|sandbox| sandbox.play_turn(moves))
+    }
+
+    pub fn set_sandbox_square(&mut self, position: INamedPosition, square: MapSquareType) -> Result<(), SocketError> {
+        self.update_sandbox(|sandbox| sandbox.set_square(position, square))
+    }
+
+    pub fn undo_sandbox(&mut self) -> Result<(), SocketError> {
+        self.update_sandbox(Sandbox::undo)
+    }
+
+    pub fn redo_sandbox(&mut self) -> Result<(), SocketError> {
+        self.update_sandbox(Sandbox::redo)
+    }
+
+    fn update_sandbox<F>(&mut self, action: F) -> Result<(), SocketError>
+        where F: FnOnce(&mut Sandbox) -> Result<(), SandboxError>
+    {
+        let sandbox = self.sandbox.as_mut().ok_or(SocketError::SandboxError(SandboxError::SandboxNotRunning))?;
+        action(sandbox).map_err(SocketError::SandboxError)?;
+        self.sender.send(RoomEvent::SandboxUpdate(sandbox.state())).ok();
+        Ok(())
+    }
+
+    // Like sandboxes, puzzles are played by the owner alone; the opponent's moves come from the puzzle
+    pub fn start_puzzle(&mut self, puzzle: &Puzzle) -> Result<(), SocketError> {
+        if self.game_started() {
+            return Err(SocketError::RoomStarted);
+        } else if self.map_veto.is_some() {
+            return Err(SocketError::MapVetoError(MapVetoError::VetoInProgress));
+        }
+
+        let run = puzzle.start(self.card_provider.clone()).map_err(SocketError::PuzzleError)?;
+        self.sender.send(RoomEvent::PuzzleUpdate(run.state())).ok();
+        self.puzzle = Some(run);
+        Ok(())
+    }
+
+    // Plays the owner's move along with the opponent's scripted move of the same turn
+    pub fn play_puzzle_move(&mut self, player_move: PlayerMove) -> Result<(), SocketError> {
+        let run = self.puzzle.as_mut().ok_or(SocketError::PuzzleNotRunning)?;
+        run.play(player_move).map_err(SocketError::PuzzleError)?;
+        self.sender.send(RoomEvent::PuzzleUpdate(run.state())).ok();
+        Ok(())
+    }
+
+    fn card_names(&self) -> Vec<String> {
+        self.card_provider.get_all().into_iter().map(|card| card.name).collect()
+    }
+
+    // Random maps are chosen by weight. Within a series, the previous map isn't played again right away.
     fn get_map_name(&self) -> String {
-        if self.map.eq(RANDOM_MAP_NAME) {
-            let mut rng = rand::thread_rng();
-            self.map_pool.clone().into_iter().choose(&mut rng).unwrap_or(DEFAULT_GAME_MAP.to_string())
-        } else {
-            self.map.to_string()
+        if !self.map.eq(RANDOM_MAP_NAME) {
+            return self.map.to_string();
+        }
+
+        let previous_map = self.series.as_ref()
+            .filter(|series| series.in_progress())
+            .and_then(|series| series.games.last())
+            .map(|game| &game.map);
+        let mut candidates: Vec<&String> = self.map_pool.iter().filter(|map| Some(*map) != previous_map).collect();
+        if candidates.is_empty() {
+            candidates = self.map_pool.iter().collect();
+        }
+
+        candidates.choose_weighted(&mut rand::thread_rng(), |map| self.map_weight(map))
+            .map(|map| map.to_string())
+            .unwrap_or(DEFAULT_GAME_MAP.to_string())
+    }
+
+    fn map_weight(&self, map: &str) -> u32 {
+        self.map_weights.get(map).copied().unwrap_or(1)
+    }
+
+    pub fn map_pool(&self) -> MapPool {
+        MapPool { maps: self.map_pool.clone(), weights: self.map_weights.clone() }
+    }
+
+    // An empty pool restores the default one
+    pub fn set_map_pool(&mut self, map_pool: MapPool) -> Result<(), SocketError> {
+        if self.game_started() {
+            return Err(SocketError::RoomStarted);
+        } else if self.series.as_ref().is_some_and(|series| series.in_progress()) {
+            return Err(SocketError::SeriesInProgress);
+        } else if self.map_veto.is_some() {
+            return Err(SocketError::MapVetoError(MapVetoError::VetoInProgress));
         }
+
+        let MapPool { maps, weights } = map_pool;
+        if maps.iter().any(|map| !self.map_provider.exists(map))
+            || maps.iter().unique().count() != maps.len()
+            || weights.iter().any(|(map, weight)| *weight == 0 || *weight > MAX_MAP_WEIGHT || !maps.contains(map))
+        {
+            return Err(SocketError::InvalidMapPool);
+        }
+
+        self.map_pool = if maps.is_empty() { Self::get_default_map_pool(self.map_provider.clone()) } else { maps };
+        self.map_weights = weights;
+        self.sender.send(RoomEvent::MapPoolChange(self.map_pool())).ok();
+        Ok(())
     }
 
     fn get_players(&self) -> HashMap<PlayerTeam, &RoomUser> {
@@ -251,10 +1095,14 @@ impl Room {
 
             if game.all_players_have_moved() {
                 let moves = game.apply_moves();
+                if game.remaining_turns == MAX_ENDGAME_TURNS {
+                    self.endgame = Some(game.snapshot());
+                }
 
                 sender.send(RoomEvent::MovesApplied { moves: moves.applied_moves.clone(), score: game.score() }).ok();
 
-                if game.completed() {
+                let completed = game.completed();
+                if completed {
                     sender.send(RoomEvent::EndGame { score: game.score() }).ok();
                 }
 
@@ -267,20 +1115,61 @@ impl Room {
                         })
                     ).await;
                 }
+
+                if completed {
+                    self.record_result();
+                }
             }
         }
-        result.map_err(|e| SocketError::GameError(e))
+        result.map_err(SocketError::GameError)
+    }
+
+    // The running game, or once it has ended, the state it was in when its endgame started
+    pub fn endgame_state(&self) -> Option<GameState> {
+        match &self.game_state {
+            Some(game) if !game.completed() => Some(game.clone()),
+            _ => self.endgame.clone().map(|game| GameState::from_snapshot(
+                game,
+                self.card_provider.clone(),
+                self.settings.rules.validator(self.card_provider.clone()))),
+        }
+    }
+
+    // Ends the running game without recording a result, e.g. when it got stuck
+    pub fn end_game(&mut self) -> Result<(), SocketError> {
+        let Some(game) = &self.game_state else {
+            return Err(SocketError::RoomNotStarted);
+        };
+
+        if !game.completed() {
+            self.sender.send(RoomEvent::EndGame { score: game.score() }).ok();
+        }
+        self.return_to_room();
+        Ok(())
     }
 
     pub fn return_to_room(&mut self) {
         self.game_state = None;
+        self.draft = None;
+        self.sandbox = None;
+        self.puzzle = None;
+        self.active_game = None;
         self.sender.send(RoomEvent::ReturnToRoom).ok();
+
+        if self.series.as_ref().is_some_and(|series| series.completed()) {
+            self.reset_series_progress();
+        } else if let Some(next_map) = self.series.as_ref()
+            .filter(|series| series.in_progress())
+            .and_then(|series| series.next_rotation_map(&self.map_pool))
+        {
+            self.update_map(next_map).ok();
+        }
     }
 
     async fn send_to_player(&self, team: PlayerTeam, message: SocketEvent) {
         let sender: Option<&SocketSender> = match team {
             PlayerTeam::Alpha => self.user_channels.get(&self.owner_id),
-            PlayerTeam::Bravo => self.opponent_id.map_or(None, |id| self.user_channels.get(&id)),
+            PlayerTeam::Bravo => self.opponent_id.and_then(|id| self.user_channels.get(&id)),
         };
 
         if let Some(sender) = sender {
@@ -288,37 +1177,263 @@ impl Room {
         }
     }
 
-    fn modify_user<F>(&mut self, id: Uuid, action: F) where F: FnOnce(&mut RoomUser) -> () {
+    fn modify_user<F>(&mut self, id: Uuid, action: F) where F: FnOnce(&mut RoomUser) {
         if let Some(user) = self.users.get_mut(&id) {
             action(user);
             self.sender.send(RoomEvent::UserUpdate { id, user: user.clone() }).ok();
         }
     }
 
+    pub fn touch(&mut self) {
+        self.last_activity = Utc::now();
+    }
+
+    pub fn summary(&self) -> RoomSummary {
+        let username = |id: Uuid| self.users.get(&id).map(|user| user.username.clone());
+        let owner = username(self.owner_id);
+        let opponent = self.opponent_id.and_then(username);
+
+        RoomSummary {
+            code: self.code.clone(),
+            player_count: owner.iter().chain(opponent.iter()).count(),
+            owner,
+            opponent,
+            spectator_count: self.spectator_count(),
+            map: self.map.clone(),
+            started: self.game_started(),
+            series: self.series.clone(),
+            settings: self.settings.summary(),
+        }
+    }
+
+    pub fn admin_summary(&self) -> AdminRoomSummary {
+        AdminRoomSummary {
+            summary: self.summary(),
+            users: self.users.clone(),
+            reserved_players: self.reserved_players.clone(),
+            // Nobody's hand is included, as the whole game state can be looked up separately
+            game: self.game_progress(Uuid::nil()).map(|game| GameProgress { hand: None, ..game }),
+            last_activity: self.last_activity,
+        }
+    }
+
+    // The record of the room's game for analytics, once it has been played until the end
+    pub fn game_record(&self) -> Option<GameRecord> {
+        let game = self.game_state.as_ref().filter(|game| game.completed())?;
+        let active_game = self.active_game.as_ref()?;
+
+        Some(GameRecord {
+            id: Uuid::new_v4(),
+            map_name: active_game.map_name.clone(),
+            finished_at: Utc::now(),
+            score: game.score(),
+            decks: [PlayerTeam::Alpha, PlayerTeam::Bravo].into_iter()
+                .filter_map(|team| game.deck(&team).map(|deck| (team, deck.clone())))
+                .collect(),
+            turns: game.turns(),
+        })
+    }
+
+    pub fn game_in_progress(&self) -> bool {
+        self.game_state.as_ref().is_some_and(|game| !game.completed())
+    }
+
     pub fn game_started(&self) -> bool {
-        self.game_state.is_some()
+        self.game_state.is_some() || self.draft.is_some() || self.sandbox.is_some() || self.puzzle.is_some()
+    }
+
+    pub fn game_progress(&self, user_id: Uuid) -> Option<GameProgress> {
+        let game_state = self.game_state.as_ref()?;
+        let hand = self.team(user_id).and_then(|team| game_state.hand(&team)).cloned();
+
+        Some(GameProgress {
+            map_name: self.active_game.as_ref().map_or_else(|| self.map.clone(), |game| game.map_name.clone()),
+            board: game_state.board.clone(),
+            score: game_state.score(),
+            remaining_turns: game_state.remaining_turns,
+            hand,
+        })
     }
 }
 
 #[derive(Default)]
 pub struct SocketRoomStore {
     rooms: HashMap<String, Room>,
+    update_sender: Option<RoomUpdateSender>,
+    registry: Option<Arc<dyn RoomRegistry>>,
+    // Set once the server shuts down, after which rooms are kept as they are for the next start
+    shutting_down: bool,
 }
 
 impl SocketRoomStore {
-    pub fn create(&mut self, conn_id: Uuid, username: &str, conn_channel: SocketSender) -> (String, Room) {
-        log::debug!("Connection {conn_id} is creating a new room");
-        let mut room_code = Self::generate_room_code();
+    pub fn new(update_sender: Option<RoomUpdateSender>, registry: Option<Arc<dyn RoomRegistry>>) -> Self {
+        Self {
+            rooms: HashMap::new(),
+            update_sender,
+            registry,
+            shutting_down: false,
+        }
+    }
+
+    pub fn is_persistent(&self) -> bool {
+        self.update_sender.is_some()
+    }
+
+    pub fn notify_all(&self, event: RoomEvent) {
+        for room in self.rooms.values() {
+            room.sender.send(event.clone()).ok();
+        }
+    }
 
-        while self.rooms.contains_key(&room_code) {
-            room_code = Self::generate_room_code();
+    // Saves every room one last time and disconnects all users. Rooms are no longer changed after this, so that users
+    // leaving do not remove them from the persistent store.
+    pub fn shut_down(&mut self) {
+        self.shutting_down = true;
+
+        for room_code in self.rooms.keys() {
+            self.persist(room_code);
+        }
+        // Closes the channel, letting the persistence writer finish once it has saved everything
+        self.update_sender = None;
+
+        for room in self.rooms.values() {
+            for channel in room.user_channels.values() {
+                send_close(channel, SocketCloseCode::ServerRestarting);
+            }
+        }
+    }
+
+    // Restores rooms from snapshots taken before a restart. Their players get their seats back once they rejoin.
+    // Rooms that are hosted by another instance are skipped. This runs before the store is shared, so the claims don't
+    // hold up anyone.
+    pub async fn restore(&mut self, snapshots: Vec<RoomSnapshot>) {
+        for snapshot in snapshots {
+            if !Self::claim_room_code(self.registry.as_deref(), &snapshot.code).await {
+                log::debug!("Not restoring room {} as it is hosted by another instance", snapshot.code);
+                continue;
+            }
+
+            log::debug!("Restoring room {}", snapshot.code);
+            self.rooms.insert(snapshot.code.clone(), Room::from_snapshot(snapshot));
         }
+    }
 
-        let room = Room::new(conn_id, conn_channel, username);
+    pub fn registry(&self) -> Option<Arc<dyn RoomRegistry>> {
+        self.registry.clone()
+    }
 
-        log::debug!("Connection {conn_id} joins room {room_code}");
-        self.rooms.insert(room_code.to_owned(), room.clone());
-        (room_code.to_owned(), room)
+    // The registry to look up rooms that are not hosted by this instance in, if running several instances. Lookups
+    // are done after releasing the store's lock, as they take a round-trip to the registry.
+    pub fn registry_for(&self, room_code: &str) -> Option<Arc<dyn RoomRegistry>> {
+        self.registry.clone().filter(|_| !self.rooms.contains_key(room_code))
+    }
+
+    // Claims the code of a new room. Claims can take a round-trip to the registry, so the store is only locked to
+    // check that no local room uses the code; the caller inserts the room once it has the code.
+    // Gives up after a few attempts, as claims keep failing while the registry can't be reached.
+    pub async fn claim_new_room_code(room_store: &RwLock<SocketRoomStore>) -> Option<String> {
+        let registry = room_store.read().await.registry.clone();
+
+        for _ in 0..ROOM_CODE_ATTEMPTS {
+            let room_code = Self::generate_room_code();
+
+            if !room_store.read().await.rooms.contains_key(&room_code) && Self::claim_room_code(registry.as_deref(), &room_code).await {
+                return Some(room_code);
+            }
+        }
+
+        None
+    }
+
+    // Fails closed: if the registry can't be reached, the room might already be hosted by another instance.
+    async fn claim_room_code(registry: Option<&dyn RoomRegistry>, room_code: &str) -> bool {
+        let Some(registry) = registry else {
+            return true;
+        };
+
+        registry.claim(room_code).await.unwrap_or_else(|err| {
+            log::error!("Failed to claim room {room_code}: {err}");
+            false
+        })
+    }
+
+    // The registry along with the codes of all rooms this instance hosts, if running several instances
+    pub fn hosted_room_codes(&self) -> Option<(Arc<dyn RoomRegistry>, Vec<String>)> {
+        let registry = self.registry.clone()?;
+        Some((registry, self.rooms.keys().cloned().collect()))
+    }
+
+    // Released in the background, as this is called while the store is locked
+    fn release_room_code(&self, room_code: &str) {
+        if let Some(registry) = self.registry.clone() {
+            let room_code = room_code.to_string();
+            tokio::spawn(async move {
+                if let Err(err) = registry.release(&room_code).await {
+                    log::error!("Failed to release room {room_code}: {err}");
+                }
+            });
+        }
+    }
+
+    // Queues the current state of the given room for persistence. This is called while the store is locked, so
+    // updates are queued in the same order as they are applied.
+    pub fn persist(&self, room_code: &str) {
+        let Some(update_sender) = &self.update_sender else {
+            return;
+        };
+
+        let update = match self.rooms.get(room_code) {
+            Some(room) => RoomUpdate::Save(Box::new(room.snapshot())),
+            None => RoomUpdate::Delete(room_code.to_string()),
+        };
+        update_sender.send(update).ok();
+    }
+
+    // Creates a room with a code claimed by claim_new_room_code
+    pub fn create(&mut self, room_code: String, conn_id: Uuid, request: JoinRequest<'_>, conn_channel: SocketSender) -> Result<Room, SocketCloseCode> {
+        log::debug!("Connection {conn_id} joins new room {room_code}");
+        let room = Room::new(room_code, conn_id, conn_channel, &request);
+        self.insert(room.clone())?;
+        Ok(room)
+    }
+
+    // Creates an empty room that only the given players may play in. Unlike other rooms, it is not removed when
+    // all of its users leave.
+    pub fn create_reserved(
+        &mut self,
+        room_code: String,
+        players: Vec<String>,
+        join_tokens: HashMap<String, String>,
+        series: Option<Series>,
+        settings: RoomSettings,
+        result_sender: RoomResultSender
+    ) -> Result<(), SocketCloseCode>
+    {
+        log::debug!("Creating reserved room {room_code} for players {}", players.join(", "));
+        self.insert(Room::reserved(room_code, players, join_tokens, series, settings, result_sender))
+    }
+
+    // Another room might have been given the same code while it was claimed
+    fn insert(&mut self, room: Room) -> Result<(), SocketCloseCode> {
+        if self.rooms.contains_key(&room.code) {
+            return Err(SocketCloseCode::RoomUnavailable);
+        }
+
+        let room_code = room.code.clone();
+        self.rooms.insert(room_code.clone(), room);
+        self.persist(&room_code);
+        Ok(())
+    }
+
+    // Result senders are not persisted, so restored reserved rooms have to be reconnected to their tournament.
+    // Returns false if the room no longer exists.
+    pub fn set_result_sender(&mut self, room_code: &str, result_sender: RoomResultSender) -> bool {
+        let Some(room) = self.rooms.get_mut(room_code) else {
+            return false;
+        };
+
+        room.result_sender = Some(result_sender);
+        true
     }
 
     // Generates a room code; Determining whether it is unique is up to the caller.
@@ -326,32 +1441,670 @@ impl SocketRoomStore {
         Alphanumeric.sample_string(&mut rand::thread_rng(), ROOM_CODE_SIZE).to_uppercase()
     }
 
-    pub fn get_and_join_if_exists(&mut self, room_code: &str, conn_id: Uuid, username: &str, conn_channel: SocketSender) -> Option<Room> {
+    pub fn get_and_join_if_exists(
+        &mut self,
+        room_code: &str,
+        conn_id: Uuid,
+        request: JoinRequest,
+        conn_channel: SocketSender
+    ) -> Result<Room, SocketCloseCode>
+    {
         log::debug!("Connection {conn_id} attempts to join room {room_code}");
-        match self.rooms.get_mut(room_code) {
+        let room = match self.rooms.get_mut(room_code) {
             Some(room) => {
-                room.add_user(conn_id, username, conn_channel);
+                room.check_join(&request)?;
+                room.add_user(conn_id, &request, conn_channel);
 
-                Some(room.clone())
+                room.clone()
             }
-            None => None
-        }
+            None => return Err(SocketCloseCode::RoomNotFound(room_code.to_string()))
+        };
+
+        self.persist(room_code);
+        Ok(room)
     }
 
     pub fn remove_user_from_room(&mut self, room_code: &str, conn_id: Uuid) {
         log::debug!("WS connection {conn_id} leaves room {room_code}");
+        if self.shutting_down {
+            return;
+        }
+
         if let Some(room) = self.rooms.get_mut(room_code) {
             room.remove_user(conn_id);
 
-            if room.users.is_empty() {
+            if room.can_be_removed() {
                 // todo: currently, if the room owner is alone in a room and refreshes their browser, they'll receive a "room not found" error
                 log::debug!("Room {room_code} is now empty, clearing it for reuse");
                 self.rooms.remove(room_code);
+                self.release_room_code(room_code);
             }
+
+            self.persist(room_code);
         }
     }
 
+    // Closes every room that has not seen any activity since the given time, disconnecting its remaining users.
+    // Reserved rooms are kept, as their tournament would otherwise wait forever for the match's result.
+    pub fn expire_idle_rooms(&mut self, idle_since: DateTime<Utc>) -> Vec<Room> {
+        if self.shutting_down {
+            return Vec::new();
+        }
+
+        let expired_codes: Vec<String> = self.rooms.values()
+            .filter(|room| room.last_activity < idle_since && room.reserved_players.is_none())
+            .map(|room| room.code.clone())
+            .collect();
+        let mut expired_rooms = Vec::with_capacity(expired_codes.len());
+
+        for room_code in expired_codes {
+            if let Some(room) = self.close(&room_code, SocketCloseCode::RoomExpired) {
+                expired_rooms.push(room);
+            }
+        }
+
+        expired_rooms
+    }
+
+    // Removes a room, disconnecting its users with the given close code
+    pub fn close(&mut self, room_code: &str, close_code: SocketCloseCode) -> Option<Room> {
+        let room = self.rooms.remove(room_code)?;
+
+        for channel in room.user_channels.values() {
+            send_close(channel, close_code.clone());
+        }
+
+        self.release_room_code(room_code);
+        self.persist(room_code);
+        Some(room)
+    }
+
+    // Only lists the rooms hosted by this instance
+    pub fn public_rooms(&self) -> Vec<RoomSummary> {
+        self.rooms.values()
+            .filter(|room| room.settings.visibility == RoomVisibility::Public)
+            .map(Room::summary)
+            .sorted_by(|a, b| a.code.cmp(&b.code))
+            .collect()
+    }
+
+    pub fn admin_rooms(&self) -> Vec<AdminRoomSummary> {
+        self.rooms.values()
+            .map(Room::admin_summary)
+            .sorted_by(|a, b| a.summary.code.cmp(&b.summary.code))
+            .collect()
+    }
+
+    pub fn games_in_progress(&self) -> usize {
+        self.rooms.values().filter(|room| room.game_in_progress()).count()
+    }
+
+    pub fn room_count(&self) -> usize {
+        self.rooms.len()
+    }
+
+    pub fn get(&self, room_code: &str) -> Option<&Room> {
+        self.rooms.get(room_code)
+    }
+
     pub fn get_mut(&mut self, room_code: &str) -> Option<&mut Room> {
         self.rooms.get_mut(room_code)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::game::puzzle::{PuzzleCards, PuzzleError, PuzzleObjective};
+    use crate::game::rules::RuleVariant;
+    use crate::game::series::SeriesMapSelection;
+    use crate::game::squares::MST;
+    use super::*;
+
+    struct TestRoom {
+        store: SocketRoomStore,
+        code: String,
+        owner: Uuid,
+    }
+
+    impl TestRoom {
+        async fn new() -> Self {
+            let mut store = SocketRoomStore::default();
+            let owner = Uuid::new_v4();
+            let code = "ABCD".to_string();
+            store.create(code.clone(), owner, JoinRequest::named("owner"), mpsc::channel(8).0).unwrap();
+            Self { store, code, owner }
+        }
+
+        fn join(&mut self, username: &str) -> (Uuid, mpsc::Receiver<SocketEvent>) {
+            let id = Uuid::new_v4();
+            let (sender, receiver) = mpsc::channel(8);
+            self.store.get_and_join_if_exists(&self.code, id, JoinRequest::named(username), sender).unwrap();
+            (id, receiver)
+        }
+
+        fn room(&mut self) -> &mut Room {
+            self.store.get_mut(&self.code).unwrap()
+        }
+
+        async fn start_game(&mut self) -> mpsc::Receiver<SocketEvent> {
+            let (opponent, opponent_receiver) = self.join("opponent");
+            let owner = self.owner;
+            let room = self.room();
+            let deck: IndexSet<String> = room.card_provider.get_all().into_iter().take(DECK_SIZE).map(|card| card.name).collect();
+            room.set_deck(owner, "owner".to_string(), deck.clone()).unwrap();
+            room.set_deck(opponent, "opponent".to_string(), deck).unwrap();
+            room.start_game().await.unwrap();
+            opponent_receiver
+        }
+
+        // Gives the team a lead and plays the last turn, so the game ends with them winning
+        async fn win_game(&mut self, team: PlayerTeam) {
+            let room = self.room();
+            let game = room.game_state.as_mut().unwrap();
+            game.remaining_turns = 1;
+            let (_, position) = game.board.clone().into_iter().find(|(square, _)| square == &MST::Empty).unwrap();
+            game.board[position] = match team {
+                PlayerTeam::Alpha => MST::FillAlpha,
+                PlayerTeam::Bravo => MST::FillBravo,
+            };
+
+            for team in [PlayerTeam::Alpha, PlayerTeam::Bravo] {
+                let card_name = room.game_state.as_ref().unwrap().hand(&team).unwrap()[0].clone();
+                room.propose_move(team, PlayerMove::Pass { card_name }).await.unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn keeps_endgame_after_game_ends() {
+        let mut room = TestRoom::new().await;
+        let _opponent_receiver = room.start_game().await;
+        room.room().game_state.as_mut().unwrap().remaining_turns = MAX_ENDGAME_TURNS + 1;
+
+        while room.room().game_state.as_ref().is_some_and(|game| !game.completed()) {
+            for team in [PlayerTeam::Alpha, PlayerTeam::Bravo] {
+                let card_name = room.room().game_state.as_ref().unwrap().hand(&team).unwrap()[0].clone();
+                room.room().propose_move(team, PlayerMove::Pass { card_name }).await.unwrap();
+            }
+        }
+        room.room().return_to_room();
+
+        let endgame = Room::from_snapshot(room.room().snapshot()).endgame_state().unwrap();
+        assert_eq!(MAX_ENDGAME_TURNS, endgame.remaining_turns);
+        assert!(endgame.hand(&PlayerTeam::Bravo).is_some());
+    }
+
+    #[tokio::test]
+    async fn kick_user_bans_username_and_address() {
+        let mut room = TestRoom::new().await;
+        let (opponent, _) = room.join("opponent");
+        let spectator = Uuid::new_v4();
+        let spectator_ip = IpAddr::from([203, 0, 113, 7]);
+        let (sender, mut spectator_receiver) = mpsc::channel(1);
+        let request = JoinRequest { client_ip: spectator_ip, ..JoinRequest::named("spectator") };
+        room.store.get_and_join_if_exists(&room.code, spectator, request, sender.clone()).unwrap();
+        // A full channel must not keep the user from being disconnected
+        sender.try_send(SocketEvent::Pong).unwrap();
+
+        room.room().kick_user(spectator).unwrap();
+
+        assert!(!room.room().users.contains_key(&spectator));
+        assert!(matches!(spectator_receiver.recv().await, Some(SocketEvent::Pong)));
+        assert!(matches!(spectator_receiver.recv().await, Some(SocketEvent::Close(SocketCloseCode::Kicked))));
+        let mut rejoin = |request| room.store.get_and_join_if_exists(&room.code, Uuid::new_v4(), request, mpsc::channel(8).0);
+        assert!(matches!(rejoin(JoinRequest::named("spectator")), Err(SocketCloseCode::Banned)));
+        assert!(matches!(rejoin(JoinRequest { client_ip: spectator_ip, ..JoinRequest::named("renamed") }), Err(SocketCloseCode::Banned)));
+        assert!(rejoin(JoinRequest::named("someone-else")).is_ok());
+
+        // The ban outlives a restart
+        let restored = Room::from_snapshot(room.room().snapshot());
+        assert!(matches!(restored.check_join(&JoinRequest { client_ip: spectator_ip, ..JoinRequest::named("renamed") }), Err(SocketCloseCode::Banned)));
+
+        // The remaining user takes the kicked opponent's seat
+        room.room().kick_user(opponent).unwrap();
+        assert_ne!(Some(opponent), room.room().opponent_id);
+    }
+
+    #[tokio::test]
+    async fn kick_user_rejects_owner() {
+        let mut room = TestRoom::new().await;
+        let owner = room.owner;
+
+        assert!(matches!(room.room().kick_user(owner), Err(SocketError::CannotTargetSelf)));
+        assert!(matches!(room.room().kick_user(Uuid::new_v4()), Err(SocketError::UserNotFound)));
+    }
+
+    #[tokio::test]
+    async fn transfer_ownership_to_spectator() {
+        let mut room = TestRoom::new().await;
+        let (opponent, _) = room.join("opponent");
+        let (spectator, _) = room.join("spectator");
+
+        room.room().transfer_ownership(spectator).unwrap();
+
+        assert_eq!(spectator, room.room().owner_id);
+        assert_eq!(Some(opponent), room.room().opponent_id);
+    }
+
+    #[tokio::test]
+    async fn transfer_ownership_to_opponent_swaps_seats() {
+        let mut room = TestRoom::new().await;
+        let owner = room.owner;
+        let (opponent, _) = room.join("opponent");
+
+        room.room().transfer_ownership(opponent).unwrap();
+
+        assert_eq!(opponent, room.room().owner_id);
+        assert_eq!(Some(owner), room.room().opponent_id);
+    }
+
+    #[tokio::test]
+    async fn choose_opponent() {
+        let mut room = TestRoom::new().await;
+        let owner = room.owner;
+        room.join("opponent");
+        let (spectator, _) = room.join("spectator");
+
+        room.room().choose_opponent(Some(spectator)).unwrap();
+        assert_eq!(Some(spectator), room.room().opponent_id);
+
+        assert!(matches!(room.room().choose_opponent(Some(owner)), Err(SocketError::CannotTargetSelf)));
+        room.room().choose_opponent(None).unwrap();
+        assert_eq!(None, room.room().opponent_id);
+    }
+
+    #[tokio::test]
+    async fn owner_leaving_promotes_opponent_and_fills_their_seat() {
+        let mut room = TestRoom::new().await;
+        let owner = room.owner;
+        let (opponent, _) = room.join("opponent");
+        let (spectator, _) = room.join("spectator");
+
+        room.store.remove_user_from_room(&room.code.clone(), owner);
+
+        assert_eq!(opponent, room.room().owner_id);
+        assert_eq!(Some(spectator), room.room().opponent_id);
+    }
+
+    #[tokio::test]
+    async fn summary() {
+        let mut room = TestRoom::new().await;
+        room.join("opponent");
+        room.join("spectator");
+
+        let summary = room.room().summary();
+
+        assert_eq!(Some("owner".to_string()), summary.owner);
+        assert_eq!(Some("opponent".to_string()), summary.opponent);
+        assert_eq!(2, summary.player_count);
+        assert_eq!(1, summary.spectator_count);
+        assert!(!summary.started);
+    }
+
+    #[tokio::test]
+    async fn public_rooms_skip_private_rooms() {
+        let mut room = TestRoom::new().await;
+        let private_code = "EFGH".to_string();
+        room.store.create(private_code.clone(), Uuid::new_v4(), JoinRequest::named("private"), mpsc::channel(8).0).unwrap();
+        let settings = RoomSettings { visibility: RoomVisibility::Private, ..RoomSettings::default() };
+        room.store.get_mut(&private_code).unwrap().set_settings(settings).unwrap();
+
+        let public_rooms = room.store.public_rooms();
+
+        assert_eq!(1, public_rooms.len());
+        assert_eq!(room.code, public_rooms[0].code);
+    }
+
+    #[tokio::test]
+    async fn shut_down_keeps_rooms() {
+        let (update_sender, mut update_receiver) = mpsc::unbounded_channel();
+        let mut store = SocketRoomStore::new(Some(update_sender), None);
+        let owner = Uuid::new_v4();
+        let (owner_sender, mut owner_receiver) = mpsc::channel(8);
+        let room_code = "ABCD".to_string();
+        store.create(room_code.clone(), owner, JoinRequest::named("owner"), owner_sender).unwrap();
+        assert!(matches!(update_receiver.recv().await, Some(RoomUpdate::Save(_))));
+
+        store.shut_down();
+        store.remove_user_from_room(&room_code, owner);
+
+        assert!(matches!(owner_receiver.recv().await, Some(SocketEvent::Close(SocketCloseCode::ServerRestarting))));
+        assert!(matches!(update_receiver.recv().await, Some(RoomUpdate::Save(snapshot)) if snapshot.code == room_code));
+        // The channel is closed without the room being deleted
+        assert!(update_receiver.recv().await.is_none());
+        assert!(store.get(&room_code).is_some());
+        assert!(!store.is_persistent());
+    }
+
+    #[tokio::test]
+    async fn end_game_returns_to_room() {
+        let mut room = TestRoom::new().await;
+        assert!(matches!(room.room().end_game(), Err(SocketError::RoomNotStarted)));
+        let _opponent_receiver = room.start_game().await;
+        let mut events = room.room().sender.subscribe();
+
+        room.room().end_game().unwrap();
+
+        assert!(!room.room().game_started());
+        assert!(matches!(events.recv().await, Ok(RoomEvent::EndGame { .. })));
+        assert!(matches!(events.recv().await, Ok(RoomEvent::ReturnToRoom)));
+    }
+
+    #[tokio::test]
+    async fn series_records_results_and_rotates_maps() {
+        let mut room = TestRoom::new().await;
+        let owner = room.owner;
+        let maps = room.room().map_pool.clone();
+        room.room().set_map(maps[0].clone()).unwrap();
+        room.room().set_series(Some(SeriesSettings { best_of: 3, map_selection: SeriesMapSelection::Rotation })).unwrap();
+        let _opponent_receiver = room.start_game().await;
+        let mut events = room.room().sender.subscribe();
+
+        room.win_game(PlayerTeam::Alpha).await;
+        room.room().return_to_room();
+        let series = room.room().series.clone().unwrap();
+        assert_eq!(HashMap::from([(owner, 1)]), series.wins);
+        assert_eq!(maps[0], series.games[0].map);
+        assert_eq!(maps[1], room.room().map);
+        assert!(matches!(room.room().set_map(maps[0].clone()), Err(SocketError::SeriesInProgress)));
+
+        room.room().start_game().await.unwrap();
+        room.win_game(PlayerTeam::Alpha).await;
+        assert!(room.room().series.as_ref().unwrap().completed());
+        let mut series_end = None;
+        while let Ok(event) = events.try_recv() {
+            if let RoomEvent::SeriesEnd { winner, .. } = event {
+                series_end = Some(winner);
+            }
+        }
+        assert_eq!(Some(owner), series_end);
+
+        // The next game starts a new series
+        room.room().return_to_room();
+        assert!(room.room().series.as_ref().unwrap().games.is_empty());
+    }
+
+    #[tokio::test]
+    async fn game_record_requires_completed_game() {
+        let mut room = TestRoom::new().await;
+        let _opponent_receiver = room.start_game().await;
+
+        assert!(room.room().game_record().is_none());
+    }
+
+    #[tokio::test]
+    async fn admin_summary_hides_hands() {
+        let mut room = TestRoom::new().await;
+        let _opponent_receiver = room.start_game().await;
+
+        let rooms = room.store.admin_rooms();
+
+        assert_eq!(1, rooms.len());
+        assert_eq!(2, rooms[0].users.len());
+        assert!(rooms[0].game.as_ref().is_some_and(|game| game.hand.is_none()));
+    }
+
+    #[tokio::test]
+    async fn close_disconnects_users() {
+        let mut room = TestRoom::new().await;
+        let (_, mut spectator_receiver) = room.join("spectator");
+
+        assert!(room.store.close(&room.code.clone(), SocketCloseCode::RoomClosed).is_some());
+
+        assert!(room.store.get(&room.code).is_none());
+        assert!(matches!(spectator_receiver.recv().await, Some(SocketEvent::Close(SocketCloseCode::RoomClosed))));
+        assert!(room.store.close("ZZZZ", SocketCloseCode::RoomClosed).is_none());
+    }
+
+    #[tokio::test]
+    async fn games_follow_room_rules() {
+        let mut room = TestRoom::new().await;
+        let settings = RoomSettings { rules: RuleVariant::NoDiagonalAdjacency, ..RoomSettings::default() };
+        room.room().set_settings(settings.clone()).unwrap();
+        let _opponent_receiver = room.start_game().await;
+
+        assert_eq!(RuleVariant::NoDiagonalAdjacency.rules(), room.room().game_state.as_ref().unwrap().rules());
+        let restored = Room::from_snapshot(room.room().snapshot());
+        assert_eq!(RuleVariant::NoDiagonalAdjacency.rules(), restored.game_state.unwrap().rules());
+
+        assert!(matches!(room.room().set_settings(RoomSettings::default()), Err(SocketError::RoomStarted)));
+        assert!(room.room().set_settings(RoomSettings { max_spectators: Some(3), ..settings }).is_ok());
+    }
+
+    #[tokio::test]
+    async fn draft_builds_decks_and_starts_game() {
+        let mut room = TestRoom::new().await;
+        assert!(matches!(room.room().start_draft(), Err(SocketError::MissingOpponent)));
+        let (opponent, _opponent_receiver) = room.join("opponent");
+
+        room.room().start_draft().unwrap();
+        assert!(room.room().game_started());
+        assert!(matches!(room.room().start_draft(), Err(SocketError::RoomStarted)));
+        assert!(matches!(room.room().start_game().await, Err(SocketError::RoomStarted)));
+        assert!(Room::from_snapshot(room.room().snapshot()).draft.is_some());
+
+        while let Some(draft) = room.room().draft.clone() {
+            room.room().pick_draft_card(draft.picker, draft.pack[0].clone()).await.unwrap();
+        }
+
+        let room = room.room();
+        assert!(room.game_state.is_some());
+        assert!(room.draft.is_none());
+        assert!(room.users[&opponent].deck.is_none());
+        assert_eq!(Some(DECK_SIZE), room.game_state.as_ref().unwrap().deck(&PlayerTeam::Bravo).map(|deck| deck.len()));
+        assert!(matches!(
+            room.pick_draft_card(PlayerTeam::Alpha, "card".to_string()).await,
+            Err(SocketError::DraftError(DraftError::DraftNotRunning))));
+    }
+
+    #[tokio::test]
+    async fn completed_draft_waits_for_missing_opponent() {
+        let mut room = TestRoom::new().await;
+        let _opponent_receiver = room.start_game().await;
+        let opponent = room.room().opponent_id.unwrap();
+        room.room().return_to_room();
+        let own_deck = room.room().users[&opponent].deck.clone();
+        room.room().start_draft().unwrap();
+
+        while let Some(draft) = room.room().draft.clone().filter(|draft| draft.picks.values().map(IndexSet::len).sum::<usize>() < DECK_SIZE * 2 - 1) {
+            room.room().pick_draft_card(draft.picker, draft.pack[0].clone()).await.unwrap();
+        }
+        room.store.remove_user_from_room(&room.code, opponent);
+        let draft = room.room().draft.clone().unwrap();
+        assert!(matches!(room.room().pick_draft_card(draft.picker, draft.pack[0].clone()).await, Err(SocketError::MissingOpponent)));
+        assert!(room.room().draft.as_ref().is_some_and(|draft| draft.completed()));
+
+        let (returning_opponent, _returning_receiver) = room.join("opponent");
+        room.room().opponent_id = Some(returning_opponent);
+        room.room().start_game().await.unwrap();
+        assert!(room.room().draft.is_none());
+        assert_ne!(own_deck.unwrap().cards, room.room().game_state.as_ref().unwrap().deck(&PlayerTeam::Bravo).unwrap().clone());
+    }
+
+    #[tokio::test]
+    async fn map_veto_sets_map() {
+        let mut room = TestRoom::new().await;
+        let (opponent, _opponent_receiver) = room.join("opponent");
+        let owner = room.owner;
+        room.room().map_pool.truncate(3);
+        let maps = room.room().map_pool.clone();
+
+        room.room().start_map_veto().unwrap();
+        assert!(matches!(room.room().start_game().await, Err(SocketError::MapVetoError(MapVetoError::VetoInProgress))));
+        assert!(matches!(room.room().start_draft(), Err(SocketError::RoomStarted)));
+        assert!(matches!(room.room().set_map("random".to_string()), Err(SocketError::MapVetoError(MapVetoError::VetoInProgress))));
+        assert!(matches!(room.room().pick_map(owner, maps[0].clone()), Err(SocketError::MapVetoError(MapVetoError::NotMapBanner))));
+
+        room.room().ban_map(PlayerTeam::Alpha, maps[1].clone()).unwrap();
+        assert!(Room::from_snapshot(room.room().snapshot()).map_veto.is_some());
+        room.room().ban_map(PlayerTeam::Bravo, maps[0].clone()).unwrap();
+
+        assert!(room.room().map_veto.is_none());
+        assert_eq!(maps[2], room.room().map);
+        assert!(matches!(room.room().ban_map(PlayerTeam::Alpha, maps[2].clone()), Err(SocketError::MapVetoError(MapVetoError::VetoNotRunning))));
+
+        // Changing seats cancels a running veto
+        room.room().start_map_veto().unwrap();
+        room.room().choose_opponent(None).unwrap();
+        assert!(room.room().map_veto.is_none());
+        room.room().choose_opponent(Some(opponent)).unwrap();
+    }
+
+    #[tokio::test]
+    async fn map_veto_lets_last_loser_pick() {
+        let mut room = TestRoom::new().await;
+        let _opponent_receiver = room.join("opponent");
+        let opponent = room.room().opponent_id.unwrap();
+        let owner = room.owner;
+        room.room().map_pool.truncate(4);
+        let maps = room.room().map_pool.clone();
+        room.room().last_loser = Some(opponent);
+
+        room.room().start_map_veto().unwrap();
+        room.room().ban_map(PlayerTeam::Alpha, maps[0].clone()).unwrap();
+        assert!(matches!(room.room().ban_map(PlayerTeam::Bravo, maps[1].clone()), Err(SocketError::MapVetoError(MapVetoError::NotMapBanner))));
+        assert!(matches!(room.room().pick_map(owner, maps[1].clone()), Err(SocketError::MapVetoError(MapVetoError::NotMapBanner))));
+
+        room.room().pick_map(opponent, maps[2].clone()).unwrap();
+        assert!(room.room().map_veto.is_none());
+        assert_eq!(maps[2], room.room().map);
+    }
+
+    #[tokio::test]
+    async fn returning_players_keep_their_last_loss() {
+        let mut room = TestRoom::new().await;
+        let _opponent_receiver = room.join("opponent");
+        let opponent = room.room().opponent_id.unwrap();
+        room.room().last_loser = Some(opponent);
+        let token = room.room().reconnect_token(opponent).unwrap().clone();
+
+        let mut restored_store = SocketRoomStore::default();
+        restored_store.restore(vec!(room.room().snapshot())).await;
+        let returning_id = Uuid::new_v4();
+        let request = JoinRequest { token: Some(&token), ..JoinRequest::named("opponent") };
+        restored_store.get_and_join_if_exists(&room.code, returning_id, request, mpsc::channel(8).0).unwrap();
+
+        assert_eq!(Some(returning_id), restored_store.get(&room.code).unwrap().last_loser);
+    }
+
+    #[tokio::test]
+    async fn set_map_pool() {
+        let mut room = TestRoom::new().await;
+        let default_pool = room.room().map_pool.clone();
+        let maps = default_pool[..2].to_vec();
+        let pool = |maps: &[String], weights: &[(&String, u32)]| MapPool {
+            maps: maps.to_vec(),
+            weights: weights.iter().map(|(map, weight)| (map.to_string(), *weight)).collect(),
+        };
+
+        assert!(matches!(room.room().set_map_pool(pool(&["Unknown".to_string()], &[])), Err(SocketError::InvalidMapPool)));
+        assert!(matches!(room.room().set_map_pool(pool(&[maps[0].clone(), maps[0].clone()], &[])), Err(SocketError::InvalidMapPool)));
+        assert!(matches!(room.room().set_map_pool(pool(&maps, &[(&maps[0], 0)])), Err(SocketError::InvalidMapPool)));
+        assert!(matches!(room.room().set_map_pool(pool(&maps[..1], &[(&maps[1], 2)])), Err(SocketError::InvalidMapPool)));
+        assert!(matches!(room.room().set_map_pool(pool(&maps, &[(&maps[0], u32::MAX), (&maps[1], 1)])), Err(SocketError::InvalidMapPool)));
+        room.room().set_map_pool(pool(&maps, &[(&maps[0], MAX_MAP_WEIGHT)])).unwrap();
+
+        room.room().set_map_pool(pool(&maps, &[(&maps[1], 3)])).unwrap();
+        assert_eq!(pool(&maps, &[(&maps[1], 3)]), room.room().map_pool());
+        assert_eq!(pool(&maps, &[(&maps[1], 3)]), Room::from_snapshot(room.room().snapshot()).map_pool());
+
+        room.room().set_map_pool(MapPool::default()).unwrap();
+        assert_eq!(default_pool, room.room().map_pool);
+    }
+
+    #[tokio::test]
+    async fn random_maps_follow_weights_and_avoid_repeats_in_series() {
+        let mut room = TestRoom::new().await;
+        let maps = room.room().map_pool[..3].to_vec();
+        room.room().set_map(RANDOM_MAP_NAME.to_string()).unwrap();
+        room.room().set_map_pool(MapPool { maps: maps[..2].to_vec(), weights: HashMap::from([(maps[0].clone(), 1000)]) }).unwrap();
+
+        let picks = (0..100).map(|_| room.room().get_map_name()).filter(|map| map == &maps[0]).count();
+        assert!(picks > 90);
+
+        let mut series = Series::new(SeriesSettings { best_of: 3, map_selection: SeriesMapSelection::LoserPick }).unwrap();
+        series.record_game(maps[0].clone(), None, None);
+        room.room().series = Some(series);
+        assert!((0..20).all(|_| room.room().get_map_name() == maps[1]));
+    }
+
+    #[tokio::test]
+    async fn sandbox_without_opponent() {
+        let mut room = TestRoom::new().await;
+        let mut events = room.room().sender.subscribe();
+        assert!(matches!(room.room().undo_sandbox(), Err(SocketError::SandboxError(SandboxError::SandboxNotRunning))));
+
+        room.room().start_sandbox().unwrap();
+        assert!(room.room().game_started());
+        assert!(matches!(room.room().start_sandbox(), Err(SocketError::RoomStarted)));
+        assert!(matches!(events.recv().await, Ok(RoomEvent::SandboxUpdate(state)) if !state.can_undo));
+
+        room.room().set_sandbox_square((0, 0).into(), MapSquareType::FillAlpha).unwrap();
+        assert!(matches!(events.recv().await, Ok(RoomEvent::SandboxUpdate(state)) if state.board[(0, 0)] == MapSquareType::FillAlpha));
+        assert!(matches!(
+            room.room().set_sandbox_square((-1, 0).into(), MapSquareType::FillAlpha),
+            Err(SocketError::SandboxError(SandboxError::SquareOutOfBounds))));
+
+        let restored = Room::from_snapshot(room.room().snapshot());
+        assert_eq!(MapSquareType::FillAlpha, restored.sandbox.unwrap().state().board[(0, 0)]);
+
+        room.room().undo_sandbox().unwrap();
+        assert!(matches!(events.recv().await, Ok(RoomEvent::SandboxUpdate(state)) if state.can_redo));
+
+        room.room().return_to_room();
+        assert!(!room.room().game_started());
+    }
+
+    #[tokio::test]
+    async fn sandbox_ends_when_owner_leaves() {
+        let mut room = TestRoom::new().await;
+        let (opponent, _opponent_receiver) = room.join("opponent");
+        let owner = room.owner;
+        room.room().start_sandbox().unwrap();
+
+        room.store.remove_user_from_room(&room.code, owner);
+
+        assert!(room.room().sandbox.is_none());
+        assert_eq!(opponent, room.room().owner_id);
+    }
+
+    #[tokio::test]
+    async fn puzzle_plays_scripted_opponent() {
+        let mut room = TestRoom::new().await;
+        let mut events = room.room().sender.subscribe();
+        let cards: Vec<String> = room.room().card_names().into_iter().take(2).collect();
+        let mut board = vec!(vec!(MST::Empty; 7); 7);
+        board[0][0] = MST::SpecialAlpha;
+        board[6][6] = MST::SpecialBravo;
+        let opponent_move = PlayerMove::Pass { card_name: cards[1].clone() };
+        let puzzle = Puzzle {
+            name: "Test".to_string(),
+            description: String::new(),
+            board,
+            rules: RuleVariant::Standard,
+            cards: PuzzleCards::Hand(cards.clone()),
+            opponent_moves: vec!(opponent_move.clone(), opponent_move.clone()),
+            objective: PuzzleObjective::InkAtLeast(2),
+        };
+        assert!(matches!(room.room().play_puzzle_move(opponent_move.clone()), Err(SocketError::PuzzleNotRunning)));
+
+        room.room().start_puzzle(&puzzle).unwrap();
+        assert!(matches!(room.room().start_sandbox(), Err(SocketError::RoomStarted)));
+        assert!(matches!(events.recv().await, Ok(RoomEvent::PuzzleUpdate(state)) if state.turn == 0 && state.turns == 2));
+
+        room.room().play_puzzle_move(PlayerMove::Pass { card_name: cards[0].clone() }).unwrap();
+        assert!(matches!(events.recv().await, Ok(RoomEvent::PuzzleUpdate(state))
+            if state.turn == 1 && state.last_moves.as_ref().is_some_and(|moves| moves[&PlayerTeam::Bravo] == opponent_move)));
+        assert!(matches!(
+            room.room().play_puzzle_move(PlayerMove::Pass { card_name: cards[0].clone() }),
+            Err(SocketError::PuzzleError(PuzzleError::CardNotPlayable { turn: 1, .. }))));
+
+        let mut restored = Room::from_snapshot(room.room().snapshot());
+        assert_eq!(1, restored.puzzle.as_ref().unwrap().turn);
+        restored.play_puzzle_move(PlayerMove::Pass { card_name: cards[1].clone() }).unwrap();
+        assert!(restored.puzzle.unwrap().state().outcome.is_some_and(|outcome| !outcome.solved));
+
+        room.room().return_to_room();
+        assert!(room.room().puzzle.is_none());
+    }
+}
@@ -0,0 +1,126 @@
+use std::fmt::{Display, Formatter};
+use axum::extract::ws::Message;
+use serde::{Deserialize, Serialize};
+use crate::socket::messages::{SocketAction, SocketEvent};
+
+// How events are serialized for a client, chosen with the `format` query parameter when connecting.
+// Actions are accepted in either format: text frames are parsed as JSON and binary frames as MessagePack.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+pub enum WireFormat {
+    #[default]
+    #[serde(rename = "json")]
+    Json,
+    #[serde(rename = "msgpack")]
+    MessagePack,
+}
+
+#[derive(Debug)]
+pub enum WireFormatError {
+    Json(serde_json::Error),
+    MessagePackEncode(rmp_serde::encode::Error),
+    MessagePackDecode(rmp_serde::decode::Error),
+}
+
+impl Display for WireFormatError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WireFormatError::Json(err) => write!(f, "{err}"),
+            WireFormatError::MessagePackEncode(err) => write!(f, "{err}"),
+            WireFormatError::MessagePackDecode(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl WireFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WireFormat::Json => "json",
+            WireFormat::MessagePack => "msgpack",
+        }
+    }
+
+    pub fn encode(&self, event: &SocketEvent) -> Result<Message, WireFormatError> {
+        match self {
+            WireFormat::Json => serde_json::to_string(event)
+                .map(Message::Text)
+                .map_err(WireFormatError::Json),
+            WireFormat::MessagePack => {
+                // Structs are written as maps and UUIDs as strings, so that both formats share the same structure
+                let mut buffer = Vec::new();
+                let mut serializer = rmp_serde::Serializer::new(&mut buffer).with_struct_map().with_human_readable();
+                event.serialize(&mut serializer).map_err(WireFormatError::MessagePackEncode)?;
+                Ok(Message::Binary(buffer))
+            }
+        }
+    }
+}
+
+// Returns None for frames that don't carry actions, e.g. pings.
+pub fn decode_action(message: &Message) -> Option<Result<SocketAction, WireFormatError>> {
+    match message {
+        Message::Text(text) => Some(serde_json::from_str(text).map_err(WireFormatError::Json)),
+        Message::Binary(data) => {
+            let mut deserializer = rmp_serde::Deserializer::new(data.as_slice()).with_human_readable();
+            Some(SocketAction::deserialize(&mut deserializer).map_err(WireFormatError::MessagePackDecode))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use serde_json::json;
+    use uuid::Uuid;
+    use crate::game::team::PlayerTeam;
+    use crate::socket::messages::{RoomEvent, SocketError};
+    use super::*;
+
+    fn to_value(message: Message) -> serde_json::Value {
+        match message {
+            Message::Text(text) => serde_json::from_str(&text).unwrap(),
+            Message::Binary(data) => rmp_serde::from_slice(&data).unwrap(),
+            _ => panic!("Unexpected message {message:?}"),
+        }
+    }
+
+    #[test]
+    fn formats_share_structure() {
+        let user_id = Uuid::new_v4();
+        let events = [
+            SocketEvent::Pong,
+            SocketEvent::Error(SocketError::RoomNotFound("ABCD".to_string())),
+            SocketEvent::RoomEvent(RoomEvent::OwnerChange(user_id)),
+            SocketEvent::RoomEvent(RoomEvent::EndGame { score: HashMap::from([(PlayerTeam::Alpha, 10), (PlayerTeam::Bravo, 8)]) }),
+        ];
+
+        for event in events {
+            let json = WireFormat::Json.encode(&event).unwrap();
+            let message_pack = WireFormat::MessagePack.encode(&event).unwrap();
+
+            assert!(matches!(json, Message::Text(_)));
+            assert!(matches!(message_pack, Message::Binary(_)));
+            assert_eq!(to_value(json), to_value(message_pack));
+        }
+    }
+
+    #[test]
+    fn message_pack_uses_string_uuids() {
+        let user_id = Uuid::new_v4();
+        let message = WireFormat::MessagePack.encode(&SocketEvent::RoomEvent(RoomEvent::UserLeave(user_id))).unwrap();
+
+        assert_eq!(json!({ "event": "RoomEvent", "detail": { "event": "UserLeave", "detail": user_id.to_string() } }), to_value(message));
+    }
+
+    #[test]
+    fn decode_action() {
+        let user_id = Uuid::new_v4();
+        let action = json!({ "action": "KickUser", "args": user_id.to_string() });
+        let binary = rmp_serde::to_vec_named(&action).unwrap();
+
+        assert!(matches!(super::decode_action(&Message::Text(action.to_string())), Some(Ok(SocketAction::KickUser(id))) if id == user_id));
+        assert!(matches!(super::decode_action(&Message::Binary(binary)), Some(Ok(SocketAction::KickUser(id))) if id == user_id));
+        assert!(matches!(super::decode_action(&Message::Binary(vec!(0xc1))), Some(Err(_))));
+        assert!(super::decode_action(&Message::Ping(Vec::new())).is_none());
+    }
+}
@@ -0,0 +1,213 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use futures::{SinkExt, StreamExt};
+use serde_json::Value;
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, RwLock};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::tungstenite::Message;
+use uuid::Uuid;
+use crate::{AppState, create_router, tournament};
+use crate::analytics::Analytics;
+use crate::app_config::LimitsConfiguration;
+use crate::game::card::CardSquareProviderImpl;
+use crate::game::map::MapProviderImpl;
+use crate::metrics::Metrics;
+use crate::puzzles::PuzzleStore;
+use crate::socket::limits::ConnectionLimiter;
+use crate::socket::messages::SocketEvent;
+use crate::socket::protocol::PROTOCOL_VERSION;
+use crate::socket::room_store::SocketRoomStore;
+use crate::tournament::TournamentStore;
+
+// How long clients wait for an event before failing the test
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(5);
+// How long clients wait to make sure no further events arrive
+const SILENCE_PERIOD: Duration = Duration::from_millis(100);
+
+// Limits loose enough not to get in the way of scripted clients
+fn test_limits() -> LimitsConfiguration {
+    LimitsConfiguration {
+        max_message_size: 16384,
+        max_connections_per_ip: 100,
+        action_burst: 100,
+        actions_per_second: 100.0,
+        max_rate_limit_violations: 10,
+        trust_forwarded_for: false,
+    }
+}
+
+// The full router on an ephemeral port, with in-memory stores only
+pub struct TestServer {
+    pub address: SocketAddr,
+    pub state: Arc<AppState>,
+}
+
+impl TestServer {
+    pub async fn start() -> Self {
+        let limits = test_limits();
+        let (room_result_sender, room_result_receiver) = mpsc::unbounded_channel();
+        let state = Arc::new(AppState {
+            room_store: RwLock::new(SocketRoomStore::default()),
+            tournament_store: RwLock::new(TournamentStore::new(room_result_sender, None)),
+            connection_limiter: Arc::new(ConnectionLimiter::new(limits.max_connections_per_ip)),
+            limits,
+            card_provider: Arc::new(CardSquareProviderImpl::new()),
+            map_provider: Arc::new(MapProviderImpl::new()),
+            metrics: Metrics::default(),
+            analytics: Analytics::default(),
+            admin_token: None,
+            relay_secret: None,
+            puzzles: PuzzleStore::new(None),
+        });
+        tokio::spawn(tournament::handle_room_results(state.clone(), room_result_receiver));
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let router = create_router(state.clone());
+        tokio::spawn(axum::Server::from_tcp(listener).unwrap()
+            .serve(router.into_make_service_with_connect_info::<SocketAddr>()));
+
+        Self { address, state }
+    }
+
+    // Connects a client that creates a new room, or joins the given one
+    pub async fn connect(&self, username: &str, room_code: Option<&str>) -> TestClient {
+        let mut client = TestClient { socket: self.open(username, room_code).await, id: Uuid::nil(), room_code: String::new() };
+
+        let welcome = client.receive().await;
+        assert_eq!("Welcome", welcome["event"], "Expected a welcome event, got {welcome}");
+        client.id = serde_json::from_value(welcome["detail"]["id"].clone()).unwrap();
+        client.room_code = welcome["detail"]["roomCode"].as_str().unwrap().to_string();
+        client
+    }
+
+    // Returns the close code of a connection the server turns away
+    pub async fn connect_rejected(&self, username: &str, room_code: Option<&str>) -> u16 {
+        TestClient { socket: self.open(username, room_code).await, id: Uuid::nil(), room_code: String::new() }
+            .expect_close().await
+    }
+
+    async fn open(&self, username: &str, room_code: Option<&str>) -> WebSocketStream<MaybeTlsStream<TcpStream>> {
+        let mut url = format!("ws://{}/ws?username={username}&version={PROTOCOL_VERSION}", self.address);
+        if let Some(room_code) = room_code {
+            url += &format!("&room={room_code}");
+        }
+
+        tokio_tungstenite::connect_async(url).await.unwrap().0
+    }
+}
+
+// A scripted WS client. Events are compared as JSON, ignoring when users joined as that can't be known in advance.
+pub struct TestClient {
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    pub id: Uuid,
+    pub room_code: String,
+}
+
+impl TestClient {
+    pub async fn send(&mut self, action: Value) {
+        self.socket.send(Message::Text(action.to_string())).await.unwrap();
+    }
+
+    pub async fn leave(mut self) {
+        self.socket.close(None).await.unwrap();
+    }
+
+    pub async fn receive(&mut self) -> Value {
+        loop {
+            let message = tokio::time::timeout(RECEIVE_TIMEOUT, self.socket.next()).await
+                .expect("Timed out waiting for an event")
+                .expect("The connection was closed")
+                .unwrap();
+
+            if let Message::Text(text) = message {
+                return normalize(serde_json::from_str(&text).unwrap());
+            }
+        }
+    }
+
+    // Asserts that the next event is the given one
+    pub async fn expect(&mut self, event: SocketEvent) {
+        assert_eq!(normalize(serde_json::to_value(event).unwrap()), self.receive().await);
+    }
+
+    // Asserts that the next events are the given ones, in any order. Events sent to a single user (e.g. hands) take a
+    // different path than those sent to the whole room, so their order relative to each other isn't guaranteed.
+    pub async fn expect_unordered(&mut self, events: Vec<SocketEvent>) {
+        let mut expected: Vec<Value> = events.into_iter().map(|event| normalize(serde_json::to_value(event).unwrap())).collect();
+
+        while !expected.is_empty() {
+            let event = self.receive().await;
+            let index = expected.iter().position(|expected| expected == &event)
+                .unwrap_or_else(|| panic!("Received unexpected event {event}, expected one of {expected:?}"));
+            expected.remove(index);
+        }
+    }
+
+    // Like expect_unordered, but also expects one event of the given kind whose content can't be known in advance, e.g.
+    // a newly drawn card. That event is returned.
+    pub async fn expect_unordered_with(&mut self, events: Vec<SocketEvent>, kind: &str) -> Value {
+        let mut expected: Vec<Value> = events.into_iter().map(|event| normalize(serde_json::to_value(event).unwrap())).collect();
+        let mut other = None;
+
+        while !expected.is_empty() || other.is_none() {
+            let event = self.receive().await;
+            match expected.iter().position(|expected| expected == &event) {
+                Some(index) => {
+                    expected.remove(index);
+                }
+                None if other.is_none() && event_kind(&event) == kind => other = Some(event),
+                None => panic!("Received unexpected event {event}, expected {kind} or one of {expected:?}"),
+            }
+        }
+
+        other.unwrap()
+    }
+
+    pub async fn expect_nothing(&mut self) {
+        if let Ok(Some(message)) = tokio::time::timeout(SILENCE_PERIOD, self.socket.next()).await {
+            panic!("Expected no more events, got {message:?}");
+        }
+    }
+
+    // Returns the close code once the server closes the connection
+    pub async fn expect_close(&mut self) -> u16 {
+        loop {
+            let message = tokio::time::timeout(RECEIVE_TIMEOUT, self.socket.next()).await
+                .expect("Timed out waiting for the connection to close");
+
+            match message {
+                Some(Ok(Message::Close(frame))) => return frame.map(|frame| frame.code.into()).unwrap_or_default(),
+                Some(Ok(Message::Text(text))) => panic!("Expected the connection to close, got {text}"),
+                Some(Ok(_)) => {}
+                other => panic!("Expected a close frame, got {other:?}"),
+            }
+        }
+    }
+}
+
+// The name of a room event, or of the socket event for anything else
+pub fn event_kind(event: &Value) -> &str {
+    match event["event"].as_str() {
+        Some("RoomEvent") => event["detail"]["event"].as_str().unwrap_or_default(),
+        kind => kind.unwrap_or_default(),
+    }
+}
+
+fn normalize(mut event: Value) -> Value {
+    remove_key(&mut event, "joinedAt");
+    event
+}
+
+fn remove_key(value: &mut Value, key: &str) {
+    match value {
+        Value::Object(map) => {
+            map.remove(key);
+            map.values_mut().for_each(|value| remove_key(value, key));
+        }
+        Value::Array(values) => values.iter_mut().for_each(|value| remove_key(value, key)),
+        _ => {}
+    }
+}
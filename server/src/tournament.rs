@@ -0,0 +1,364 @@
+pub mod bracket;
+pub mod routes;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use ts_rs::TS;
+use tokio::sync::{mpsc, RwLock};
+use uuid::Uuid;
+use crate::AppState;
+use crate::game::rules::RuleVariant;
+use crate::game::series::{Series, SeriesSettings};
+use crate::persistence::TournamentUpdateSender;
+use crate::socket::messages::RoomEvent;
+use crate::socket::room_settings::{RoomSettings, RoomVisibility};
+use crate::socket::room_store::{generate_token, RoomResult, RoomResultSender, SocketRoomStore};
+use crate::tournament::bracket::{Bracket, Standing, TournamentFormat};
+
+#[derive(Serialize, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(tag = "code", content = "detail")]
+pub enum TournamentError {
+    TournamentNotFound(Uuid),
+    TournamentStarted,
+    NotEnoughPlayers,
+    DuplicatePlayer,
+    InvalidRoundCount,
+    InvalidSeriesLength,
+    MatchNotFound,
+    MatchCompleted,
+    PlayerNotInMatch,
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TournamentSettings {
+    pub name: String,
+    pub format: TournamentFormat,
+    pub players: Vec<String>,
+    pub series: Option<SeriesSettings>,
+    pub max_spectators: Option<usize>,
+    #[serde(default)]
+    pub rules: RuleVariant,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tournament {
+    pub id: Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub series: Option<SeriesSettings>,
+    pub max_spectators: Option<usize>,
+    pub rules: RuleVariant,
+    pub bracket: Bracket,
+    // Players need their token to join their match rooms, which are reserved for them by username only otherwise
+    #[serde(default)]
+    pub player_tokens: HashMap<String, String>,
+}
+
+#[derive(Clone, Debug, Serialize, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct TournamentSummary {
+    pub id: Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub started: bool,
+    pub completed: bool,
+    pub winner: Option<String>,
+    pub bracket: Bracket,
+    pub standings: Vec<Standing>,
+}
+
+// Only returned to admins, who pass the room codes and tokens on to the players
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminTournamentSummary {
+    #[serde(flatten)]
+    pub summary: TournamentSummary,
+    pub player_tokens: HashMap<String, String>,
+}
+
+impl Tournament {
+    // Leaves out the room codes of the matches, which are only handed to their players along with their tokens
+    pub fn summary(&self) -> TournamentSummary {
+        let mut bracket = self.bracket.clone();
+        for bracket_match in bracket.rounds.iter_mut().flatten() {
+            bracket_match.room_code = None;
+        }
+
+        TournamentSummary { bracket, ..self.admin_summary().summary }
+    }
+
+    pub fn admin_summary(&self) -> AdminTournamentSummary {
+        AdminTournamentSummary {
+            summary: TournamentSummary {
+                id: self.id,
+                name: self.name.clone(),
+                created_at: self.created_at,
+                started: !self.bracket.rounds.is_empty(),
+                completed: self.bracket.completed(),
+                winner: self.bracket.winner(),
+                bracket: self.bracket.clone(),
+                standings: self.bracket.standings(),
+            },
+            player_tokens: self.player_tokens.clone(),
+        }
+    }
+
+    // Match rooms are kept out of room listings; only the paired players may play in them anyway.
+    fn room_settings(&self) -> RoomSettings {
+        RoomSettings {
+            visibility: RoomVisibility::Private,
+            password: None,
+            max_spectators: self.max_spectators,
+            rules: self.rules,
+        }
+    }
+
+    fn room_codes(&self) -> impl Iterator<Item = &String> {
+        self.bracket.rounds.iter().flatten().filter_map(|bracket_match| bracket_match.room_code.as_ref())
+    }
+}
+
+struct MatchLocation {
+    tournament_id: Uuid,
+    round: usize,
+    match_index: usize,
+}
+
+pub struct TournamentStore {
+    tournaments: HashMap<Uuid, Tournament>,
+    match_rooms: HashMap<String, MatchLocation>,
+    result_sender: RoomResultSender,
+    update_sender: Option<TournamentUpdateSender>,
+}
+
+impl TournamentStore {
+    pub fn new(result_sender: RoomResultSender, update_sender: Option<TournamentUpdateSender>) -> Self {
+        Self {
+            tournaments: HashMap::new(),
+            match_rooms: HashMap::new(),
+            result_sender,
+            update_sender,
+        }
+    }
+
+    // Restores tournaments saved by a previous run. Rooms have to be restored first, so that the rooms of
+    // unfinished matches can report their results again.
+    pub fn restore(&mut self, tournaments: Vec<Tournament>, room_store: &mut SocketRoomStore) {
+        for mut tournament in tournaments {
+            // Tournaments saved before players had tokens get them for the rooms of their upcoming matches
+            for player in &tournament.bracket.players {
+                tournament.player_tokens.entry(player.clone()).or_insert_with(generate_token);
+            }
+
+            for (round, matches) in tournament.bracket.rounds.iter().enumerate() {
+                for (match_index, bracket_match) in matches.iter().enumerate().filter(|(_, bracket_match)| bracket_match.winner.is_none()) {
+                    let Some(room_code) = &bracket_match.room_code else {
+                        continue;
+                    };
+
+                    if !room_store.set_result_sender(room_code, self.result_sender.clone()) {
+                        log::warn!("Room {room_code} of tournament {} could not be restored", tournament.id);
+                        continue;
+                    }
+
+                    self.match_rooms.insert(room_code.clone(), MatchLocation { tournament_id: tournament.id, round, match_index });
+                }
+            }
+
+            self.tournaments.insert(tournament.id, tournament);
+        }
+
+        log::info!("Restored {} tournament(s)", self.tournaments.len());
+    }
+
+    pub fn create(&mut self, settings: TournamentSettings) -> Result<&Tournament, TournamentError> {
+        if settings.series.as_ref().is_some_and(|series| Series::new(series.clone()).is_none()) {
+            return Err(TournamentError::InvalidSeriesLength);
+        }
+
+        let player_tokens = settings.players.iter().map(|player| (player.clone(), generate_token())).collect();
+        let tournament = Tournament {
+            id: Uuid::new_v4(),
+            name: settings.name,
+            created_at: Utc::now(),
+            series: settings.series,
+            max_spectators: settings.max_spectators,
+            rules: settings.rules,
+            bracket: Bracket::new(settings.format, settings.players)?,
+            player_tokens,
+        };
+
+        log::debug!("Creating tournament {} with {} players", tournament.id, tournament.bracket.players.len());
+        let id = tournament.id;
+        self.tournaments.insert(id, tournament);
+        self.persist(id);
+        Ok(&self.tournaments[&id])
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<&Tournament> {
+        self.tournaments.get(&id)
+    }
+
+    pub async fn start(&mut self, id: Uuid, room_store: &RwLock<SocketRoomStore>) -> Result<&Tournament, TournamentError> {
+        let tournament = self.tournaments.get(&id).ok_or(TournamentError::TournamentNotFound(id))?;
+        if !tournament.bracket.rounds.is_empty() {
+            return Err(TournamentError::TournamentStarted);
+        }
+
+        log::debug!("Starting tournament {id}");
+        self.advance(id, room_store).await;
+        Ok(&self.tournaments[&id])
+    }
+
+    pub async fn record_result(&mut self, result: RoomResult, room_store: &RwLock<SocketRoomStore>) {
+        let Some(location) = self.match_rooms.remove(&result.room_code) else {
+            return;
+        };
+
+        let Some(tournament) = self.tournaments.get_mut(&location.tournament_id) else {
+            return;
+        };
+
+        log::debug!("{} beats {} in match {} of round {} in tournament {}", result.winner, result.loser, location.match_index, location.round, tournament.id);
+        if let Err(err) = tournament.bracket.record_result(location.round, location.match_index, &result.winner) {
+            log::warn!("Failed to record result of room {} in tournament {}: {err:?}", result.room_code, tournament.id);
+            return;
+        }
+
+        self.advance(location.tournament_id, room_store).await;
+    }
+
+    // Starts as many rounds as possible, creating rooms for every match that is not a bye, then notifies
+    // the tournament's rooms of the new state. The room store is only locked to insert each room once its code was
+    // claimed.
+    async fn advance(&mut self, id: Uuid, room_store: &RwLock<SocketRoomStore>) {
+        let Some(tournament) = self.tournaments.get_mut(&id) else {
+            return;
+        };
+
+        let room_settings = tournament.room_settings();
+        while let Some(round) = tournament.bracket.start_next_round() {
+            for (match_index, bracket_match) in tournament.bracket.rounds[round].iter_mut().enumerate()
+                .filter(|(_, bracket_match)| !bracket_match.is_bye())
+            {
+                let join_tokens = bracket_match.players.iter()
+                    .filter_map(|player| Some((player.clone(), tournament.player_tokens.get(player)?.clone())))
+                    .collect();
+                let Some(room_code) = SocketRoomStore::claim_new_room_code(room_store).await else {
+                    log::error!("Failed to create a room for match {match_index} of round {round} in tournament {id}");
+                    continue;
+                };
+                let created = room_store.write().await.create_reserved(
+                    room_code.clone(),
+                    bracket_match.players.clone(),
+                    join_tokens,
+                    tournament.series.clone().and_then(Series::new),
+                    room_settings.clone(),
+                    self.result_sender.clone());
+                if created.is_err() {
+                    log::error!("Room {room_code} for match {match_index} of round {round} in tournament {id} was taken in the meantime");
+                    continue;
+                }
+
+                self.match_rooms.insert(room_code.clone(), MatchLocation { tournament_id: id, round, match_index });
+                bracket_match.room_code = Some(room_code);
+            }
+        }
+
+        if tournament.bracket.completed() {
+            log::info!("Tournament {id} has been won by {}", tournament.bracket.winner().unwrap_or_default());
+        }
+
+        let summary = tournament.summary();
+        let room_store = room_store.read().await;
+        for room in tournament.room_codes().filter_map(|room_code| room_store.get(room_code)) {
+            room.sender.send(RoomEvent::TournamentUpdate(summary.clone())).ok();
+        }
+
+        self.persist(id);
+    }
+
+    // Closes the channel to the persistent store, letting its writer finish once it has saved everything
+    pub fn shut_down(&mut self) {
+        self.update_sender = None;
+    }
+
+    fn persist(&self, id: Uuid) {
+        if let (Some(update_sender), Some(tournament)) = (&self.update_sender, self.tournaments.get(&id)) {
+            update_sender.send(Box::new(tournament.clone())).ok();
+        }
+    }
+}
+
+pub async fn handle_room_results(state: Arc<AppState>, mut receiver: mpsc::UnboundedReceiver<RoomResult>) {
+    while let Some(result) = receiver.recv().await {
+        let mut tournament_store = state.tournament_store.write().await;
+        tournament_store.record_result(result, &state.room_store).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::socket::close_code::SocketCloseCode;
+    use crate::socket::room_store::JoinRequest;
+    use super::*;
+
+    fn settings() -> TournamentSettings {
+        TournamentSettings {
+            name: "Weekly".to_string(),
+            format: TournamentFormat::SingleElimination,
+            players: vec!("a".to_string(), "b".to_string()),
+            series: None,
+            max_spectators: None,
+            rules: RuleVariant::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn restored_tournaments_keep_collecting_results() {
+        let (update_sender, mut update_receiver) = mpsc::unbounded_channel();
+        let room_store = RwLock::new(SocketRoomStore::default());
+        let mut tournament_store = TournamentStore::new(mpsc::unbounded_channel().0, Some(update_sender));
+        let id = tournament_store.create(settings()).unwrap().id;
+        tournament_store.start(id, &room_store).await.unwrap();
+        let room_code = tournament_store.get(id).unwrap().bracket.rounds[0][0].room_code.clone().unwrap();
+
+        let mut saved_tournament = None;
+        while let Ok(tournament) = update_receiver.try_recv() {
+            saved_tournament = Some(serde_json::to_string(&tournament).unwrap());
+        }
+        let mut restored_room_store = SocketRoomStore::default();
+        restored_room_store.restore(vec!(room_store.read().await.get(&room_code).unwrap().snapshot())).await;
+        let mut restored_store = TournamentStore::new(mpsc::unbounded_channel().0, None);
+        restored_store.restore(vec!(serde_json::from_str(&saved_tournament.unwrap()).unwrap()), &mut restored_room_store);
+        assert!(restored_store.match_rooms.contains_key(&room_code));
+
+        let result = RoomResult { room_code, winner: "b".to_string(), loser: "a".to_string() };
+        restored_store.record_result(result, &RwLock::new(restored_room_store)).await;
+
+        assert_eq!(Some("b".to_string()), restored_store.get(id).unwrap().bracket.winner());
+    }
+
+    #[tokio::test]
+    async fn match_rooms_require_player_tokens() {
+        let room_store = RwLock::new(SocketRoomStore::default());
+        let mut tournament_store = TournamentStore::new(mpsc::unbounded_channel().0, None);
+        let id = tournament_store.create(settings()).unwrap().id;
+        let tournament = tournament_store.start(id, &room_store).await.unwrap();
+        assert_eq!(None, tournament.summary().bracket.rounds[0][0].room_code);
+        let AdminTournamentSummary { summary, player_tokens } = tournament.admin_summary();
+        let room_code = summary.bracket.rounds[0][0].room_code.clone().unwrap();
+
+        let mut room_store = room_store.into_inner();
+        let mut join = |request| room_store.get_and_join_if_exists(&room_code, Uuid::new_v4(), request, mpsc::channel(1).0);
+        assert!(matches!(join(JoinRequest::named("a")), Err(SocketCloseCode::InvalidToken)));
+        assert!(matches!(join(JoinRequest { token: Some(&player_tokens["b"]), ..JoinRequest::named("a") }), Err(SocketCloseCode::InvalidToken)));
+        assert!(join(JoinRequest { token: Some(&player_tokens["a"]), ..JoinRequest::named("a") }).is_ok());
+        assert!(join(JoinRequest::named("spectator")).is_ok());
+    }
+}
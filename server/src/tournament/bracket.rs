@@ -0,0 +1,376 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use ts_rs::TS;
+use crate::tournament::TournamentError;
+
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, JsonSchema, TS)]
+#[serde(tag = "type")]
+pub enum TournamentFormat {
+    SingleElimination,
+    Swiss { rounds: usize },
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct BracketMatch {
+    // A match with only one player is a bye, which that player wins automatically.
+    pub players: Vec<String>,
+    pub winner: Option<String>,
+    pub room_code: Option<String>,
+}
+
+impl BracketMatch {
+    fn new(players: Vec<String>) -> Self {
+        let winner = if players.len() == 1 { players.first().cloned() } else { None };
+
+        Self {
+            players,
+            winner,
+            room_code: None,
+        }
+    }
+
+    pub fn is_bye(&self) -> bool {
+        self.players.len() == 1
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Eq, PartialEq, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct Standing {
+    pub player: String,
+    pub wins: usize,
+    pub buchholz: usize,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct Bracket {
+    pub format: TournamentFormat,
+    pub players: Vec<String>,
+    pub rounds: Vec<Vec<BracketMatch>>,
+}
+
+impl Bracket {
+    pub fn new(format: TournamentFormat, players: Vec<String>) -> Result<Self, TournamentError> {
+        if players.len() < 2 {
+            return Err(TournamentError::NotEnoughPlayers);
+        }
+
+        if !players.iter().all_unique() {
+            return Err(TournamentError::DuplicatePlayer);
+        }
+
+        if let TournamentFormat::Swiss { rounds } = format {
+            if rounds == 0 || rounds >= players.len() {
+                return Err(TournamentError::InvalidRoundCount);
+            }
+        }
+
+        Ok(Self {
+            format,
+            players,
+            rounds: Vec::new(),
+        })
+    }
+
+    pub fn round_count(&self) -> usize {
+        match self.format {
+            TournamentFormat::SingleElimination => self.players.len().next_power_of_two().trailing_zeros() as usize,
+            TournamentFormat::Swiss { rounds } => rounds,
+        }
+    }
+
+    pub fn current_round_completed(&self) -> bool {
+        self.rounds.last().is_none_or(|round| round.iter().all(|bracket_match| bracket_match.winner.is_some()))
+    }
+
+    pub fn completed(&self) -> bool {
+        self.rounds.len() == self.round_count() && self.current_round_completed()
+    }
+
+    // Creates the pairings for the next round. Returns the index of the new round, or None if the current round is
+    // still being played or the bracket is completed.
+    pub fn start_next_round(&mut self) -> Option<usize> {
+        if !self.current_round_completed() || self.completed() {
+            return None;
+        }
+
+        let round = match self.format {
+            TournamentFormat::SingleElimination => self.next_elimination_round(),
+            TournamentFormat::Swiss { .. } => self.next_swiss_round(),
+        };
+        self.rounds.push(round);
+        Some(self.rounds.len() - 1)
+    }
+
+    pub fn record_result(&mut self, round: usize, match_index: usize, winner: &str) -> Result<(), TournamentError> {
+        let bracket_match = self.rounds.get_mut(round)
+            .and_then(|round| round.get_mut(match_index))
+            .ok_or(TournamentError::MatchNotFound)?;
+
+        if bracket_match.winner.is_some() {
+            Err(TournamentError::MatchCompleted)
+        } else if !bracket_match.players.iter().any(|player| player == winner) {
+            Err(TournamentError::PlayerNotInMatch)
+        } else {
+            bracket_match.winner = Some(winner.to_owned());
+            Ok(())
+        }
+    }
+
+    pub fn winner(&self) -> Option<String> {
+        if !self.completed() {
+            return None;
+        }
+
+        match self.format {
+            TournamentFormat::SingleElimination => self.rounds.last()
+                .and_then(|round| round.first())
+                .and_then(|final_match| final_match.winner.clone()),
+            TournamentFormat::Swiss { .. } => self.standings().first().map(|standing| standing.player.clone()),
+        }
+    }
+
+    // Players ordered by wins, using the sum of their opponents' wins (Buchholz score) as a tiebreaker.
+    pub fn standings(&self) -> Vec<Standing> {
+        let wins = self.wins();
+        let opponents = self.opponents();
+
+        self.players.iter()
+            .map(|player| Standing {
+                player: player.clone(),
+                wins: wins[player],
+                buchholz: opponents[player].iter().map(|opponent| wins[*opponent]).sum(),
+            })
+            .sorted_by_key(|standing| (Reverse(standing.wins), Reverse(standing.buchholz)))
+            .collect()
+    }
+
+    fn wins(&self) -> HashMap<&String, usize> {
+        let mut result: HashMap<&String, usize> = self.players.iter().map(|player| (player, 0)).collect();
+
+        for winner in self.rounds.iter().flatten().filter_map(|bracket_match| bracket_match.winner.as_ref()) {
+            *result.entry(winner).or_insert(0) += 1;
+        }
+
+        result
+    }
+
+    fn opponents(&self) -> HashMap<&String, Vec<&String>> {
+        let mut result: HashMap<&String, Vec<&String>> = self.players.iter().map(|player| (player, Vec::new())).collect();
+
+        for bracket_match in self.rounds.iter().flatten().filter(|bracket_match| !bracket_match.is_bye()) {
+            for (player, opponent) in bracket_match.players.iter().tuple_combinations() {
+                result.entry(player).or_default().push(opponent);
+                result.entry(opponent).or_default().push(player);
+            }
+        }
+
+        result
+    }
+
+    fn next_elimination_round(&self) -> Vec<BracketMatch> {
+        match self.rounds.last() {
+            None => {
+                let bracket_size = self.players.len().next_power_of_two();
+                Self::seed_order(bracket_size).into_iter()
+                    .tuples()
+                    .map(|(seed_a, seed_b)| BracketMatch::new([seed_a, seed_b].into_iter()
+                        .filter_map(|seed| self.players.get(seed).cloned())
+                        .collect()))
+                    .collect()
+            }
+            Some(previous_round) => previous_round.iter()
+                .filter_map(|bracket_match| bracket_match.winner.clone())
+                .chunks(2).into_iter()
+                .map(|players| BracketMatch::new(players.collect()))
+                .collect(),
+        }
+    }
+
+    // Orders seeds so the highest seeds can only meet in the latest rounds, e.g. [0, 7, 3, 4, 1, 6, 2, 5] for 8
+    // players. Seeds beyond the number of players become byes for the highest seeds.
+    fn seed_order(bracket_size: usize) -> Vec<usize> {
+        let mut result = vec!(0);
+
+        while result.len() < bracket_size {
+            let round_size = result.len() * 2;
+            result = result.into_iter()
+                .flat_map(|seed| [seed, round_size - 1 - seed])
+                .collect();
+        }
+
+        result
+    }
+
+    fn next_swiss_round(&self) -> Vec<BracketMatch> {
+        let mut unpaired: Vec<String> = self.standings().into_iter().map(|standing| standing.player).collect();
+        let bye = if unpaired.len() % 2 == 1 {
+            let previous_byes: HashSet<&String> = self.rounds.iter().flatten()
+                .filter(|bracket_match| bracket_match.is_bye())
+                .flat_map(|bracket_match| bracket_match.players.iter())
+                .collect();
+            let bye_index = unpaired.iter().rposition(|player| !previous_byes.contains(player))
+                .unwrap_or(unpaired.len() - 1);
+
+            Some(unpaired.remove(bye_index))
+        } else {
+            None
+        };
+
+        let opponents = self.opponents();
+        let mut result = Vec::new();
+        while !unpaired.is_empty() {
+            let player = unpaired.remove(0);
+            // Pair with the highest ranked player that hasn't been faced yet, allowing rematches if there are none
+            let opponent_index = unpaired.iter()
+                .position(|opponent| !opponents[&player].contains(&opponent))
+                .unwrap_or(0);
+            let opponent = unpaired.remove(opponent_index);
+
+            result.push(BracketMatch::new(vec!(player, opponent)));
+        }
+
+        if let Some(player) = bye {
+            result.push(BracketMatch::new(vec!(player)));
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn players(count: usize) -> Vec<String> {
+        (1..=count).map(|index| format!("player_{index}")).collect()
+    }
+
+    fn play_round(bracket: &mut Bracket, pick_winner: impl Fn(&BracketMatch) -> String) {
+        let round = bracket.rounds.len() - 1;
+        let results: Vec<(usize, String)> = bracket.rounds[round].iter().enumerate()
+            .filter(|(_, bracket_match)| bracket_match.winner.is_none())
+            .map(|(index, bracket_match)| (index, pick_winner(bracket_match)))
+            .collect();
+
+        for (index, winner) in results {
+            bracket.record_result(round, index, &winner).unwrap();
+        }
+    }
+
+    fn match_players(round: &[BracketMatch]) -> Vec<Vec<&str>> {
+        round.iter().map(|bracket_match| bracket_match.players.iter().map(|player| player.as_str()).collect()).collect()
+    }
+
+    #[test]
+    fn new_validates_players() {
+        assert_eq!(TournamentError::NotEnoughPlayers, Bracket::new(TournamentFormat::SingleElimination, players(1)).unwrap_err());
+        assert_eq!(
+            TournamentError::DuplicatePlayer,
+            Bracket::new(TournamentFormat::SingleElimination, vec!("a".to_string(), "a".to_string())).unwrap_err());
+        assert_eq!(TournamentError::InvalidRoundCount, Bracket::new(TournamentFormat::Swiss { rounds: 4 }, players(4)).unwrap_err());
+        assert_eq!(TournamentError::InvalidRoundCount, Bracket::new(TournamentFormat::Swiss { rounds: 0 }, players(4)).unwrap_err());
+    }
+
+    #[test]
+    fn seed_order() {
+        assert_eq!(vec!(0, 1), Bracket::seed_order(2));
+        assert_eq!(vec!(0, 3, 1, 2), Bracket::seed_order(4));
+        assert_eq!(vec!(0, 7, 3, 4, 1, 6, 2, 5), Bracket::seed_order(8));
+    }
+
+    #[test]
+    fn single_elimination_first_round_with_byes() {
+        let mut bracket = Bracket::new(TournamentFormat::SingleElimination, players(6)).unwrap();
+
+        assert_eq!(Some(0), bracket.start_next_round());
+        assert_eq!(3, bracket.round_count());
+        assert_eq!(match_players(&bracket.rounds[0]), vec!(
+            vec!("player_1"),
+            vec!("player_4", "player_5"),
+            vec!("player_2"),
+            vec!("player_3", "player_6"),
+        ));
+        assert_eq!(Some("player_1".to_string()), bracket.rounds[0][0].winner);
+        assert_eq!(None, bracket.rounds[0][1].winner);
+    }
+
+    #[test]
+    fn single_elimination_full_bracket() {
+        let mut bracket = Bracket::new(TournamentFormat::SingleElimination, players(4)).unwrap();
+        bracket.start_next_round();
+        assert_eq!(None, bracket.start_next_round());
+
+        play_round(&mut bracket, |bracket_match| bracket_match.players[1].clone());
+        assert_eq!(Some(1), bracket.start_next_round());
+        assert_eq!(match_players(&bracket.rounds[1]), vec!(vec!("player_4", "player_3")));
+        assert_eq!(None, bracket.winner());
+
+        play_round(&mut bracket, |bracket_match| bracket_match.players[0].clone());
+        assert!(bracket.completed());
+        assert_eq!(None, bracket.start_next_round());
+        assert_eq!(Some("player_4".to_string()), bracket.winner());
+    }
+
+    #[test]
+    fn record_result_errors() {
+        let mut bracket = Bracket::new(TournamentFormat::SingleElimination, players(3)).unwrap();
+        bracket.start_next_round();
+
+        assert_eq!(Err(TournamentError::MatchNotFound), bracket.record_result(1, 0, "player_1"));
+        assert_eq!(Err(TournamentError::MatchCompleted), bracket.record_result(0, 0, "player_1"));
+        assert_eq!(Err(TournamentError::PlayerNotInMatch), bracket.record_result(0, 1, "player_1"));
+        assert_eq!(Ok(()), bracket.record_result(0, 1, "player_3"));
+    }
+
+    #[test]
+    fn swiss_avoids_rematches() {
+        let mut bracket = Bracket::new(TournamentFormat::Swiss { rounds: 3 }, players(4)).unwrap();
+        bracket.start_next_round();
+        assert_eq!(match_players(&bracket.rounds[0]), vec!(
+            vec!("player_1", "player_2"),
+            vec!("player_3", "player_4"),
+        ));
+
+        play_round(&mut bracket, |bracket_match| bracket_match.players[0].clone());
+        bracket.start_next_round();
+        assert_eq!(match_players(&bracket.rounds[1]), vec!(
+            vec!("player_1", "player_3"),
+            vec!("player_2", "player_4"),
+        ));
+
+        play_round(&mut bracket, |bracket_match| bracket_match.players[0].clone());
+        bracket.start_next_round();
+        assert_eq!(match_players(&bracket.rounds[2]), vec!(
+            vec!("player_1", "player_4"),
+            vec!("player_2", "player_3"),
+        ));
+
+        play_round(&mut bracket, |bracket_match| bracket_match.players[0].clone());
+        assert!(bracket.completed());
+        assert_eq!(Some("player_1".to_string()), bracket.winner());
+        assert_eq!(bracket.standings()[0], Standing { player: "player_1".to_string(), wins: 3, buchholz: 3 });
+    }
+
+    #[test]
+    fn swiss_assigns_byes_to_lowest_ranked_player_once() {
+        let mut bracket = Bracket::new(TournamentFormat::Swiss { rounds: 2 }, players(3)).unwrap();
+        bracket.start_next_round();
+        assert_eq!(match_players(&bracket.rounds[0]), vec!(
+            vec!("player_1", "player_2"),
+            vec!("player_3"),
+        ));
+
+        play_round(&mut bracket, |bracket_match| bracket_match.players[1].clone());
+        bracket.start_next_round();
+        assert_eq!(match_players(&bracket.rounds[1]), vec!(
+            vec!("player_2", "player_3"),
+            vec!("player_1"),
+        ));
+    }
+}
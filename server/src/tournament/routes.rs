@@ -0,0 +1,49 @@
+use std::sync::Arc;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use axum::response::{IntoResponse, Response};
+use uuid::Uuid;
+use crate::AppState;
+use crate::api::admin::AdminAuth;
+use crate::tournament::{AdminTournamentSummary, TournamentError, TournamentSettings, TournamentSummary};
+
+type TournamentResponse = Result<Json<TournamentSummary>, TournamentError>;
+type AdminTournamentResponse = Result<Json<AdminTournamentSummary>, TournamentError>;
+
+impl IntoResponse for TournamentError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            TournamentError::TournamentNotFound(_) => StatusCode::NOT_FOUND,
+            TournamentError::TournamentStarted => StatusCode::CONFLICT,
+            _ => StatusCode::BAD_REQUEST,
+        };
+
+        (status, Json(self)).into_response()
+    }
+}
+
+pub async fn create_tournament(_: AdminAuth, State(state): State<Arc<AppState>>, Json(settings): Json<TournamentSettings>) -> AdminTournamentResponse {
+    let mut tournament_store = state.tournament_store.write().await;
+    tournament_store.create(settings).map(|tournament| Json(tournament.admin_summary()))
+}
+
+pub async fn get_tournament(State(state): State<Arc<AppState>>, Path(id): Path<Uuid>) -> TournamentResponse {
+    let tournament_store = state.tournament_store.read().await;
+    tournament_store.get(id)
+        .map(|tournament| Json(tournament.summary()))
+        .ok_or(TournamentError::TournamentNotFound(id))
+}
+
+pub async fn get_admin_tournament(_: AdminAuth, State(state): State<Arc<AppState>>, Path(id): Path<Uuid>) -> AdminTournamentResponse {
+    let tournament_store = state.tournament_store.read().await;
+    tournament_store.get(id)
+        .map(|tournament| Json(tournament.admin_summary()))
+        .ok_or(TournamentError::TournamentNotFound(id))
+}
+
+pub async fn start_tournament(_: AdminAuth, State(state): State<Arc<AppState>>, Path(id): Path<Uuid>) -> AdminTournamentResponse {
+    // The room store is only locked while the tournament store is, never the other way around
+    let mut tournament_store = state.tournament_store.write().await;
+    tournament_store.start(id, &state.room_store).await.map(|tournament| Json(tournament.admin_summary()))
+}
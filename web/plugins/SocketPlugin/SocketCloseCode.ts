@@ -1,3 +1,16 @@
 export enum SocketCloseCode {
-    ROOM_NOT_FOUND = 4000
+    ROOM_NOT_FOUND = 4000,
+    INVALID_USERNAME = 4001,
+    ROOM_EXPIRED = 4002,
+    WRONG_PASSWORD = 4003,
+    ROOM_FULL = 4004,
+    KICKED = 4005,
+    BANNED = 4006,
+    ABUSIVE = 4007,
+    TOO_MANY_CONNECTIONS = 4008,
+    UNSUPPORTED_PROTOCOL = 4009,
+    SERVER_RESTARTING = 4010,
+    ROOM_CLOSED = 4011,
+    ROOM_UNAVAILABLE = 4012,
+    INVALID_TOKEN = 4013
 }
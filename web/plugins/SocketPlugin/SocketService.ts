@@ -1,12 +1,14 @@
-import { AnySocketMessage } from '~/types/socket/SocketEvent';
 import { useRoomStore } from '~/stores/RoomStore';
-import { AnyRoomEvent } from '~/types/socket/RoomEvent';
-import { SocketActionMap } from '~/types/socket/SocketAction';
+import { ActionArgs } from '~/types/socket/EventHelper';
 import { useGameBoardStore } from '~/stores/GameBoardStore';
 import { useDeckStore } from '~/stores/DeckStore';
 import { navigateTo } from '#imports';
 import { useCurrentMoveStore } from '~/stores/CurrentMoveStore';
 import { useMoveStore } from '~/stores/MoveStore';
+import { PROTOCOL_VERSION, RoomEvent, SocketAction, SocketEvent } from '~/types/socket/generated/Protocol';
+
+// Lets players reclaim their seat after the server restarts, see the server's README
+const reconnectTokenKey = (roomCode: string) => `reconnectToken:${roomCode}`;
 
 export class SocketService {
     private wsPingInterval: number | undefined;
@@ -22,7 +24,7 @@ export class SocketService {
         return this.ws != null && (this.ws?.readyState === WebSocket.OPEN || this.ws?.readyState === WebSocket.CONNECTING);
     }
 
-    async connect(roomCode: string | undefined, username: string): Promise<string> {
+    async connect(roomCode: string | undefined, username: string, password?: string, token?: string): Promise<string> {
         if (this.isOpen()) {
             throw new Error('Websocket is already open');
         }
@@ -34,6 +36,15 @@ export class SocketService {
                     url.searchParams.set('room', roomCode);
                 }
                 url.searchParams.set('username', username);
+                url.searchParams.set('version', PROTOCOL_VERSION.toString());
+                if (password != null) {
+                    url.searchParams.set('password', password);
+                }
+                // Tournament players are given their token, everyone else reuses the one of their last connection
+                const joinToken = token ?? (roomCode != null ? sessionStorage.getItem(reconnectTokenKey(roomCode)) : null);
+                if (joinToken != null) {
+                    url.searchParams.set('token', joinToken);
+                }
 
                 this.ws = new WebSocket(url.toString());
             } catch (e) {
@@ -50,6 +61,7 @@ export class SocketService {
 
                 if (parsed != null && parsed.event === 'Welcome') {
                     removeListeners();
+                    sessionStorage.setItem(reconnectTokenKey(parsed.detail.roomCode), parsed.detail.reconnectToken);
                     resolve(parsed.detail.roomCode);
                 }
             };
@@ -106,7 +118,7 @@ export class SocketService {
         }, 45 * 1000);
     }
 
-    private parseSocketMessage(msg: string): AnySocketMessage | null {
+    private parseSocketMessage(msg: string): SocketEvent | null {
         try {
             return JSON.parse(msg);
         } catch (e) {
@@ -115,7 +127,7 @@ export class SocketService {
         }
     }
 
-    private handleSocketMessage(msg: AnySocketMessage) {
+    private handleSocketMessage(msg: SocketEvent) {
         switch (msg.event) {
             case 'Welcome':
                 useRoomStore().joinRoom(msg.detail);
@@ -125,7 +137,7 @@ export class SocketService {
                 this.handleRoomEvent(msg.detail);
                 break;
             case 'Error':
-                if (msg.detail.detail != null) {
+                if ('detail' in msg.detail) {
                     console.error(`Received error "${msg.detail.code}":`, msg.detail.detail);
                 } else {
                     console.error(`Received error "${msg.detail.code}"`);
@@ -142,7 +154,7 @@ export class SocketService {
         }
     }
 
-    private async handleRoomEvent(event: AnyRoomEvent) {
+    private async handleRoomEvent(event: RoomEvent) {
         switch (event.event) {
             case 'UserJoin':
             case 'UserUpdate':
@@ -213,7 +225,7 @@ export class SocketService {
         }
     }
 
-    send<K extends keyof SocketActionMap, D = SocketActionMap[K]>(action: K, args?: D): void {
+    send<K extends SocketAction['action']>(action: K, args?: ActionArgs<K>): void {
         if (this.ws == null || this.ws.readyState !== WebSocket.OPEN) {
             throw new Error('Websocket is not open, cannot send message');
         }
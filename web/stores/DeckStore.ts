@@ -2,7 +2,7 @@ import { defineStore } from 'pinia';
 import { useRoomStore } from '~/stores/RoomStore';
 import { PlayerTeam, TeamMap } from '~/types/PlayerTeam';
 import { useDeckListStore } from '~/stores/DeckListStore';
-import { PlayerMove } from '~/types/socket/SocketCommon';
+import { PlayerMove } from '~/types/socket/generated/Protocol';
 
 interface DeckStore {
     availableCards: string[]
@@ -17,7 +17,7 @@ import { CardSquareType } from '~/types/CardSquareType';
 import cloneDeep from 'lodash/cloneDeep';
 import { PlayerTeam } from '~/types/PlayerTeam';
 import { useRoomStore } from '~/stores/RoomStore';
-import { PlaceCardMove, PlayerMove } from '~/types/socket/SocketCommon';
+import { PlayerMove } from '~/types/socket/generated/Protocol';
 import { isFillSquare, isSpecialSquare, mapSquareFromCardSquare } from '~/helpers/SquareHelper';
 import { activateSpecialSquares } from '~/helpers/BoardHelper';
 import { CardMap } from '~/helpers/Cards';
@@ -211,7 +211,7 @@ export const useGameBoardStore = defineStore('gameBoard', {
             const boardUpdates = fill2D(this.boardSize.width, this.boardSize.height, MST.EMPTY);
 
             const movesWithCards = (Object.entries(moves)
-                .filter(([, move]) => move.type === 'PlaceCard') as Array<[PlayerTeam, PlaceCardMove]>)
+                .filter(([, move]) => move.type === 'PlaceCard') as Array<[PlayerTeam, Extract<PlayerMove, { type: 'PlaceCard' }>]>)
                 .map(([team, move]) => {
                     const card = CardMap.get(move.cardName);
                     if (card == null) {
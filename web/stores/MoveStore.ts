@@ -1,5 +1,5 @@
 import { PlayerTeam, TeamMap } from '~/types/PlayerTeam';
-import { PlayerMove } from '~/types/socket/SocketCommon';
+import { PlayerMove } from '~/types/socket/generated/Protocol';
 import { defineStore } from 'pinia';
 import { useCurrentMoveStore } from '~/stores/CurrentMoveStore';
 import { useGameBoardStore } from '~/stores/GameBoardStore';
@@ -1,5 +1,6 @@
 import { defineStore } from 'pinia';
-import { SocketMessageMap, SocketUser } from '~/types/socket/SocketEvent';
+import { RoomUser, SocketEvent } from '~/types/socket/generated/Protocol';
+import { EventDetail } from '~/types/socket/EventHelper';
 import { PlayerTeam, TeamMap } from '~/types/PlayerTeam';
 import { TURN_COUNT } from '~/data/Constants';
 
@@ -8,7 +9,7 @@ interface RoomStore {
     roomCode: string | null
     owner: string | null
     opponent: string | null
-    users: Record<string, SocketUser>
+    users: Record<string, RoomUser>
     started: boolean
     completed: boolean
     remainingTurns: number
@@ -44,14 +45,14 @@ export const useRoomStore = defineStore('room', {
                 return null;
             }
         },
-        ownerUser: (state): SocketUser | null => {
+        ownerUser: (state): RoomUser | null => {
             if (state.users == null || state.owner == null) {
                 return null;
             }
 
             return state.users[state.owner] ?? null;
         },
-        opponentUser: (state): SocketUser | null => {
+        opponentUser: (state): RoomUser | null => {
             if (state.users == null || state.opponent == null) {
                 return null;
             }
@@ -60,7 +61,7 @@ export const useRoomStore = defineStore('room', {
         }
     },
     actions: {
-        joinRoom(message: SocketMessageMap['Welcome']) {
+        joinRoom(message: EventDetail<SocketEvent, 'Welcome'>) {
             this.id = message.id;
             this.roomCode = message.roomCode;
             this.users = message.users;
@@ -68,7 +69,7 @@ export const useRoomStore = defineStore('room', {
             this.opponent = message.opponent;
             this.started = message.started;
         },
-        upsertUser(id: string, user: SocketUser) {
+        upsertUser(id: string, user: RoomUser) {
             this.users[id] = user;
         },
         removeUser(id: string) {
@@ -1,9 +1,10 @@
-// Takes a map of event names and parameters and creates a union type of them
-// For example: { A: string, B: number } becomes { event: 'A', detail: string } | { event: 'B', detail: number }
-export type AnyMessage<MessageMap> = {
-    [K in keyof MessageMap]: { event: K, detail: MessageMap[K] }
-}[keyof MessageMap];
+import { SocketAction } from '~/types/socket/generated/Protocol';
 
-export type AnyError<MessageMap> = {
-    [K in keyof MessageMap]: { code: K, detail: MessageMap[K] }
-}[keyof MessageMap];
+// Looks up the detail of one of the generated message unions by its name
+// For example: EventDetail<SocketEvent, 'Welcome'> is the detail of the 'Welcome' event
+export type EventDetail<Message extends { event: string }, K extends Message['event']> =
+    Extract<Message, { event: K }> extends { detail: infer D } ? D : never;
+
+// The arguments of an action, never for actions without any
+export type ActionArgs<K extends SocketAction['action']> =
+    Extract<SocketAction, { action: K }> extends { args: infer A } ? A : never;
@@ -0,0 +1,85 @@
+// Generated from the server's socket messages, do not edit.
+
+export const PROTOCOL_VERSION = 1;
+
+export type Bracket = { format: TournamentFormat, players: Array<string>, rounds: Array<Array<BracketMatch>>, };
+
+export type BracketMatch = { players: Array<string>, winner: string | null, roomCode: string | null, };
+
+export type CardRotation = 0 | 90 | 180 | 270;
+
+export type ChatMessage = { userId: string, username: string, message: string, sentAt: string, };
+
+export type Draft = { round: number, pack: Array<string>, picker: PlayerTeam, picks: { [key in PlayerTeam]?: Array<string> }, };
+
+export type DraftError = "DraftNotRunning" | "NotEnoughCards" | "NotDraftPicker" | "CardNotInPack";
+
+export type Emote = "Hello" | "GoodLuck" | "WellPlayed" | "GoodGame" | "Thanks" | "Oops" | "Wow" | "Rematch";
+
+export type GameError = { "code": "InvalidMove", "detail": InvalidMoveError } | { "code": "CardNotFound" } | { "code": "MapNotFound" } | { "code": "IncorrectDeckSize" } | { "code": "GameEnded" } | { "code": "RedrawNotPermitted" };
+
+export type GameProgress = { mapName: string, board: Array<Array<MapSquareType>>, score: { [key in PlayerTeam]: number }, remainingTurns: number, hand: Array<string> | null, };
+
+export type INamedPosition = { x: number, y: number, };
+
+export type InvalidMoveError = "CardNotFound" | "CardNotInHand" | "CannotAffordSpecial" | "CardOutOfBounds" | "CardOnDisallowedSquares" | "NoExpectedSquaresNearCard";
+
+export type MapBan = { team: PlayerTeam, map: string, };
+
+export type MapPool = { maps: Array<string>, weights: { [key in string]?: number }, };
+
+export type MapSquareType = 0 | 1 | 2 | 3 | 4 | 5 | 6;
+
+export type MapVeto = { maps: Array<string>, bans: Array<MapBan>, banner: PlayerTeam, loser: PlayerTeam | null, };
+
+export type MapVetoError = "VetoNotRunning" | "VetoInProgress" | "NotEnoughMaps" | "NotMapBanner" | "MapNotInVeto";
+
+export type PlayerMove = { "type": "PlaceCard", cardName: string, position: INamedPosition, rotation: CardRotation, special: boolean, } | { "type": "Pass", cardName: string, };
+
+export type PlayerTeam = "Alpha" | "Bravo";
+
+export type PuzzleError = { "code": "InvalidBoard" } | { "code": "InvalidTurnCount" } | { "code": "CardNotFound", "detail": string } | { "code": "NotEnoughCards" } | { "code": "WrongMoveCount" } | { "code": "CardNotPlayable", "detail": { turn: number, card_name: string, } } | { "code": "InvalidMove", "detail": { turn: number, error: GameError, } };
+
+export type PuzzleObjective = { "type": "FinishAhead", "squares": number } | { "type": "InkAtLeast", "squares": number };
+
+export type PuzzleOutcome = { solved: boolean, score: { [key in PlayerTeam]?: number }, };
+
+export type PuzzleState = { name: string, description: string, objective: PuzzleObjective, board: Array<Array<MapSquareType>>, score: { [key in PlayerTeam]?: number }, turn: number, turns: number, playableCards: Array<string>, specialPoints: number, lastMoves: { [key in PlayerTeam]?: PlayerMove } | null, outcome: PuzzleOutcome | null, };
+
+export type RoomEvent = { "event": "UserJoin", "detail": { id: string, user: RoomUser, } } | { "event": "UserUpdate", "detail": { id: string, user: RoomUser, } } | { "event": "UserLeave", "detail": string } | { "event": "OwnerChange", "detail": string } | { "event": "OpponentChange", "detail": string | null } | { "event": "MapChange", "detail": string } | { "event": "MapPoolChange", "detail": MapPool } | { "event": "StartGame", "detail": { score: { [key in PlayerTeam]: number }, mapName: string, } } | { "event": "MoveReceived", "detail": { team: PlayerTeam, remainingTurns: number, } } | { "event": "MovesApplied", "detail": { moves: { [key in PlayerTeam]: PlayerMove }, score: { [key in PlayerTeam]: number }, } } | { "event": "HandAssigned", "detail": Array<string> } | { "event": "NextCardDrawn", "detail": { newCard: string, replacing: string, } } | { "event": "EndGame", "detail": { score: { [key in PlayerTeam]: number }, } } | { "event": "ReturnToRoom" } | { "event": "RoomSettingsChange", "detail": RoomSettingsSummary } | { "event": "ChatMessage", "detail": ChatMessage } | { "event": "Emote", "detail": { userId: string, emote: Emote, } } | { "event": "SeriesChange", "detail": Series | null } | { "event": "SeriesEnd", "detail": { winner: string, wins: { [key in string]?: number }, } } | { "event": "TournamentUpdate", "detail": TournamentSummary } | { "event": "DraftPack", "detail": { round: number, cards: Array<string>, picker: PlayerTeam, } } | { "event": "DraftPick", "detail": { team: PlayerTeam, card: string, nextPicker: PlayerTeam | null, } } | { "event": "MapVetoChange", "detail": MapVeto | null } | { "event": "MapBan", "detail": { team: PlayerTeam, map: string, nextTeam: PlayerTeam | null, } } | { "event": "SandboxUpdate", "detail": SandboxState } | { "event": "PuzzleUpdate", "detail": PuzzleState } | { "event": "ServerRestarting", "detail": { secondsRemaining: number, } } | { "event": "Announcement", "detail": string };
+
+export type RoomSettings = { visibility: RoomVisibility, password: string | null, maxSpectators: number | null, rules: RuleVariant, };
+
+export type RoomSettingsSummary = { visibility: RoomVisibility, hasPassword: boolean, maxSpectators: number | null, rules: RuleVariant, };
+
+export type RoomUser = { username: string, joinedAt: string, deck: RoomUserDeck | null, muted: boolean, };
+
+export type RoomUserDeck = { id: string, cards: Array<string>, };
+
+export type RoomVisibility = "Public" | "Private";
+
+export type RuleVariant = "Standard" | "NoSpecialAttacks" | "SpecialsCoverAnything" | "SpecialsNextToAnySquare" | "NoDiagonalAdjacency";
+
+export type SandboxError = { "code": "SandboxNotRunning" } | { "code": "MissingMove" } | { "code": "SquareOutOfBounds" } | { "code": "NothingToUndo" } | { "code": "NothingToRedo" } | { "code": "GameError", "detail": GameError };
+
+export type SandboxState = { board: Array<Array<MapSquareType>>, score: { [key in PlayerTeam]?: number }, remainingTurns: number, specialPoints: { [key in PlayerTeam]?: number }, canUndo: boolean, canRedo: boolean, };
+
+export type Series = { bestOf: number, mapSelection: SeriesMapSelection, wins: { [key in string]?: number }, games: Array<SeriesGame>, };
+
+export type SeriesGame = { map: string, winner: string | null, loser: string | null, };
+
+export type SeriesMapSelection = "Rotation" | "LoserPick";
+
+export type SeriesSettings = { bestOf: number, mapSelection: SeriesMapSelection, };
+
+export type SocketAction = { "action": "SetMap", "args": string } | { "action": "StartGame" } | { "action": "RequestRedraw" } | { "action": "ProposeMove", "args": PlayerMove } | { "action": "SetDeck", "args": { id: string, cards: Array<string>, } } | { "action": "ReturnToRoom" } | { "action": "SetSeries", "args": SeriesSettings | null } | { "action": "PickMap", "args": string } | { "action": "SetRoomSettings", "args": RoomSettings } | { "action": "Chat", "args": string } | { "action": "Emote", "args": Emote } | { "action": "SetMuted", "args": { id: string, muted: boolean, } } | { "action": "KickUser", "args": string } | { "action": "TransferOwnership", "args": string } | { "action": "ChooseOpponent", "args": string | null } | { "action": "StartDraft" } | { "action": "DraftPick", "args": string } | { "action": "StartMapVeto" } | { "action": "BanMap", "args": string } | { "action": "SetMapPool", "args": MapPool } | { "action": "StartSandbox" } | { "action": "SandboxTurn", "args": { [key in PlayerTeam]?: PlayerMove } } | { "action": "SandboxSetSquare", "args": { position: INamedPosition, square: MapSquareType, } } | { "action": "SandboxUndo" } | { "action": "SandboxRedo" } | { "action": "StartPuzzle", "args": string } | { "action": "PuzzleMove", "args": PlayerMove } | { "action": "Ping" };
+
+export type SocketError = { "code": "MessageParsingFailed" } | { "code": "UserNotRoomOwner" } | { "code": "UserNotPlaying" } | { "code": "RoomNotFound", "detail": string } | { "code": "MissingOpponent" } | { "code": "RoomStarted" } | { "code": "RoomNotStarted" } | { "code": "DecksNotChosen" } | { "code": "InvalidSeriesLength" } | { "code": "SeriesInProgress" } | { "code": "NotMapPicker" } | { "code": "RoomReserved" } | { "code": "InvalidRoomSettings" } | { "code": "InvalidMapPool" } | { "code": "InvalidChatMessage" } | { "code": "ChatRateLimited" } | { "code": "UserMuted" } | { "code": "UserNotFound" } | { "code": "UserCannotPlay" } | { "code": "CannotTargetSelf" } | { "code": "RateLimited" } | { "code": "DraftError", "detail": DraftError } | { "code": "MapVetoError", "detail": MapVetoError } | { "code": "SandboxError", "detail": SandboxError } | { "code": "PuzzleNotFound", "detail": string } | { "code": "PuzzleNotRunning" } | { "code": "PuzzleError", "detail": PuzzleError } | { "code": "GameError", "detail": GameError };
+
+export type SocketEvent = { "event": "Welcome", "detail": { id: string, roomCode: string, reconnectToken: string, users: Record<string, RoomUser>, owner: string, opponent: string | null, map: string, mapPool: MapPool, started: boolean, series: Series | null, settings: RoomSettingsSummary, chat: Array<ChatMessage>, game: GameProgress | null, draft: Draft | null, mapVeto: MapVeto | null, sandbox: SandboxState | null, puzzle: PuzzleState | null, } } | { "event": "Error", "detail": SocketError } | { "event": "RoomEvent", "detail": RoomEvent } | { "event": "Pong" };
+
+export type Standing = { player: string, wins: number, buchholz: number, };
+
+export type TournamentFormat = { "type": "SingleElimination" } | { "type": "Swiss", rounds: number, };
+
+export type TournamentSummary = { id: string, name: string, createdAt: string, started: boolean, completed: boolean, winner: string | null, bracket: Bracket, standings: Array<Standing>, };